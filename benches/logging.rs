@@ -0,0 +1,99 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use log::Log;
+use loggers::{AsyncLogger, CustomLogger, FileSink};
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+const DIR: &str = "tests/output/bench";
+
+/// the system allocator, counting allocations
+struct Counting;
+
+static ALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+/// print the average number of allocations it takes `logger` to write a record
+fn report_allocations(name: &str, logger: &dyn Log) {
+    const RECORDS: u64 = 1000;
+    log_one(logger, "bench");
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    for _ in 0..RECORDS {
+        log_one(logger, "bench");
+    }
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - before;
+    println!(
+        "{}: {:.1} allocations per record",
+        name,
+        allocations as f64 / RECORDS as f64
+    );
+}
+
+fn log_one(logger: &dyn Log, target: &str) {
+    let kvs = [("user", "ferris"), ("request_id", "7f3a")];
+    logger.log(
+        &log::Record::builder()
+            .target(target)
+            .level(log::Level::Info)
+            .key_values(&kvs)
+            .args(format_args!("handled request in {}ms", 42))
+            .build(),
+    );
+}
+
+fn path(name: &str) -> String {
+    let path = format!("{}/{}.log", DIR, name);
+    let _ = std::fs::remove_file(&path);
+    path
+}
+
+fn bench_logging(c: &mut Criterion) {
+    std::fs::create_dir_all(DIR).unwrap();
+
+    let file_json = CustomLogger::new("bench", &path("file_json")).console(false);
+    report_allocations("file_json", &file_json);
+    c.bench_function("file_json", |b| b.iter(|| log_one(&file_json, "bench")));
+
+    let reuse_buffers = CustomLogger::new("bench", &path("reuse_buffers"))
+        .reuse_buffers(true)
+        .console(false);
+    report_allocations("reuse_buffers", &reuse_buffers);
+    c.bench_function("reuse_buffers", |b| {
+        b.iter(|| log_one(&reuse_buffers, "bench"))
+    });
+
+    let file_console = CustomLogger::new("bench", &path("file_console"));
+    c.bench_function("file_console", |b| {
+        b.iter(|| log_one(&file_console, "bench"))
+    });
+
+    let filtered = CustomLogger::new("bench", &path("filtered")).console(false);
+    c.bench_function("filtered_out", |b| b.iter(|| log_one(&filtered, "other")));
+
+    let sink = FileSink::new(&path("async")).unwrap();
+    let async_logger = AsyncLogger::new("bench", Box::new(sink));
+    c.bench_function("async", |b| b.iter(|| log_one(&async_logger, "bench")));
+    async_logger.flush();
+}
+
+criterion_group!(benches, bench_logging);
+criterion_main!(benches);
@@ -0,0 +1,79 @@
+use crate::LogEntry;
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+struct Pending {
+    entry: LogEntry,
+    started: Instant,
+    count: u64,
+    last_seen: String,
+}
+
+impl Pending {
+    fn new(entry: LogEntry) -> Pending {
+        Pending {
+            last_seen: entry.timestamp.clone(),
+            entry,
+            started: Instant::now(),
+            count: 1,
+        }
+    }
+
+    /// the entry to write; repeated records carry `count`, `first_seen` and `last_seen`
+    fn finish(self) -> LogEntry {
+        let mut entry = self.entry;
+        if self.count > 1 {
+            entry.fields.insert("count".to_string(), self.count.into());
+            entry
+                .fields
+                .insert("first_seen".to_string(), entry.timestamp.clone().into());
+            entry
+                .fields
+                .insert("last_seen".to_string(), self.last_seen.into());
+        }
+        entry
+    }
+}
+
+/// Collapses bursts of records that differ only in their timestamp
+pub(crate) struct Aggregator {
+    window: Duration,
+    pending: Mutex<Option<Pending>>,
+}
+
+impl Aggregator {
+    pub(crate) fn new(window: Duration) -> Aggregator {
+        Aggregator {
+            window,
+            pending: Mutex::new(None),
+        }
+    }
+
+    /// hold back `entry`, returning the previously held one if it is now complete
+    pub(crate) fn push(&self, entry: LogEntry) -> Option<LogEntry> {
+        let mut pending = self.pending.lock().unwrap();
+        if let Some(p) = pending.as_mut() {
+            if p.started.elapsed() < self.window && same_record(&p.entry, &entry) {
+                p.count += 1;
+                p.last_seen = entry.timestamp;
+                return None;
+            }
+        }
+        pending.replace(Pending::new(entry)).map(Pending::finish)
+    }
+
+    /// release the held entry, if any
+    pub(crate) fn take(&self) -> Option<LogEntry> {
+        self.pending.lock().unwrap().take().map(Pending::finish)
+    }
+}
+
+fn same_record(a: &LogEntry, b: &LogEntry) -> bool {
+    a.severity == b.severity
+        && a.target == b.target
+        && a.message == b.message
+        && a.location == b.location
+        && a.fields == b.fields
+}
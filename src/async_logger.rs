@@ -0,0 +1,524 @@
+use crate::{LogEntry, Sink};
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Condvar, Mutex, Weak},
+    thread::JoinHandle,
+    time::{Duration, Instant},
+};
+
+/// default number of records an AsyncLogger queues before dropping new ones
+pub const DEFAULT_QUEUE_CAPACITY: usize = 8192;
+
+/// every worker started, so [`crate::shutdown`] can stop them
+static WORKERS: Mutex<Vec<Weak<Shared>>> = Mutex::new(Vec::new());
+
+/// Which record an AsyncLogger drops when its queue is full
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DropPolicy {
+    /// drop the new record
+    #[default]
+    Newest,
+    /// make room by dropping the oldest of the least severe queued records,
+    /// if it is less severe than the new one, so Errors outlast Traces;
+    /// otherwise drop the new record
+    LeastSevere,
+}
+
+struct State {
+    queue: VecDeque<(log::Level, LogEntry)>,
+    busy: bool,
+    shutdown: bool,
+    dropped: u64,
+    flush_interval: Option<Duration>,
+}
+
+struct Shared {
+    state: Mutex<State>,
+    ready: Condvar,
+    idle: Condvar,
+    sink: Box<dyn Sink>,
+    worker: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl Shared {
+    fn write(&self, entry: &LogEntry) {
+        let line = serde_json::to_string(entry).unwrap();
+        if let Err(e) = self.sink.write_line(&line) {
+            eprintln!("AsyncLogger {}: cannot write record: {}", entry.target, e);
+        }
+    }
+
+    fn run(&self) {
+        let mut state = self.state.lock().unwrap();
+        // whether records were written since the sink was last flushed
+        let mut unflushed = false;
+        let mut last_flush = Instant::now();
+        loop {
+            while state.queue.is_empty() && !state.shutdown {
+                match state.flush_interval.filter(|_| unflushed) {
+                    Some(interval) if last_flush.elapsed() >= interval => {
+                        drop(state);
+                        self.flush_sink();
+                        unflushed = false;
+                        last_flush = Instant::now();
+                        state = self.state.lock().unwrap();
+                    }
+                    Some(interval) => {
+                        let wait = interval.saturating_sub(last_flush.elapsed());
+                        state = self.ready.wait_timeout(state, wait).unwrap().0;
+                    }
+                    None => state = self.ready.wait(state).unwrap(),
+                }
+            }
+            if state.queue.is_empty() {
+                break;
+            }
+            let batch: Vec<LogEntry> = state.queue.drain(..).map(|(_, entry)| entry).collect();
+            let flush_interval = state.flush_interval;
+            state.busy = true;
+            drop(state);
+            for entry in &batch {
+                self.write(entry);
+            }
+            unflushed = true;
+            if flush_interval.is_some_and(|interval| last_flush.elapsed() >= interval) {
+                self.flush_sink();
+                unflushed = false;
+                last_flush = Instant::now();
+            }
+            state = self.state.lock().unwrap();
+            state.busy = false;
+            self.idle.notify_all();
+        }
+        self.idle.notify_all();
+    }
+
+    fn flush_sink(&self) {
+        if let Err(e) = self.sink.flush() {
+            eprintln!("AsyncLogger: cannot flush sink: {}", e);
+        }
+    }
+
+    /// block until every queued record has been handed to the sink
+    fn wait_idle(&self) {
+        let mut state = self.state.lock().unwrap();
+        while (!state.queue.is_empty() || state.busy) && !state.shutdown {
+            state = self.idle.wait(state).unwrap();
+        }
+    }
+
+    /// drain the queue, then stop and join the worker
+    fn stop(&self) {
+        self.state.lock().unwrap().shutdown = true;
+        self.ready.notify_all();
+        if let Some(worker) = self.worker.lock().unwrap().take() {
+            let _ = worker.join();
+        }
+        let _ = self.sink.flush();
+    }
+}
+
+/// A logger that writes records to a sink from a background thread
+///
+/// Records are rendered, including their timestamp, on the calling thread
+/// and queued; the worker thread serializes and writes them. When the queue
+/// holds `capacity` records new ones are dropped and counted. After
+/// [`crate::shutdown`] records are written on the calling thread.
+pub struct AsyncLogger {
+    target: String,
+    capacity: usize,
+    drop_policy: DropPolicy,
+    shared: Arc<Shared>,
+}
+
+impl AsyncLogger {
+    /// create an AsyncLogger and start its worker thread
+    /// # Arguments
+    /// * `target` - The target to match
+    /// * `sink` - Where the worker writes records
+    /// # Example
+    /// ```
+    /// # use crate::loggers::*;
+    /// let logger = AsyncLogger::new("test", Box::new(FileSink::new("system.log").unwrap()));
+    /// ```
+    pub fn new(target: &str, sink: Box<dyn Sink>) -> AsyncLogger {
+        let shared = Arc::new(Shared {
+            state: Mutex::new(State {
+                queue: VecDeque::new(),
+                busy: false,
+                shutdown: false,
+                dropped: 0,
+                flush_interval: None,
+            }),
+            ready: Condvar::new(),
+            idle: Condvar::new(),
+            sink,
+            worker: Mutex::new(None),
+        });
+        let worker = {
+            let shared = shared.clone();
+            std::thread::Builder::new()
+                .name(format!("loggers-{}", target))
+                .spawn(move || shared.run())
+                .expect("Failed to spawn logger thread")
+        };
+        *shared.worker.lock().unwrap() = Some(worker);
+
+        let mut workers = WORKERS.lock().unwrap();
+        workers.retain(|w| w.strong_count() > 0);
+        workers.push(Arc::downgrade(&shared));
+
+        AsyncLogger {
+            target: target.to_string(),
+            capacity: DEFAULT_QUEUE_CAPACITY,
+            drop_policy: DropPolicy::Newest,
+            shared,
+        }
+    }
+
+    /// set the number of records queued before new ones are dropped
+    pub fn capacity(mut self, capacity: usize) -> AsyncLogger {
+        self.capacity = capacity;
+        self
+    }
+
+    /// set which record is dropped when the queue is full (default:
+    /// [`DropPolicy::Newest`])
+    /// # Example
+    /// ```
+    /// # use crate::loggers::*;
+    /// let logger = AsyncLogger::new("test", Box::new(FileSink::new("system.log").unwrap()))
+    ///     .drop_policy(DropPolicy::LeastSevere);
+    /// ```
+    pub fn drop_policy(mut self, drop_policy: DropPolicy) -> AsyncLogger {
+        self.drop_policy = drop_policy;
+        self
+    }
+
+    /// let the worker flush the sink every `interval` while it has written
+    /// records that were not flushed yet, so a buffering sink gets them to
+    /// disk without [`flush`](log::Log::flush) being called
+    ///
+    /// The worker flushes between batches and while it waits for records;
+    /// it stops together with the logger, after a final flush.
+    /// # Example
+    /// ```
+    /// # use crate::loggers::*;
+    /// use std::time::Duration;
+    ///
+    /// let logger = AsyncLogger::new("test", Box::new(FileSink::new("system.log").unwrap()))
+    ///     .flush_interval(Duration::from_secs(1));
+    /// ```
+    pub fn flush_interval(self, interval: Duration) -> AsyncLogger {
+        self.shared.state.lock().unwrap().flush_interval = Some(interval);
+        self.shared.ready.notify_all();
+        self
+    }
+
+    /// a snapshot of the records queued but not yet taken by the worker,
+    /// oldest first
+    ///
+    /// Records the worker is writing at the moment are no longer queued and
+    /// are not included.
+    pub fn pending(&self) -> Vec<LogEntry> {
+        self.shared
+            .state
+            .lock()
+            .unwrap()
+            .queue
+            .iter()
+            .map(|(_, entry)| entry.clone())
+            .collect()
+    }
+
+    /// the number of records dropped because the queue was full
+    pub fn dropped(&self) -> u64 {
+        self.shared.state.lock().unwrap().dropped
+    }
+}
+
+/// stop every AsyncLogger worker, writing out what they have queued
+pub(crate) fn shutdown_workers() {
+    let workers: Vec<Weak<Shared>> = WORKERS.lock().unwrap().drain(..).collect();
+    for shared in workers.iter().filter_map(Weak::upgrade) {
+        shared.stop();
+    }
+}
+
+impl log::Log for AsyncLogger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        if crate::inspect::is_hook(metadata) {
+            let name = format!("AsyncLogger {}", self.target);
+            crate::inspect::report_sink(&name, self.shared.sink.as_ref());
+            return false;
+        }
+        metadata.target() == self.target
+    }
+
+    fn log(&self, record: &log::Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        let timestamp = crate::clock::timestamp();
+        let entry = LogEntry::from_record(record, &self.target, &timestamp);
+        let mut state = self.shared.state.lock().unwrap();
+        if state.shutdown {
+            drop(state);
+            self.shared.write(&entry);
+            return;
+        }
+        if state.queue.len() >= self.capacity {
+            state.dropped += 1;
+            let victim = match self.drop_policy {
+                DropPolicy::Newest => None,
+                DropPolicy::LeastSevere => state
+                    .queue
+                    .iter()
+                    .enumerate()
+                    // the oldest of the least severe, as max_by_key keeps the last maximum
+                    .rev()
+                    .max_by_key(|(_, (level, _))| *level)
+                    .filter(|(_, (level, _))| *level > record.level())
+                    .map(|(i, _)| i),
+            };
+            let Some(victim) = victim else {
+                return;
+            };
+            state.queue.remove(victim);
+        }
+        state.queue.push_back((record.level(), entry));
+        self.shared.ready.notify_one();
+    }
+
+    fn flush(&self) {
+        self.shared.wait_idle();
+        let _ = self.shared.sink.flush();
+    }
+}
+
+impl Drop for AsyncLogger {
+    fn drop(&mut self) {
+        self.shared.stop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MemorySink;
+    use log::Log;
+
+    /// keeps lines in `pending` until it is flushed
+    struct BufferedSink {
+        pending: Mutex<Vec<String>>,
+        flushed: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl Sink for BufferedSink {
+        fn write_line(&self, line: &str) -> std::io::Result<()> {
+            self.pending.lock().unwrap().push(line.to_string());
+            Ok(())
+        }
+
+        fn flush(&self) -> std::io::Result<()> {
+            let mut pending = self.pending.lock().unwrap();
+            self.flushed.lock().unwrap().append(&mut pending);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_async_flush() {
+        let memory = MemorySink::new();
+        let logger = AsyncLogger::new("async", Box::new(memory.clone()));
+        for i in 0..100 {
+            logger.log(
+                &log::Record::builder()
+                    .target("async")
+                    .level(log::Level::Info)
+                    .args(format_args!("record {}", i))
+                    .build(),
+            );
+        }
+        logger.flush();
+
+        let lines = memory.lines();
+        assert_eq!(lines.len(), 100);
+        let last: LogEntry = serde_json::from_str(&lines[99]).unwrap();
+        assert_eq!(last.message, "record 99");
+        assert_eq!(logger.dropped(), 0);
+    }
+
+    #[test]
+    fn test_flush_interval() {
+        let flushed = Arc::new(Mutex::new(Vec::new()));
+        let sink = BufferedSink {
+            pending: Mutex::new(Vec::new()),
+            flushed: flushed.clone(),
+        };
+        let logger = AsyncLogger::new("async_interval", Box::new(sink))
+            .flush_interval(Duration::from_millis(20));
+        logger.log(
+            &log::Record::builder()
+                .target("async_interval")
+                .level(log::Level::Info)
+                .args(format_args!("buffered"))
+                .build(),
+        );
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while flushed.lock().unwrap().is_empty() && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        let lines = flushed.lock().unwrap().clone();
+        assert_eq!(lines.len(), 1);
+        let entry: LogEntry = serde_json::from_str(&lines[0]).unwrap();
+        assert_eq!(entry.message, "buffered");
+    }
+
+    /// blocks in `write_line` while the test holds `gate`
+    struct GatedSink {
+        gate: Arc<Mutex<()>>,
+        started: Mutex<std::sync::mpsc::Sender<()>>,
+        memory: MemorySink,
+    }
+
+    impl Sink for GatedSink {
+        fn write_line(&self, line: &str) -> std::io::Result<()> {
+            let _ = self.started.lock().unwrap().send(());
+            let _gate = self.gate.lock().unwrap();
+            self.memory.write_line(line)
+        }
+    }
+
+    #[test]
+    fn test_pending() {
+        let gate = Arc::new(Mutex::new(()));
+        let (started, writing) = std::sync::mpsc::channel();
+        let memory = MemorySink::new();
+        let sink = GatedSink {
+            gate: gate.clone(),
+            started: Mutex::new(started),
+            memory: memory.clone(),
+        };
+        let logger = AsyncLogger::new("async_pending", Box::new(sink));
+        let log = |message: &str| {
+            logger.log(
+                &log::Record::builder()
+                    .target("async_pending")
+                    .level(log::Level::Info)
+                    .args(format_args!("{}", message))
+                    .build(),
+            )
+        };
+
+        let paused = gate.lock().unwrap();
+        log("first");
+        // the worker holds "first" and waits for the gate
+        writing.recv().unwrap();
+        log("second");
+        log("third");
+        let queued: Vec<String> = logger.pending().into_iter().map(|e| e.message).collect();
+        assert_eq!(queued, vec!["second", "third"]);
+
+        drop(paused);
+        logger.flush();
+        assert!(logger.pending().is_empty());
+        assert_eq!(memory.lines().len(), 3);
+    }
+
+    #[test]
+    fn test_enqueue_timestamp() {
+        let gate = Arc::new(Mutex::new(()));
+        let (started, writing) = std::sync::mpsc::channel();
+        let memory = MemorySink::new();
+        let sink = GatedSink {
+            gate: gate.clone(),
+            started: Mutex::new(started),
+            memory: memory.clone(),
+        };
+        let logger = AsyncLogger::new("async_enqueue", Box::new(sink));
+        let log = |message: &str| {
+            logger.log(
+                &log::Record::builder()
+                    .target("async_enqueue")
+                    .level(log::Level::Info)
+                    .args(format_args!("{}", message))
+                    .build(),
+            )
+        };
+
+        let paused = gate.lock().unwrap();
+        log("first");
+        writing.recv().unwrap();
+        let before = chrono::Local::now();
+        log("delayed");
+        let enqueued = chrono::Local::now();
+        std::thread::sleep(Duration::from_millis(200));
+        drop(paused);
+        logger.flush();
+
+        let lines = memory.lines();
+        let delayed: LogEntry = serde_json::from_str(&lines[1]).unwrap();
+        assert_eq!(delayed.message, "delayed");
+        let timestamp = chrono::DateTime::parse_from_rfc3339(&delayed.timestamp).unwrap();
+        // timestamps have millisecond precision
+        assert!(timestamp >= before - chrono::Duration::milliseconds(1));
+        assert!(timestamp <= enqueued);
+    }
+
+    #[test]
+    fn test_drop_least_severe() {
+        let gate = Arc::new(Mutex::new(()));
+        let (started, writing) = std::sync::mpsc::channel();
+        let memory = MemorySink::new();
+        let sink = GatedSink {
+            gate: gate.clone(),
+            started: Mutex::new(started),
+            memory: memory.clone(),
+        };
+        let logger = AsyncLogger::new("async_drop", Box::new(sink))
+            .capacity(4)
+            .drop_policy(DropPolicy::LeastSevere);
+        let log = |level, message: &str| {
+            logger.log(
+                &log::Record::builder()
+                    .target("async_drop")
+                    .level(level)
+                    .args(format_args!("{}", message))
+                    .build(),
+            )
+        };
+
+        let paused = gate.lock().unwrap();
+        log(log::Level::Info, "stalled");
+        writing.recv().unwrap();
+        for i in 0..4 {
+            log(log::Level::Trace, &format!("trace {}", i));
+        }
+        log(log::Level::Error, "error 0");
+        log(log::Level::Debug, "debug 0");
+        log(log::Level::Error, "error 1");
+        log(log::Level::Trace, "trace 4");
+        log(log::Level::Error, "error 2");
+        log(log::Level::Error, "error 3");
+        log(log::Level::Error, "error 4");
+
+        let queued: Vec<String> = logger.pending().into_iter().map(|e| e.message).collect();
+        assert_eq!(queued, vec!["error 0", "error 1", "error 2", "error 3"]);
+        assert_eq!(logger.dropped(), 7);
+
+        drop(paused);
+        logger.flush();
+        let written: Vec<String> = memory
+            .lines()
+            .iter()
+            .map(|line| serde_json::from_str::<LogEntry>(line).unwrap().message)
+            .collect();
+        assert_eq!(
+            written,
+            vec!["stalled", "error 0", "error 1", "error 2", "error 3"]
+        );
+    }
+}
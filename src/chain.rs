@@ -0,0 +1,210 @@
+use crate::{reader::strip_bom, Error};
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
+use std::{
+    fs::File,
+    io::{BufRead, BufReader},
+    path::Path,
+};
+
+/// the `prev_hash` of the first record of a new chain
+pub const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// the hash of a record, which must already carry its `prev_hash` field
+///
+/// The record is canonicalized as compact JSON with keys in alphabetical
+/// order and without its `hash` field.
+pub(crate) fn hash_record(record: &Map<String, Value>) -> String {
+    let mut record = record.clone();
+    record.remove("hash");
+    hash_canonical(&Value::Object(record))
+}
+
+fn hash_canonical(value: &Value) -> String {
+    let mut canonical = String::new();
+    write_canonical(value, &mut canonical);
+    Sha256::digest(canonical.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// compact JSON with object keys in alphabetical order
+fn write_canonical(value: &Value, out: &mut String) {
+    match value {
+        Value::Object(object) => {
+            let mut keys: Vec<&String> = object.keys().collect();
+            keys.sort();
+            out.push('{');
+            for (i, key) in keys.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                out.push_str(&serde_json::to_string(key).unwrap());
+                out.push(':');
+                write_canonical(&object[key], out);
+            }
+            out.push('}');
+        }
+        Value::Array(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_canonical(item, out);
+            }
+            out.push(']');
+        }
+        other => out.push_str(&other.to_string()),
+    }
+}
+
+/// add `prev_hash` and `hash` fields to `record`, returning the new hash
+pub(crate) fn link(record: &mut Map<String, Value>, prev_hash: &str) -> String {
+    record.insert("prev_hash".to_string(), prev_hash.into());
+    let hash = hash_record(record);
+    record.insert("hash".to_string(), hash.clone().into());
+    hash
+}
+
+/// the hash of the last record in `path`, to continue its chain
+pub(crate) fn last_hash(path: &Path) -> Option<String> {
+    let file = File::open(path).ok()?;
+    let last = BufReader::new(file)
+        .lines()
+        .map_while(Result::ok)
+        .filter(|line| !line.trim().is_empty())
+        .last()?;
+    let value: Value = serde_json::from_str(strip_bom(&last)).ok()?;
+    value["hash"].as_str().map(str::to_string)
+}
+
+/// check the hash chain of a file written with [`crate::CustomLogger::hash_chain`]
+///
+/// Every record's `hash` must match its contents and its `prev_hash` must be
+/// the `hash` of the record before it; the first record's must be
+/// [`GENESIS_HASH`], so removing leading lines is detected. Returns the
+/// `hash` of the last record, or [`Error::Tampered`] with the first line that
+/// does not verify.
+///
+/// A segment started by rotation continues the chain of the one before it;
+/// check it with [`verify_chain_from`] and the hash returned for that one.
+/// Records cut off the end of the newest file leave a valid chain, so keep
+/// the returned hash elsewhere to detect that.
+/// # Example
+/// ```no_run
+/// # use crate::loggers::*;
+/// let last = verify_chain("audit.log.1").unwrap();
+/// verify_chain_from("audit.log", &last).unwrap();
+/// ```
+pub fn verify_chain<P: AsRef<Path>>(path: P) -> Result<String, Error> {
+    verify_chain_from(path, GENESIS_HASH)
+}
+
+/// check the hash chain of a file whose first record's `prev_hash` must be
+/// `prev_hash`, such as a rotated segment; see [`verify_chain`]
+pub fn verify_chain_from<P: AsRef<Path>>(path: P, prev_hash: &str) -> Result<String, Error> {
+    let file = File::open(path)?;
+    let mut prev = prev_hash.to_string();
+    for (i, line) in BufReader::new(file).lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let tampered = Error::Tampered { line: i + 1 };
+        let mut value: Value =
+            serde_json::from_str(strip_bom(&line)).map_err(|source| Error::Parse {
+                line: i + 1,
+                source,
+            })?;
+        let Some(object) = value.as_object_mut() else {
+            return Err(tampered);
+        };
+        let (Some(hash), Some(prev_hash)) = (
+            object
+                .remove("hash")
+                .and_then(|h| h.as_str().map(str::to_string)),
+            object.get("prev_hash").and_then(|h| h.as_str()),
+        ) else {
+            return Err(tampered);
+        };
+        if prev != prev_hash || hash_canonical(&value) != hash {
+            return Err(tampered);
+        }
+        prev = hash;
+    }
+    Ok(prev)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CustomLogger;
+    use log::Log;
+
+    #[test]
+    fn test_hash_chain() {
+        let path = "tests/output/hash_chain.log";
+        let logger = CustomLogger::new("hash_chain", path).hash_chain(true);
+        for i in 0..4 {
+            logger.log(
+                &log::Record::builder()
+                    .target("hash_chain")
+                    .level(log::Level::Info)
+                    .args(format_args!("record {}", i))
+                    .build(),
+            );
+        }
+        verify_chain(path).unwrap();
+
+        let contents = std::fs::read_to_string(path).unwrap();
+        let first: Value = serde_json::from_str(contents.lines().next().unwrap()).unwrap();
+        assert_eq!(first["prev_hash"], GENESIS_HASH);
+
+        // a restarted logger continues the chain
+        drop(logger);
+        let logger = CustomLogger::append("hash_chain", path)
+            .unwrap()
+            .hash_chain(true);
+        logger.log(
+            &log::Record::builder()
+                .target("hash_chain")
+                .level(log::Level::Info)
+                .args(format_args!("record 4"))
+                .build(),
+        );
+        let last = verify_chain(path).unwrap();
+
+        let contents = std::fs::read_to_string(path).unwrap();
+        let value: Value = serde_json::from_str(contents.lines().last().unwrap()).unwrap();
+        assert_eq!(value["hash"], last.as_str());
+        std::fs::write(path, contents.replacen("record 2", "record X", 1)).unwrap();
+        assert!(matches!(
+            verify_chain(path),
+            Err(Error::Tampered { line: 3 })
+        ));
+
+        let mut lines: Vec<&str> = contents.lines().collect();
+        lines.remove(1);
+        std::fs::write(path, lines.join("\n")).unwrap();
+        assert!(matches!(
+            verify_chain(path),
+            Err(Error::Tampered { line: 2 })
+        ));
+
+        // dropping the first line is detected, unless the rest is checked as
+        // a segment continuing from it
+        let lines: Vec<&str> = contents.lines().collect();
+        std::fs::write(path, lines[1..].join("\n")).unwrap();
+        assert!(matches!(
+            verify_chain(path),
+            Err(Error::Tampered { line: 1 })
+        ));
+        let first: Value = serde_json::from_str(lines[0]).unwrap();
+        assert_eq!(
+            verify_chain_from(path, first["hash"].as_str().unwrap()).unwrap(),
+            last
+        );
+    }
+}
@@ -0,0 +1,342 @@
+use std::{
+    cell::RefCell,
+    fmt,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        OnceLock,
+    },
+    time::{Instant, SystemTime, UNIX_EPOCH},
+};
+
+static START: OnceLock<Instant> = OnceLock::new();
+static LAST: AtomicU64 = AtomicU64::new(0);
+
+thread_local! {
+    static SHARED: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// nanoseconds since the first call, strictly increasing across all threads
+///
+/// Unlike the wall clock this never goes backwards; calls closer together
+/// than the clock resolution get consecutive values.
+pub(crate) fn nanos_since_start() -> u64 {
+    let now = START.get_or_init(Instant::now).elapsed().as_nanos() as u64;
+    let mut last = LAST.load(Ordering::Relaxed);
+    loop {
+        let next = now.max(last + 1);
+        match LAST.compare_exchange_weak(last, next, Ordering::Relaxed, Ordering::Relaxed) {
+            Ok(_) => return next,
+            Err(current) => last = current,
+        }
+    }
+}
+
+/// the timestamp of a record logged now: the shared one inside
+/// [`with_shared_timestamp`], otherwise the current time
+pub(crate) fn timestamp() -> String {
+    SHARED
+        .with(|shared| shared.borrow().clone())
+        .unwrap_or_else(now)
+}
+
+/// run `f`, stamping every record logged on this thread while it runs with
+/// the same timestamp, so that a record broadcast to several loggers carries
+/// an identical timestamp in each of them. Nested calls keep the outer one.
+pub(crate) fn with_shared_timestamp<R>(f: impl FnOnce() -> R) -> R {
+    struct Clear;
+
+    impl Drop for Clear {
+        fn drop(&mut self) {
+            SHARED.with(|shared| *shared.borrow_mut() = None);
+        }
+    }
+
+    if SHARED.with(|shared| shared.borrow().is_some()) {
+        return f();
+    }
+    SHARED.with(|shared| *shared.borrow_mut() = Some(now()));
+    let _clear = Clear;
+    f()
+}
+
+/// the current time as RFC3339 with milliseconds, in local time
+#[cfg(feature = "chrono")]
+fn now() -> String {
+    chrono::Local::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true)
+}
+
+/// the current time as RFC3339 with milliseconds; without chrono the local
+/// offset is unknown, so it is in UTC
+#[cfg(not(feature = "chrono"))]
+fn now() -> String {
+    let micros = micros_since_epoch(SystemTime::now());
+    let (days, micros) = (micros.div_euclid(DAY_MICROS), micros.rem_euclid(DAY_MICROS));
+    let seconds = micros / 1_000_000;
+    format!(
+        "{}T{:02}:{:02}:{:02}.{:03}Z",
+        Date::from_days(days),
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60,
+        micros / 1000 % 1000
+    )
+}
+
+const DAY_MICROS: i64 = 86_400_000_000;
+
+/// `time` in UTC in the ISO 8601 basic format, `YYYYMMDDTHHMMSSZ`
+#[cfg(feature = "s3")]
+pub(crate) fn utc_basic(time: SystemTime) -> String {
+    let micros = micros_since_epoch(time);
+    let (days, micros) = (micros.div_euclid(DAY_MICROS), micros.rem_euclid(DAY_MICROS));
+    let date = Date::from_days(days);
+    let seconds = micros / 1_000_000;
+    format!(
+        "{:04}{:02}{:02}T{:02}{:02}{:02}Z",
+        date.year,
+        date.month,
+        date.day,
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+fn micros_since_epoch(time: SystemTime) -> i64 {
+    match time.duration_since(UNIX_EPOCH) {
+        Ok(d) => d.as_micros() as i64,
+        Err(e) => -(e.duration().as_micros() as i64),
+    }
+}
+
+/// A calendar date, ordered chronologically and displayed as `YYYY-MM-DD`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) struct Date {
+    year: i32,
+    month: u32,
+    day: u32,
+}
+
+impl Date {
+    /// parse a `YYYY-MM-DD` date
+    pub(crate) fn parse(text: &str) -> Option<Date> {
+        let bytes = text.as_bytes();
+        if bytes.len() != 10 || bytes[4] != b'-' || bytes[7] != b'-' {
+            return None;
+        }
+        let date = Date {
+            year: digits(&text[..4])? as i32,
+            month: digits(&text[5..7])?,
+            day: digits(&text[8..])?,
+        };
+        let valid = (1..=12).contains(&date.month)
+            && (1..=days_in_month(date.year, date.month)).contains(&date.day);
+        valid.then_some(date)
+    }
+
+    /// the date `days` days after 1970-01-01
+    fn from_days(days: i64) -> Date {
+        // Howard Hinnant's civil_from_days
+        let z = days + 719_468;
+        let era = z.div_euclid(146_097);
+        let doe = z.rem_euclid(146_097);
+        let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+        let year = (yoe + era * 400 + i64::from(month <= 2)) as i32;
+        Date { year, month, day }
+    }
+
+    /// the number of days from 1970-01-01 to this date
+    fn days(&self) -> i64 {
+        let year = i64::from(self.year) - i64::from(self.month <= 2);
+        let era = year.div_euclid(400);
+        let yoe = year.rem_euclid(400);
+        let month = i64::from(self.month);
+        let doy =
+            (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + i64::from(self.day) - 1;
+        let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+        era * 146_097 + doe - 719_468
+    }
+
+    /// today's date, in local time with the `chrono` feature and in UTC without
+    pub(crate) fn today() -> Date {
+        Date::of(SystemTime::now())
+    }
+
+    /// the date of `time`, in local time with the `chrono` feature and in UTC without
+    pub(crate) fn of(time: SystemTime) -> Date {
+        #[cfg(feature = "chrono")]
+        let micros = {
+            let offset = chrono::DateTime::<chrono::Local>::from(time)
+                .offset()
+                .local_minus_utc();
+            micros_since_epoch(time) + i64::from(offset) * 1_000_000
+        };
+        #[cfg(not(feature = "chrono"))]
+        let micros = micros_since_epoch(time);
+        Date::from_days(micros.div_euclid(DAY_MICROS))
+    }
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+fn digits(text: &str) -> Option<u32> {
+    if text.is_empty() || !text.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    text.parse().ok()
+}
+
+fn days_in_month(year: i32, month: u32) -> u32 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// An RFC3339 timestamp such as `2024-06-01T12:30:00.250+09:00`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Timestamp {
+    /// the date at the timestamp's own offset
+    pub(crate) date: Date,
+    /// microseconds since midnight at the timestamp's own offset
+    time: i64,
+    offset_seconds: i64,
+}
+
+impl Timestamp {
+    /// parse an RFC3339 timestamp, with or without fractional seconds
+    pub(crate) fn parse(text: &str) -> Option<Timestamp> {
+        let date = Date::parse(text.get(..10)?)?;
+        let rest = text.get(10..)?;
+        let rest = rest.strip_prefix(['T', 't', ' '])?;
+        let (hour, minute, second) = (
+            digits(rest.get(..2)?)?,
+            digits(rest.get(3..5)?)?,
+            digits(rest.get(6..8)?)?,
+        );
+        if &rest[2..3] != ":" || &rest[5..6] != ":" || hour > 23 || minute > 59 || second > 59 {
+            return None;
+        }
+        let mut rest = &rest[8..];
+        let mut micros = 0;
+        if let Some(fraction) = rest.strip_prefix('.') {
+            let len = fraction.bytes().take_while(u8::is_ascii_digit).count();
+            if len == 0 {
+                return None;
+            }
+            let scale = format!("{:0<6}", &fraction[..len.min(6)]);
+            micros = digits(&scale)?;
+            rest = &fraction[len..];
+        }
+        let offset_seconds = match rest {
+            "Z" | "z" => 0,
+            _ => {
+                let sign = match rest.get(..1)? {
+                    "+" => 1,
+                    "-" => -1,
+                    _ => return None,
+                };
+                if rest.len() != 6 || rest.get(3..4)? != ":" {
+                    return None;
+                }
+                let (hours, minutes) = (digits(rest.get(1..3)?)?, digits(rest.get(4..6)?)?);
+                if hours > 23 || minutes > 59 {
+                    return None;
+                }
+                sign * i64::from(hours * 3600 + minutes * 60)
+            }
+        };
+        Some(Timestamp {
+            date,
+            time: i64::from(hour * 3600 + minute * 60 + second) * 1_000_000 + i64::from(micros),
+            offset_seconds,
+        })
+    }
+
+    /// microseconds since the Unix epoch
+    pub(crate) fn epoch_micros(&self) -> i64 {
+        self.date.days() * DAY_MICROS + self.time - self.offset_seconds * 1_000_000
+    }
+
+    /// milliseconds since the Unix epoch
+    pub(crate) fn epoch_millis(&self) -> i64 {
+        self.epoch_micros().div_euclid(1000)
+    }
+
+    /// the time of day at the timestamp's own offset, as `HH:MM:SS.mmm`
+    pub(crate) fn time_of_day(&self) -> String {
+        let seconds = self.time / 1_000_000;
+        format!(
+            "{:02}:{:02}:{:02}.{:03}",
+            seconds / 3600,
+            seconds / 60 % 60,
+            seconds % 60,
+            self.time / 1000 % 1000
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timestamp() {
+        let text = timestamp();
+        let parsed = chrono::DateTime::parse_from_rfc3339(&text).unwrap();
+        // milliseconds, in every configuration
+        assert_eq!(&text[19..20], ".");
+        assert!(matches!(&text[23..24], "Z" | "+" | "-"));
+        #[cfg(not(feature = "chrono"))]
+        assert!(text.ends_with('Z'));
+        let ours = Timestamp::parse(&text).unwrap();
+        assert_eq!(ours.epoch_micros(), parsed.timestamp_micros());
+        let now = chrono::Utc::now().timestamp_micros();
+        assert!((now - ours.epoch_micros()).abs() < 10_000_000);
+    }
+
+    #[test]
+    fn test_parse() {
+        for text in [
+            "2024-06-01T12:30:00.250+09:00",
+            "2024-02-29T23:59:59Z",
+            "1969-12-31T23:59:59.999999-05:30",
+            "2000-03-01 00:00:00.1z",
+        ] {
+            let ours = Timestamp::parse(text).unwrap();
+            let theirs = chrono::DateTime::parse_from_rfc3339(text).unwrap();
+            assert_eq!(ours.epoch_micros(), theirs.timestamp_micros(), "{}", text);
+            assert_eq!(ours.date.to_string(), theirs.format("%Y-%m-%d").to_string());
+            assert_eq!(
+                ours.time_of_day(),
+                theirs.format("%H:%M:%S%.3f").to_string()
+            );
+        }
+        for text in [
+            "2023-02-29T00:00:00Z",
+            "2024-06-01T12:30:00",
+            "2024-06-01T24:00:00Z",
+            "2024-06-01",
+            "2024-06-01T12:30:00+0é00",
+            "2024-06-01T12:30:00+é:00",
+            "2024-06-01T12:30:00+09:é",
+        ] {
+            assert_eq!(Timestamp::parse(text), None, "{}", text);
+        }
+        assert_eq!(Date::parse("2024-13-01"), None);
+        assert_eq!(
+            Date::from_days(Date::parse("1600-02-29").unwrap().days()).to_string(),
+            "1600-02-29"
+        );
+    }
+}
@@ -0,0 +1,147 @@
+use std::{
+    fs::File,
+    io,
+    sync::{Arc, Condvar, Mutex},
+    thread::JoinHandle,
+    time::{Duration, Instant},
+};
+
+struct State {
+    last_sync: Option<Instant>,
+    // a handle to the file written since the last sync, if any
+    pending: Option<File>,
+    syncs: u64,
+    stop: bool,
+}
+
+struct Shared {
+    state: Mutex<State>,
+    wake: Condvar,
+}
+
+/// Limits the `sync_all` calls of a CustomLogger with `fsync` enabled to
+/// one per interval
+///
+/// A record written sooner than `interval` after the last sync is synced by
+/// a background thread once the interval has passed, so the file still
+/// reaches the disk shortly after a burst ends.
+pub(crate) struct FlushCoalescer {
+    interval: Duration,
+    shared: Arc<Shared>,
+    worker: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl FlushCoalescer {
+    pub(crate) fn new(interval: Duration) -> FlushCoalescer {
+        FlushCoalescer {
+            interval,
+            shared: Arc::new(Shared {
+                state: Mutex::new(State {
+                    last_sync: None,
+                    pending: None,
+                    syncs: 0,
+                    stop: false,
+                }),
+                wake: Condvar::new(),
+            }),
+            worker: Mutex::new(None),
+        }
+    }
+
+    /// sync `file` now if the last sync was at least an interval ago,
+    /// otherwise leave it to the background thread
+    pub(crate) fn sync(&self, file: &File) -> io::Result<()> {
+        let mut state = self.shared.state.lock().unwrap();
+        let now = Instant::now();
+        if state
+            .last_sync
+            .is_none_or(|last| now - last >= self.interval)
+        {
+            state.pending = None;
+            state.last_sync = Some(now);
+            state.syncs += 1;
+            drop(state);
+            return file.sync_all();
+        }
+        if state.pending.is_none() {
+            state.pending = Some(file.try_clone()?);
+        }
+        drop(state);
+        let mut worker = self.worker.lock().unwrap();
+        if worker.is_none() {
+            let shared = self.shared.clone();
+            let interval = self.interval;
+            *worker = Some(
+                std::thread::Builder::new()
+                    .name("loggers-fsync".to_string())
+                    .spawn(move || run(&shared, interval))?,
+            );
+        }
+        self.shared.wake.notify_one();
+        Ok(())
+    }
+
+    /// sync `file`, which is about to be closed by rotation or a reopen, if
+    /// writes to it are waiting for the background thread
+    ///
+    /// This drops the pending handle, so the next record written to the new
+    /// file is synced through that file instead of the closed one.
+    pub(crate) fn close(&self, file: &File) -> io::Result<()> {
+        let mut state = self.shared.state.lock().unwrap();
+        if state.pending.take().is_none() {
+            return Ok(());
+        }
+        state.last_sync = Some(Instant::now());
+        state.syncs += 1;
+        drop(state);
+        file.sync_all()
+    }
+
+    /// whether writes are waiting for the background thread
+    #[cfg(test)]
+    pub(crate) fn is_pending(&self) -> bool {
+        self.shared.state.lock().unwrap().pending.is_some()
+    }
+
+    /// the number of times the file was synced so far
+    #[cfg(test)]
+    pub(crate) fn syncs(&self) -> u64 {
+        self.shared.state.lock().unwrap().syncs
+    }
+}
+
+/// sync the pending file once its interval has passed, until stopped
+fn run(shared: &Shared, interval: Duration) {
+    let mut state = shared.state.lock().unwrap();
+    loop {
+        let due = state.last_sync.map(|last| last + interval);
+        match (state.pending.is_some(), due) {
+            (true, Some(due)) if !state.stop && Instant::now() < due => {
+                let wait = due.saturating_duration_since(Instant::now());
+                state = shared.wake.wait_timeout(state, wait).unwrap().0;
+            }
+            (true, _) => {
+                let file = state.pending.take().unwrap();
+                state.last_sync = Some(Instant::now());
+                state.syncs += 1;
+                drop(state);
+                if let Err(e) = file.sync_all() {
+                    eprintln!("Cannot sync log file: {}", e);
+                }
+                state = shared.state.lock().unwrap();
+            }
+            (false, _) if state.stop => return,
+            (false, _) => state = shared.wake.wait(state).unwrap(),
+        }
+    }
+}
+
+impl Drop for FlushCoalescer {
+    fn drop(&mut self) {
+        self.shared.state.lock().unwrap().stop = true;
+        self.shared.wake.notify_one();
+        if let Some(worker) = self.worker.lock().unwrap().take() {
+            let _ = worker.join();
+        }
+    }
+}
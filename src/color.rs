@@ -0,0 +1,163 @@
+/// the ANSI escape sequence used for `level` on the console
+pub(crate) fn level_color(level: log::Level) -> &'static str {
+    match level {
+        log::Level::Error => "\x1b[31m",
+        log::Level::Warn => "\x1b[33m",
+        log::Level::Info => "\x1b[32m",
+        log::Level::Debug => "\x1b[34m",
+        log::Level::Trace => "\x1b[36m",
+    }
+}
+
+pub(crate) const RESET: &str = "\x1b[0m";
+
+/// the escape sequence for an ANSI color spec: SGR parameters such as `35`,
+/// `1;31` or `38;5;208`, optionally written as the full `\x1b[...m` sequence
+pub(crate) fn sequence(spec: &str) -> Option<String> {
+    let params = spec.strip_prefix("\x1b[").unwrap_or(spec);
+    let params = params.strip_suffix('m').unwrap_or(params);
+    let valid = params
+        .split(';')
+        .all(|p| !p.is_empty() && p.len() <= 3 && p.bytes().all(|b| b.is_ascii_digit()));
+    valid.then(|| format!("\x1b[{}m", params))
+}
+
+/// wrap `text` in the escape sequence `color`
+pub(crate) fn paint(color: &str, text: &str) -> String {
+    format!("{}{}{}", color, text, RESET)
+}
+
+/// When console lines are colored
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorMode {
+    /// never color
+    #[default]
+    Never,
+    /// follow the `NO_COLOR`, `CLICOLOR_FORCE` and `CLICOLOR` conventions,
+    /// coloring only when stdout is a terminal if none of them is set
+    Auto,
+    /// always color, regardless of the environment
+    Always,
+}
+
+impl ColorMode {
+    /// whether to color, resolving [`ColorMode::Auto`] against the environment now
+    pub fn enabled(&self) -> bool {
+        match self {
+            ColorMode::Never => false,
+            ColorMode::Always => true,
+            ColorMode::Auto => auto(
+                std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty()),
+                std::env::var_os("CLICOLOR_FORCE").is_some_and(|v| !v.is_empty() && v != "0"),
+                std::env::var_os("CLICOLOR").is_some_and(|v| v == "0"),
+                std::io::IsTerminal::is_terminal(&std::io::stdout()),
+            ),
+        }
+    }
+}
+
+/// `NO_COLOR` disables color, then `CLICOLOR_FORCE` forces it, then
+/// `CLICOLOR=0` disables it, and otherwise color is used on a terminal
+fn auto(no_color: bool, force: bool, clicolor_off: bool, is_terminal: bool) -> bool {
+    if no_color {
+        return false;
+    }
+    if force {
+        return true;
+    }
+    !clicolor_off && is_terminal
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CustomLogger, LogEntry};
+
+    fn colored(mode: ColorMode) -> bool {
+        let logger = CustomLogger::console_only("color").color_mode(mode);
+        let entry = LogEntry::from_record(
+            &log::Record::builder()
+                .target("color")
+                .level(log::Level::Warn)
+                .args(format_args!("careful"))
+                .build(),
+            "color",
+            "2024-01-01T00:00:00.000Z",
+        );
+        logger.console_line(&entry).contains("\x1b[")
+    }
+
+    #[test]
+    fn test_color_env() {
+        let vars = ["NO_COLOR", "CLICOLOR_FORCE", "CLICOLOR"];
+        let set = |values: [Option<&str>; 3]| {
+            for (var, value) in vars.iter().zip(values) {
+                match value {
+                    Some(value) => std::env::set_var(var, value),
+                    None => std::env::remove_var(var),
+                }
+            }
+        };
+
+        set([Some("1"), Some("1"), None]);
+        assert!(!colored(ColorMode::Auto));
+        assert!(colored(ColorMode::Always));
+
+        set([None, Some("1"), Some("0")]);
+        assert!(colored(ColorMode::Auto));
+
+        set([None, Some("0"), None]);
+        assert_eq!(
+            colored(ColorMode::Auto),
+            std::io::IsTerminal::is_terminal(&std::io::stdout())
+        );
+
+        set([None, None, Some("0")]);
+        assert!(!colored(ColorMode::Auto));
+        assert!(!colored(ColorMode::Never));
+
+        set([None, None, None]);
+    }
+
+    #[test]
+    fn test_color_map() {
+        let logger = CustomLogger::console_only("color_map")
+            .color(true)
+            .color_map(&[(log::Level::Warn, "1;35"), (log::Level::Info, "blue")]);
+        let line = |level| {
+            let entry = LogEntry::from_record(
+                &log::Record::builder()
+                    .target("color_map")
+                    .level(level)
+                    .args(format_args!("styled"))
+                    .build(),
+                "color_map",
+                "2024-01-01T00:00:00.000Z",
+            );
+            logger.console_line(&entry)
+        };
+        assert!(line(log::Level::Warn).starts_with("\x1b[1;35m[WARN]\x1b[0m"));
+        // an invalid spec keeps the default color
+        assert!(line(log::Level::Info).starts_with("\x1b[32m[INFO]\x1b[0m"));
+        assert!(line(log::Level::Error).starts_with("\x1b[31m[ERROR]\x1b[0m"));
+    }
+
+    #[test]
+    fn test_sequence() {
+        assert_eq!(sequence("35").unwrap(), "\x1b[35m");
+        assert_eq!(sequence("38;5;208").unwrap(), "\x1b[38;5;208m");
+        assert_eq!(sequence("\x1b[1;31m").unwrap(), "\x1b[1;31m");
+        assert_eq!(sequence("red"), None);
+        assert_eq!(sequence("1;;31"), None);
+        assert_eq!(sequence(""), None);
+    }
+
+    #[test]
+    fn test_auto() {
+        assert!(!auto(false, false, false, false));
+        assert!(auto(false, false, false, true));
+        assert!(!auto(false, false, true, true));
+        assert!(auto(false, true, true, false));
+        assert!(!auto(true, true, false, true));
+    }
+}
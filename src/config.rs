@@ -0,0 +1,316 @@
+//! Snapshots of a Logger's configuration with [`Logger::export_config`]
+
+use crate::{
+    ConsoleFormat, CustomLogger, DispatchMode, EscapeMode, Format, LevelCase, Logger,
+    NoMatchPolicy, OpenOptions, TargetField,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// The configuration of a [`Logger`], as returned by
+/// [`Logger::export_config`]
+///
+/// Only CustomLoggers and [`crate::LevelLogger`]s can be described; other
+/// loggers are left out, see [`Logger::export_config`].
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub loggers: Vec<LoggerConfig>,
+    pub fallback: Option<LoggerConfig>,
+    pub audit: Option<LoggerConfig>,
+    pub dispatch_mode: DispatchMode,
+    pub no_match_policy: NoMatchPolicy,
+    pub run_id: Option<String>,
+}
+
+/// The configuration of one [`CustomLogger`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LoggerConfig {
+    pub target: String,
+    /// the file written to, or `None` for a console-only logger
+    pub filepath: Option<String>,
+    /// the least severe level accepted
+    pub min_level: log::LevelFilter,
+    /// the most severe level accepted
+    pub max_level: log::Level,
+    pub any_target: bool,
+    pub match_module_default: bool,
+    pub format: Format,
+    pub console: bool,
+    pub console_format: ConsoleFormat,
+    pub location: bool,
+    pub target_field: TargetField,
+    pub level_case: LevelCase,
+    pub escape_mode: EscapeMode,
+    pub max_size: Option<u64>,
+    pub max_lines: Option<u64>,
+    pub rotate_daily: bool,
+}
+
+impl Default for LoggerConfig {
+    fn default() -> Self {
+        LoggerConfig {
+            target: String::new(),
+            filepath: None,
+            min_level: log::LevelFilter::Trace,
+            max_level: log::Level::Error,
+            any_target: false,
+            match_module_default: false,
+            format: Format::default(),
+            console: true,
+            console_format: ConsoleFormat::default(),
+            location: false,
+            target_field: TargetField::default(),
+            level_case: LevelCase::default(),
+            escape_mode: EscapeMode::default(),
+            max_size: None,
+            max_lines: None,
+            rotate_daily: false,
+        }
+    }
+}
+
+impl CustomLogger {
+    pub(crate) fn config(&self) -> LoggerConfig {
+        let min_level = match self.min_level.load(Ordering::Relaxed) {
+            0 => log::LevelFilter::Off,
+            1 => log::LevelFilter::Error,
+            2 => log::LevelFilter::Warn,
+            3 => log::LevelFilter::Info,
+            4 => log::LevelFilter::Debug,
+            _ => log::LevelFilter::Trace,
+        };
+        LoggerConfig {
+            target: self.target.clone(),
+            filepath: self.filepath.clone(),
+            min_level,
+            max_level: self.max_level,
+            any_target: self.any_target,
+            match_module_default: self.match_module_default,
+            format: self.format,
+            console: self.console,
+            console_format: self.console_format,
+            location: self.location,
+            target_field: self.target_field,
+            level_case: self.level_case,
+            escape_mode: self.escape_mode,
+            max_size: self.max_size,
+            max_lines: self.max_lines,
+            rotate_daily: self.rotate_daily,
+        }
+    }
+
+    /// a CustomLogger configured by `config`, appending to its file
+    fn from_config(config: &LoggerConfig) -> std::io::Result<CustomLogger> {
+        let mut logger = match &config.filepath {
+            Some(filepath) => OpenOptions::new()
+                .append(true)
+                .open(&config.target, filepath)?,
+            None => CustomLogger::console_only(&config.target),
+        };
+        logger.min_level = AtomicUsize::new(config.min_level as usize);
+        logger.max_level = config.max_level;
+        logger.any_target = config.any_target;
+        logger.match_module_default = config.match_module_default;
+        logger.format = config.format;
+        logger.console = config.console;
+        logger.console_format = config.console_format;
+        logger.location = config.location;
+        logger.target_field = config.target_field;
+        logger.level_case = config.level_case;
+        logger.escape_mode = config.escape_mode;
+        logger.max_size = config.max_size;
+        logger.max_lines = config.max_lines;
+        logger.rotate_daily = config.rotate_daily;
+        Ok(logger)
+    }
+}
+
+impl Logger {
+    /// a snapshot of this logger's configuration: the targets, levels,
+    /// formats and paths of its CustomLoggers, its fallback and audit
+    /// logger, and how it dispatches records
+    ///
+    /// The snapshot can be serialized, e.g. with `serde_json`, and turned
+    /// back into a Logger with [`Logger::from_config`]. Options not in
+    /// [`LoggerConfig`] are not included, and neither are loggers that are
+    /// not a single CustomLogger or [`crate::LevelLogger`]: loggers combining
+    /// several, such as [`crate::TeeLogger`], [`crate::RoutingLogger`] or
+    /// [`crate::ShardedLogger`], loggers writing to a [`crate::Sink`], such as
+    /// [`crate::AsyncLogger`], and loggers of other crates. The export does not
+    /// flush or write to any logger.
+    /// # Example
+    /// ```
+    /// # use crate::loggers::*;
+    /// let mut logger = Logger::new();
+    /// logger.add_logger(Box::new(CustomLogger::new("api", "tests/output/api.log")));
+    /// let json = serde_json::to_string(&logger.export_config()).unwrap();
+    /// ```
+    pub fn export_config(&self) -> Config {
+        let describe = |logger: &dyn log::Log| {
+            let mut configs = crate::inspect::describe(logger);
+            (configs.len() == 1).then(|| configs.remove(0))
+        };
+        Config {
+            loggers: self
+                .loggers
+                .iter()
+                .filter_map(|logger| describe(logger.as_ref()))
+                .collect(),
+            fallback: self.fallback.as_deref().and_then(describe),
+            audit: self.audit.as_deref().and_then(describe),
+            dispatch_mode: self.dispatch_mode,
+            no_match_policy: self.no_match_policy,
+            run_id: self.run_id.clone(),
+        }
+    }
+
+    /// build a Logger from a configuration returned by [`Logger::export_config`]
+    ///
+    /// Files are opened in append mode, so rebuilding a logger keeps the
+    /// records already written.
+    /// # Example
+    /// ```
+    /// # use crate::loggers::*;
+    /// # let mut logger = Logger::new();
+    /// # logger.add_logger(Box::new(CustomLogger::new("api", "tests/output/api.log")));
+    /// let config = logger.export_config();
+    /// let copy = Logger::from_config(config).expect("Failed to open log file");
+    /// ```
+    pub fn from_config(config: Config) -> std::io::Result<Logger> {
+        let mut logger = Logger::new();
+        for logger_config in &config.loggers {
+            logger.add_logger(Box::new(CustomLogger::from_config(logger_config)?));
+        }
+        if let Some(fallback) = &config.fallback {
+            logger.set_fallback(Box::new(CustomLogger::from_config(fallback)?));
+        }
+        if let Some(audit) = &config.audit {
+            logger.set_audit(Box::new(CustomLogger::from_config(audit)?));
+        }
+        logger.set_dispatch_mode(config.dispatch_mode);
+        logger.set_no_match_policy(config.no_match_policy);
+        logger.run_id = config.run_id;
+        Ok(logger)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::read_log;
+
+    #[test]
+    fn test_config_round_trip() {
+        let (api, db, other) = (
+            "tests/output/config_api.log",
+            "tests/output/config_db.log",
+            "tests/output/config_other.log",
+        );
+        let mut logger = Logger::with_run_id("config");
+        logger.add_logger(Box::new(
+            CustomLogger::new("api", api)
+                .min_level(log::Level::Warn)
+                .console(false),
+        ));
+        logger.add_logger(Box::new(
+            CustomLogger::new("db", db)
+                .level_range(log::Level::Trace, log::Level::Debug)
+                .format(Format::Gelf)
+                .console(false),
+        ));
+        logger.set_fallback(Box::new(
+            CustomLogger::new("default", other)
+                .any_target(true)
+                .console(false),
+        ));
+        logger.set_dispatch_mode(DispatchMode::FirstMatch);
+
+        let config = logger.export_config();
+        let json = serde_json::to_string(&config).unwrap();
+        let restored: Config = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored, config);
+        assert_eq!(config.loggers.len(), 2);
+        assert_eq!(config.loggers[0].min_level, log::LevelFilter::Warn);
+        assert_eq!(config.loggers[1].format, Format::Gelf);
+
+        let rebuilt = Logger::from_config(restored).unwrap();
+        assert_eq!(rebuilt.export_config(), config);
+
+        let send = |logger: &Logger| {
+            for (level, target) in [
+                (log::Level::Info, "api"),
+                (log::Level::Error, "api"),
+                (log::Level::Debug, "db"),
+                (log::Level::Info, "db"),
+                (log::Level::Info, "billing"),
+            ] {
+                logger.log_record(level, target, &format!("{} {}", target, level), &[]);
+            }
+        };
+        let lines = |path| std::fs::read_to_string(path).unwrap().lines().count();
+        send(&logger);
+        let written = [lines(api), lines(db), lines(other)];
+        assert_eq!(written, [1, 1, 3]);
+        send(&rebuilt);
+        assert_eq!(
+            [lines(api), lines(db), lines(other)],
+            written.map(|n| 2 * n)
+        );
+
+        let fallback: Vec<_> = read_log(other, false).map(|e| e.unwrap()).collect();
+        assert_eq!(fallback[3].message, fallback[0].message);
+        assert_eq!(fallback[3].fields["run_id"], "config");
+    }
+
+    #[test]
+    fn test_export_leaves_out_composites() {
+        /// a logger of another crate, which must not be flushed by an export
+        struct Foreign;
+
+        impl log::Log for Foreign {
+            fn enabled(&self, _: &log::Metadata) -> bool {
+                true
+            }
+
+            fn log(&self, _: &log::Record) {}
+
+            fn flush(&self) {
+                panic!("flushed by export_config");
+            }
+        }
+
+        let level = "tests/output/config_level.log";
+        let mut logger = Logger::new();
+        logger.add_logger(Box::new(crate::TeeLogger::to_file(
+            Box::new(Foreign),
+            "tests/output/config_tee.log",
+        )));
+        logger.add_logger(Box::new(
+            crate::ShardedLogger::new("shards", "tests/output/config_shards.log", 2).unwrap(),
+        ));
+        let routing = crate::RoutingLogger::template("tests/output/config_routing/{target}.log");
+        log::Log::log(
+            &routing,
+            &log::Record::builder()
+                .target("opened")
+                .level(log::Level::Info)
+                .args(format_args!("opens a file"))
+                .build(),
+        );
+        logger.add_logger(Box::new(routing));
+        logger.add_logger(Box::new(crate::AsyncLogger::new(
+            "async",
+            Box::new(crate::MemorySink::new()),
+        )));
+        logger.add_logger(Box::new(Foreign));
+        logger.add_logger(Box::new(crate::LevelLogger::new(log::Level::Error, level)));
+
+        let config = logger.export_config();
+        assert_eq!(config.loggers.len(), 1);
+        assert_eq!(config.loggers[0].filepath.as_deref(), Some(level));
+        assert_eq!(config.loggers[0].min_level, log::LevelFilter::Error);
+        assert!(config.loggers[0].any_target);
+    }
+}
@@ -0,0 +1,41 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        OnceLock,
+    },
+    time::{Duration, Instant},
+};
+
+/// the environment variable that turns all logging through a Logger off
+pub const DISABLE_VAR: &str = "LOGGERS_DISABLE";
+
+/// how often [`DISABLE_VAR`] is re-read
+pub const DISABLE_RECHECK: Duration = Duration::from_secs(1);
+
+static DISABLED: AtomicBool = AtomicBool::new(false);
+// milliseconds since START at which DISABLE_VAR is read next
+static NEXT_CHECK: AtomicU64 = AtomicU64::new(0);
+static START: OnceLock<Instant> = OnceLock::new();
+
+/// whether logging is switched off by [`DISABLE_VAR`]
+///
+/// The variable counts as set unless it is missing, empty, `0` or `false`.
+/// Its value is cached and re-read at most once per [`DISABLE_RECHECK`].
+pub(crate) fn disabled() -> bool {
+    let now = START.get_or_init(Instant::now).elapsed().as_millis() as u64;
+    let next = NEXT_CHECK.load(Ordering::Relaxed);
+    if now >= next
+        && NEXT_CHECK
+            .compare_exchange(
+                next,
+                now + DISABLE_RECHECK.as_millis() as u64,
+                Ordering::Relaxed,
+                Ordering::Relaxed,
+            )
+            .is_ok()
+    {
+        let set = std::env::var(DISABLE_VAR).is_ok_and(|v| !matches!(v.trim(), "" | "0" | "false"));
+        DISABLED.store(set, Ordering::Relaxed);
+    }
+    DISABLED.load(Ordering::Relaxed)
+}
@@ -0,0 +1,354 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::cell::Cell;
+
+/// the largest buffer kept by [`give_buffer`]; larger ones are freed so one
+/// huge record does not pin its memory for the life of the thread
+const MAX_RETAINED: usize = 64 * 1024;
+
+thread_local! {
+    static BUFFER: Cell<Vec<u8>> = const { Cell::new(Vec::new()) };
+}
+
+/// an empty buffer to encode a record into, reusing this thread's spare one
+pub(crate) fn take_buffer() -> Vec<u8> {
+    let mut buffer = BUFFER.with(Cell::take);
+    buffer.clear();
+    buffer
+}
+
+/// keep `buffer` as this thread's spare for the next [`take_buffer`]
+pub(crate) fn give_buffer(buffer: Vec<u8>) {
+    if buffer.capacity() <= MAX_RETAINED {
+        BUFFER.with(|spare| spare.set(buffer));
+    }
+}
+
+/// the key under which [`crate::log_fn!`] passes the calling function
+pub(crate) const FUNCTION_KEY: &str = "loggers.function";
+
+/// the version of the record shape CustomLogger writes, in the
+/// `schema_version` field of every record
+///
+/// It is incremented whenever standard fields are added, renamed or change
+/// meaning, so consumers can branch on it.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// The JSON shape of a single record written by CustomLogger
+///
+/// Structured key-value fields are flattened into the top-level object, so
+/// any key that is not one of the standard fields ends up in `fields` when
+/// deserializing.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LogEntry {
+    pub severity: String,
+    pub timestamp: String,
+    pub target: String,
+    pub message: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub location: Option<Location>,
+    #[serde(flatten)]
+    pub fields: Map<String, Value>,
+}
+
+impl LogEntry {
+    /// build the entry for `record` as emitted by the logger configured for `target`
+    pub fn from_record(record: &log::Record, target: &str, timestamp: &str) -> LogEntry {
+        let mut fields = record_fields(record);
+        let spans = crate::span::current();
+        if !spans.is_empty() {
+            fields.insert("spans".to_string(), spans.into());
+        }
+        if let Some(trace) = crate::span::trace() {
+            let format = crate::span::IdFormat::Hex;
+            fields.insert(
+                "trace_id".to_string(),
+                format.trace_id(trace.trace_id).into(),
+            );
+            fields.insert("span_id".to_string(), format.span_id(trace.span_id).into());
+        }
+        LogEntry {
+            severity: record.level().to_string(),
+            timestamp: timestamp.to_string(),
+            target: target.to_string(),
+            message: record.args().to_string(),
+            location: None,
+            fields,
+        }
+    }
+}
+
+/// Where in the source a record was emitted
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Location {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub module: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub line: Option<u32>,
+}
+
+impl Location {
+    pub fn from_record(record: &log::Record) -> Location {
+        Location {
+            module: record.module_path().map(str::to_string),
+            file: record.file().map(str::to_string),
+            line: record.line(),
+        }
+    }
+}
+
+/// collect the key-value fields of a record into a JSON object
+///
+/// If any value fails to serialize, the fields are replaced by a single
+/// `error: "serialization_failed"` marker so the record is still written.
+pub(crate) fn record_fields(record: &log::Record) -> Map<String, Value> {
+    struct Collect<'a>(&'a mut Map<String, Value>);
+
+    impl<'kvs> log::kv::VisitSource<'kvs> for Collect<'_> {
+        fn visit_pair(
+            &mut self,
+            key: log::kv::Key<'kvs>,
+            value: log::kv::Value<'kvs>,
+        ) -> Result<(), log::kv::Error> {
+            if key.as_str() == FUNCTION_KEY {
+                return Ok(());
+            }
+            let value = serde_json::to_value(&value).map_err(log::kv::Error::boxed)?;
+            self.0.insert(key.to_string(), value);
+            Ok(())
+        }
+    }
+
+    let mut fields = Map::new();
+    if record
+        .key_values()
+        .visit(&mut Collect(&mut fields))
+        .is_err()
+    {
+        fields = serialization_failed();
+    }
+    fields
+}
+
+/// the fields `value` contributes to a record: its own fields if it serializes
+/// to an object, otherwise a single `value` field
+pub(crate) fn struct_fields<T: Serialize + ?Sized>(value: &T) -> Map<String, Value> {
+    match serde_json::to_value(value) {
+        Ok(Value::Object(fields)) => fields,
+        Ok(value) => {
+            let mut fields = Map::new();
+            fields.insert("value".to_string(), value);
+            fields
+        }
+        Err(_) => serialization_failed(),
+    }
+}
+
+pub(crate) fn serialization_failed() -> Map<String, Value> {
+    let mut fields = Map::new();
+    fields.insert("error".to_string(), "serialization_failed".into());
+    fields
+}
+
+/// append the newline-terminated JSON line for `record` to `out`, degrading
+/// to the standard fields and an `error: "serialization_failed"` marker if it
+/// fails to serialize
+///
+/// With an `indent` of `n > 0` the record is pretty-printed using `n` spaces.
+pub(crate) fn json_line<T: Serialize>(
+    out: &mut Vec<u8>,
+    record: &T,
+    entry: &LogEntry,
+    indent: usize,
+) {
+    let start = out.len();
+    if write_json(out, record, indent).is_err() {
+        let fallback = LogEntry {
+            location: None,
+            fields: serialization_failed(),
+            ..entry.clone()
+        };
+        out.truncate(start);
+        write_json(out, &fallback, indent).unwrap();
+    }
+    out.push(b'\n');
+}
+
+fn write_json<T: Serialize>(
+    out: &mut Vec<u8>,
+    record: &T,
+    indent: usize,
+) -> Result<(), serde_json::Error> {
+    if indent == 0 {
+        return serde_json::to_writer(out, record);
+    }
+    let indent = vec![b' '; indent];
+    let formatter = serde_json::ser::PrettyFormatter::with_indent(&indent);
+    record.serialize(&mut serde_json::Serializer::with_formatter(out, formatter))
+}
+
+/// the logfmt line for `entry`: `level=info ts=... target=... msg=...`
+/// followed by its fields, without a trailing newline
+///
+/// Values containing spaces, quotes, `=` or control characters are quoted;
+/// non-string fields are written as compact JSON.
+pub(crate) fn logfmt_line(entry: &LogEntry) -> String {
+    let mut line = String::with_capacity(128);
+    let mut push = |key: &str, value: &str| {
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(key);
+        line.push('=');
+        push_logfmt_value(&mut line, value);
+    };
+    push("level", &entry.severity.to_lowercase());
+    push("ts", &entry.timestamp);
+    push("target", &entry.target);
+    push("msg", &entry.message);
+    for (key, value) in &entry.fields {
+        match value {
+            Value::String(value) => push(key, value),
+            value => push(key, &value.to_string()),
+        }
+    }
+    line
+}
+
+fn push_logfmt_value(out: &mut String, value: &str) {
+    let needs_quotes = value.is_empty()
+        || value
+            .chars()
+            .any(|c| c == ' ' || c == '"' || c == '=' || c == '\\' || c.is_control());
+    if !needs_quotes {
+        out.push_str(value);
+        return;
+    }
+    out.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+/// `text` with its control characters handled according to `mode`
+pub(crate) fn escape(text: &str, mode: crate::EscapeMode) -> String {
+    use crate::EscapeMode;
+    if mode == EscapeMode::JsonStandard || !text.chars().any(char::is_control) {
+        return text.to_string();
+    }
+    let mut escaped = String::with_capacity(text.len() + 8);
+    for c in text.chars() {
+        match (c.is_control(), mode) {
+            (false, _) | (true, EscapeMode::JsonStandard) => escaped.push(c),
+            (true, EscapeMode::StripControl) => {}
+            (true, EscapeMode::Visible) => {
+                escaped.push_str(&format!("\\x{:02x}", c as u32));
+            }
+        }
+    }
+    escaped
+}
+
+/// apply [`escape`] to every string inside `value`
+pub(crate) fn escape_value(value: &mut Value, mode: crate::EscapeMode) {
+    match value {
+        Value::String(text) => *text = escape(text, mode),
+        Value::Array(values) => values.iter_mut().for_each(|v| escape_value(v, mode)),
+        Value::Object(object) => object.values_mut().for_each(|v| escape_value(v, mode)),
+        _ => {}
+    }
+}
+
+/// cut every string in `value` longer than `max` bytes to at most `max`
+/// bytes, ending on a character boundary, followed by `…`
+pub(crate) fn truncate_value(value: &mut Value, max: usize) {
+    match value {
+        Value::String(text) if text.len() > max => {
+            let mut end = max;
+            while !text.is_char_boundary(end) {
+                end -= 1;
+            }
+            text.truncate(end);
+            text.push('…');
+        }
+        Value::Array(values) => values.iter_mut().for_each(|v| truncate_value(v, max)),
+        Value::Object(object) => object.values_mut().for_each(|v| truncate_value(v, max)),
+        _ => {}
+    }
+}
+
+/// sort the keys of `value` and of every object nested in it alphabetically
+pub(crate) fn sort_keys(value: &mut Value) {
+    match value {
+        Value::Object(object) => {
+            object.sort_keys();
+            object.values_mut().for_each(sort_keys);
+        }
+        Value::Array(values) => values.iter_mut().for_each(sort_keys),
+        _ => {}
+    }
+}
+
+/// move `record[from]` to `to`, where a dotted `to` is a path into nested objects
+pub(crate) fn rename(record: &mut Map<String, Value>, from: &str, to: &str) {
+    let Some(value) = record.shift_remove(from) else {
+        return;
+    };
+    let mut keys: Vec<&str> = to.split('.').collect();
+    let last = keys.pop().unwrap_or(to);
+    let mut object = record;
+    for key in keys {
+        let slot = object
+            .entry(key.to_string())
+            .or_insert_with(|| Value::Object(Map::new()));
+        if !slot.is_object() {
+            *slot = Value::Object(Map::new());
+        }
+        object = slot.as_object_mut().unwrap();
+    }
+    object.insert(last.to_string(), value);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn test_entry_round_trip() {
+        let mut fields = Map::new();
+        fields.insert("user".to_string(), Value::from(42));
+        let entry = LogEntry {
+            severity: "INFO".to_string(),
+            timestamp: "2024-01-01T00:00:00.000Z".to_string(),
+            target: "entry".to_string(),
+            message: "say \"hi\"\nand leave".to_string(),
+            location: Some(Location {
+                module: Some("loggers::entry".to_string()),
+                file: Some("src/entry.rs".to_string()),
+                line: Some(1),
+            }),
+            fields,
+        };
+
+        let path = "tests/output/entry.log";
+        std::fs::create_dir_all("tests/output").unwrap();
+        let mut file = std::fs::File::create(path).unwrap();
+        writeln!(file, "{}", serde_json::to_string(&entry).unwrap()).unwrap();
+
+        let contents = std::fs::read_to_string(path).unwrap();
+        assert_eq!(contents.lines().count(), 1);
+        let read_back: LogEntry = serde_json::from_str(contents.trim_end()).unwrap();
+        assert_eq!(read_back, entry);
+    }
+}
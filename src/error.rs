@@ -0,0 +1,59 @@
+use std::fmt;
+
+/// Errors produced while reading back log files
+#[derive(Debug)]
+pub enum Error {
+    /// The file could not be opened or read
+    Io(std::io::Error),
+    /// A line (1-based) could not be parsed as a log record
+    Parse {
+        line: usize,
+        source: serde_json::Error,
+    },
+    /// A line (1-based) does not match the hash chain
+    Tampered { line: usize },
+    /// A binary record (1-based) could not be decoded
+    Decode {
+        record: usize,
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "io error: {}", e),
+            Error::Parse { line, source } => {
+                write!(f, "malformed record at line {}: {}", line, source)
+            }
+            Error::Tampered { line } => write!(f, "hash chain broken at line {}", line),
+            Error::Decode { record, source } => {
+                write!(f, "malformed binary record {}: {}", record, source)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            Error::Parse { source, .. } => Some(source),
+            Error::Tampered { .. } => None,
+            Error::Decode { source, .. } => Some(source.as_ref()),
+        }
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+/// the messages of `err` and each of its sources, outermost first
+pub fn error_chain(err: &dyn std::error::Error) -> Vec<String> {
+    std::iter::successors(Some(err), |e| e.source())
+        .map(|e| e.to_string())
+        .collect()
+}
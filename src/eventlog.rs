@@ -0,0 +1,127 @@
+use std::{io, ptr};
+use windows_sys::Win32::{
+    Foundation::HANDLE,
+    System::EventLog::{
+        DeregisterEventSource, RegisterEventSourceW, ReportEventW, EVENTLOG_ERROR_TYPE,
+        EVENTLOG_INFORMATION_TYPE, EVENTLOG_WARNING_TYPE, REPORT_EVENT_TYPE,
+    },
+};
+
+/// A logger that reports records to the Windows Event Log
+///
+/// `Error` records are reported as error events, `Warn` as warning events and
+/// everything else as information events.
+pub struct WindowsEventLogger {
+    target: String,
+    handle: HANDLE,
+}
+
+// The event log handle returned by RegisterEventSourceW may be used from any thread.
+unsafe impl Send for WindowsEventLogger {}
+unsafe impl Sync for WindowsEventLogger {}
+
+impl WindowsEventLogger {
+    /// register `source` as an event source on the local machine
+    /// # Arguments
+    /// * `target` - The target to match
+    /// * `source` - The event source name records are reported under
+    pub fn new(target: &str, source: &str) -> io::Result<WindowsEventLogger> {
+        let source = to_wide(source);
+        let handle = unsafe { RegisterEventSourceW(ptr::null(), source.as_ptr()) };
+        if handle.is_null() {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(WindowsEventLogger {
+            target: target.to_string(),
+            handle,
+        })
+    }
+
+    /// report a single event
+    pub fn report(&self, level: log::Level, message: &str) -> io::Result<()> {
+        let message = to_wide(message);
+        let strings = [message.as_ptr()];
+        let ok = unsafe {
+            ReportEventW(
+                self.handle,
+                event_type(level),
+                0,
+                0,
+                ptr::null_mut(),
+                strings.len() as u16,
+                0,
+                strings.as_ptr(),
+                ptr::null(),
+            )
+        };
+        if ok == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+}
+
+/// map a log level to the event type it is reported as
+pub fn event_type(level: log::Level) -> REPORT_EVENT_TYPE {
+    match level {
+        log::Level::Error => EVENTLOG_ERROR_TYPE,
+        log::Level::Warn => EVENTLOG_WARNING_TYPE,
+        _ => EVENTLOG_INFORMATION_TYPE,
+    }
+}
+
+fn to_wide(s: &str) -> Vec<u16> {
+    s.encode_utf16().chain(std::iter::once(0)).collect()
+}
+
+impl Drop for WindowsEventLogger {
+    fn drop(&mut self) {
+        unsafe {
+            DeregisterEventSource(self.handle);
+        }
+    }
+}
+
+impl log::Log for WindowsEventLogger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        metadata.target() == self.target
+    }
+
+    fn log(&self, record: &log::Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        if let Err(e) = self.report(record.level(), &record.args().to_string()) {
+            eprintln!("Cannot report event for {}: {}", self.target, e);
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_type() {
+        assert_eq!(event_type(log::Level::Error), EVENTLOG_ERROR_TYPE);
+        assert_eq!(event_type(log::Level::Warn), EVENTLOG_WARNING_TYPE);
+        assert_eq!(event_type(log::Level::Info), EVENTLOG_INFORMATION_TYPE);
+        assert_eq!(event_type(log::Level::Trace), EVENTLOG_INFORMATION_TYPE);
+    }
+
+    #[test]
+    fn test_report_error() {
+        let logger = match WindowsEventLogger::new("eventlog", "loggers-test") {
+            Ok(logger) => logger,
+            Err(e) => {
+                eprintln!("skipping: event source unavailable: {}", e);
+                return;
+            }
+        };
+        logger
+            .report(log::Level::Error, "loggers test event")
+            .unwrap();
+    }
+}
@@ -0,0 +1,176 @@
+#[cfg(feature = "chrono")]
+use chrono::{DateTime, FixedOffset, SecondsFormat, TimeZone};
+use serde::{Serialize, Serializer};
+use std::time::Duration;
+
+/// A typed structured field value
+///
+/// Numbers and booleans are written as JSON numbers and booleans, never as
+/// quoted strings, which keeps metrics such as `latency_ms` queryable. A
+/// `Duration` becomes its whole number of milliseconds and, with the
+/// `chrono` feature, a `DateTime` an RFC3339 string.
+/// Works as the value of a key-value field in the `log` macros and in
+/// [`jlog!`](crate::jlog).
+/// # Example
+/// ```
+/// # use loggers::*;
+/// log::info!(target: "http", latency_ms = FieldValue::from(123), cached = FieldValue::from(false); "served");
+///
+/// # #[cfg(feature = "chrono")] {
+/// let started = chrono::Utc::now();
+/// let elapsed = std::time::Duration::from_millis(1500);
+/// log::info!(target: "job", started = FieldValue::from(started), elapsed_ms = FieldValue::from(elapsed); "done");
+/// # }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FieldValue<'a> {
+    I64(i64),
+    F64(f64),
+    Bool(bool),
+    Str(&'a str),
+    #[cfg(feature = "chrono")]
+    Timestamp(DateTime<FixedOffset>),
+}
+
+impl From<i64> for FieldValue<'_> {
+    fn from(v: i64) -> Self {
+        FieldValue::I64(v)
+    }
+}
+
+impl From<i32> for FieldValue<'_> {
+    fn from(v: i32) -> Self {
+        FieldValue::I64(v.into())
+    }
+}
+
+impl From<u32> for FieldValue<'_> {
+    fn from(v: u32) -> Self {
+        FieldValue::I64(v.into())
+    }
+}
+
+impl From<f64> for FieldValue<'_> {
+    fn from(v: f64) -> Self {
+        FieldValue::F64(v)
+    }
+}
+
+impl From<bool> for FieldValue<'_> {
+    fn from(v: bool) -> Self {
+        FieldValue::Bool(v)
+    }
+}
+
+impl<'a> From<&'a str> for FieldValue<'a> {
+    fn from(v: &'a str) -> Self {
+        FieldValue::Str(v)
+    }
+}
+
+impl From<Duration> for FieldValue<'_> {
+    fn from(v: Duration) -> Self {
+        FieldValue::I64(v.as_millis().try_into().unwrap_or(i64::MAX))
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl<Tz: TimeZone> From<DateTime<Tz>> for FieldValue<'_> {
+    fn from(v: DateTime<Tz>) -> Self {
+        FieldValue::Timestamp(v.fixed_offset())
+    }
+}
+
+impl Serialize for FieldValue<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match *self {
+            FieldValue::I64(v) => serializer.serialize_i64(v),
+            FieldValue::F64(v) => serializer.serialize_f64(v),
+            FieldValue::Bool(v) => serializer.serialize_bool(v),
+            FieldValue::Str(v) => serializer.serialize_str(v),
+            #[cfg(feature = "chrono")]
+            FieldValue::Timestamp(v) => {
+                serializer.serialize_str(&v.to_rfc3339_opts(SecondsFormat::AutoSi, true))
+            }
+        }
+    }
+}
+
+impl log::kv::ToValue for FieldValue<'_> {
+    fn to_value(&self) -> log::kv::Value<'_> {
+        match *self {
+            FieldValue::I64(v) => log::kv::Value::from(v),
+            FieldValue::F64(v) => log::kv::Value::from(v),
+            FieldValue::Bool(v) => log::kv::Value::from(v),
+            FieldValue::Str(v) => log::kv::Value::from(v),
+            #[cfg(feature = "chrono")]
+            FieldValue::Timestamp(_) => log::kv::Value::from_serde(self),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CustomLogger;
+    use log::Log;
+
+    #[test]
+    fn test_typed_fields() {
+        let path = "tests/output/typed_fields.log";
+        let logger = CustomLogger::new("typed_fields", path);
+        let kvs = [
+            ("latency_ms", FieldValue::from(123)),
+            ("ratio", FieldValue::from(0.25)),
+            ("cached", FieldValue::from(true)),
+            ("route", FieldValue::from("/users")),
+        ];
+        logger.log(
+            &log::Record::builder()
+                .target("typed_fields")
+                .level(log::Level::Info)
+                .key_values(&kvs)
+                .args(format_args!("served"))
+                .build(),
+        );
+
+        let contents = std::fs::read_to_string(path).unwrap();
+        let v: serde_json::Value = serde_json::from_str(contents.trim_end()).unwrap();
+        assert_eq!(v["latency_ms"], serde_json::json!(123));
+        assert!(v["latency_ms"].is_i64());
+        assert_eq!(v["ratio"], serde_json::json!(0.25));
+        assert!(v["ratio"].is_f64());
+        assert_eq!(v["cached"], serde_json::json!(true));
+        assert_eq!(v["route"], serde_json::json!("/users"));
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn test_duration_and_timestamp_fields() {
+        let path = "tests/output/typed_time_fields.log";
+        let logger = CustomLogger::new("typed_time_fields", path);
+        let started = chrono::DateTime::parse_from_rfc3339("2024-06-01T12:30:00.250+09:00")
+            .unwrap()
+            .with_timezone(&chrono::Utc);
+        let kvs = [
+            (
+                "elapsed_ms",
+                FieldValue::from(Duration::from_micros(1_500_900)),
+            ),
+            ("started", FieldValue::from(started)),
+        ];
+        logger.log(
+            &log::Record::builder()
+                .target("typed_time_fields")
+                .level(log::Level::Info)
+                .key_values(&kvs)
+                .args(format_args!("done"))
+                .build(),
+        );
+
+        let contents = std::fs::read_to_string(path).unwrap();
+        let v: serde_json::Value = serde_json::from_str(contents.trim_end()).unwrap();
+        assert_eq!(v["elapsed_ms"], serde_json::json!(1500));
+        assert_eq!(v["started"], serde_json::json!("2024-06-01T03:30:00.250Z"));
+    }
+}
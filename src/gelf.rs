@@ -0,0 +1,333 @@
+use crate::LogEntry;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::{
+    io,
+    net::{SocketAddr, ToSocketAddrs, UdpSocket},
+    sync::OnceLock,
+};
+
+/// the most chunks a GELF message may be split into
+const MAX_CHUNKS: usize = 128;
+
+/// the GELF chunk header: magic bytes, message id, sequence number and count
+const CHUNK_HEADER: usize = 12;
+
+/// map a log level name, in any case, to a syslog severity number
+pub(crate) fn syslog_level(severity: &str) -> u8 {
+    match severity.parse::<log::Level>() {
+        Ok(log::Level::Error) => 3,
+        Ok(log::Level::Warn) => 4,
+        Ok(log::Level::Debug) | Ok(log::Level::Trace) => 7,
+        Ok(log::Level::Info) | Err(_) => 6,
+    }
+}
+
+/// the name of this machine, as reported in the GELF `host` field
+pub(crate) fn hostname() -> &'static str {
+    static HOSTNAME: OnceLock<String> = OnceLock::new();
+    HOSTNAME.get_or_init(|| {
+        std::env::var("HOSTNAME")
+            .ok()
+            .or_else(|| std::fs::read_to_string("/etc/hostname").ok())
+            .or_else(|| std::env::var("COMPUTERNAME").ok())
+            .map(|name| name.trim().to_string())
+            .filter(|name| !name.is_empty())
+            .unwrap_or_else(|| "localhost".to_string())
+    })
+}
+
+/// the GELF 1.1 message for a serialized record
+///
+/// `message` becomes `short_message`, `severity` the syslog `level` and
+/// `timestamp` seconds since the epoch. Every other field is added with a
+/// `_` prefix; nested objects are flattened with `_` between the keys, and
+/// values that are neither strings nor numbers are written as JSON text.
+pub(crate) fn message(record: &Value, host: &str) -> Value {
+    let mut gelf = Map::new();
+    gelf.insert("version".to_string(), "1.1".into());
+    gelf.insert("host".to_string(), host.into());
+    let Value::Object(record) = record else {
+        gelf.insert("short_message".to_string(), record.to_string().into());
+        return Value::Object(gelf);
+    };
+    let text = |key: &str| record.get(key).and_then(Value::as_str).unwrap_or_default();
+    gelf.insert("short_message".to_string(), text("message").into());
+    if let Some(t) = crate::clock::Timestamp::parse(text("timestamp")) {
+        let seconds = t.epoch_micros() as f64 / 1e6;
+        gelf.insert("timestamp".to_string(), seconds.into());
+    }
+    gelf.insert("level".to_string(), syslog_level(text("severity")).into());
+    for (key, value) in record {
+        if !["message", "timestamp", "severity"].contains(&key.as_str()) {
+            add_field(&mut gelf, key, value);
+        }
+    }
+    Value::Object(gelf)
+}
+
+fn add_field(gelf: &mut Map<String, Value>, key: &str, value: &Value) {
+    match value {
+        Value::Object(object) => {
+            for (inner, value) in object {
+                add_field(gelf, &format!("{}_{}", key, inner), value);
+            }
+        }
+        Value::String(_) | Value::Number(_) => {
+            gelf.insert(field_name(key), value.clone());
+        }
+        other => {
+            gelf.insert(field_name(key), other.to_string().into());
+        }
+    }
+}
+
+/// `key` as a GELF additional field name: `_`-prefixed with only word
+/// characters, dots and dashes, and never the reserved `_id`
+fn field_name(key: &str) -> String {
+    let key: String = key
+        .chars()
+        .map(|c| match c {
+            c if c.is_ascii_alphanumeric() || c == '_' || c == '.' || c == '-' => c,
+            _ => '_',
+        })
+        .collect();
+    match key.as_str() {
+        "id" => "__id".to_string(),
+        _ => format!("_{}", key),
+    }
+}
+
+/// append the newline-terminated GELF message for `record` to `out`
+pub(crate) fn line<T: Serialize>(out: &mut Vec<u8>, record: &T, entry: &LogEntry) {
+    let record = serde_json::to_value(record).unwrap_or_else(|_| {
+        serde_json::to_value(LogEntry {
+            location: None,
+            fields: crate::entry::serialization_failed(),
+            ..entry.clone()
+        })
+        .unwrap()
+    });
+    serde_json::to_writer(&mut *out, &message(&record, hostname())).unwrap();
+    out.push(b'\n');
+}
+
+/// split `payload` into GELF chunks of at most `chunk_size` bytes each
+///
+/// Returns `None` if it would take more than the 128 chunks GELF allows.
+fn chunks(payload: &[u8], chunk_size: usize, id: u64) -> Option<Vec<Vec<u8>>> {
+    let body = chunk_size.saturating_sub(CHUNK_HEADER).max(1);
+    let count = payload.len().div_ceil(body);
+    if count > MAX_CHUNKS {
+        return None;
+    }
+    let chunks = payload
+        .chunks(body)
+        .enumerate()
+        .map(|(i, part)| {
+            let mut chunk = Vec::with_capacity(CHUNK_HEADER + part.len());
+            chunk.extend_from_slice(&[0x1e, 0x0f]);
+            chunk.extend_from_slice(&id.to_be_bytes());
+            chunk.push(i as u8);
+            chunk.push(count as u8);
+            chunk.extend_from_slice(part);
+            chunk
+        })
+        .collect();
+    Some(chunks)
+}
+
+/// A logger that sends records to Graylog as GELF messages over UDP
+///
+/// Messages larger than the chunk size are split into GELF chunks; a message
+/// that needs more than 128 chunks is dropped with a warning on stderr.
+pub struct GelfUdpLogger {
+    target: String,
+    socket: UdpSocket,
+    addr: SocketAddr,
+    host: String,
+    chunk_size: usize,
+}
+
+impl GelfUdpLogger {
+    /// send to the GELF UDP input at `addr`
+    /// # Arguments
+    /// * `target` - The target to match
+    /// * `addr` - The address of the Graylog input
+    /// # Example
+    /// ```
+    /// # use crate::loggers::*;
+    /// let logger = GelfUdpLogger::new("api", "127.0.0.1:12201")
+    ///     .unwrap()
+    ///     .host("api-1");
+    /// ```
+    pub fn new<A: ToSocketAddrs>(target: &str, addr: A) -> io::Result<GelfUdpLogger> {
+        let addr = addr
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "no address to send to"))?;
+        let local: SocketAddr = match addr {
+            SocketAddr::V4(_) => ([0, 0, 0, 0], 0).into(),
+            SocketAddr::V6(_) => ([0u16; 8], 0).into(),
+        };
+        Ok(GelfUdpLogger {
+            target: target.to_string(),
+            socket: UdpSocket::bind(local)?,
+            addr,
+            host: hostname().to_string(),
+            chunk_size: 8192,
+        })
+    }
+
+    /// set the `host` field of every message (default: the machine's hostname)
+    pub fn host(mut self, host: &str) -> GelfUdpLogger {
+        self.host = host.to_string();
+        self
+    }
+
+    /// set the largest datagram sent, including the 12-byte chunk header
+    /// (default: 8192)
+    ///
+    /// Lower it to about 1420 when the messages cross a WAN.
+    pub fn chunk_size(mut self, chunk_size: usize) -> GelfUdpLogger {
+        self.chunk_size = chunk_size.max(CHUNK_HEADER + 1);
+        self
+    }
+
+    /// send the GELF message for `entry`
+    fn send(&self, entry: &LogEntry) -> io::Result<()> {
+        let record = serde_json::to_value(entry).unwrap();
+        let payload = serde_json::to_vec(&message(&record, &self.host)).unwrap();
+        if payload.len() <= self.chunk_size {
+            self.socket.send_to(&payload, self.addr)?;
+            return Ok(());
+        }
+        let id = crate::clock::nanos_since_start() ^ (u64::from(std::process::id()) << 40);
+        let chunks = chunks(&payload, self.chunk_size, id).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "message of {} bytes needs more than 128 chunks",
+                    payload.len()
+                ),
+            )
+        })?;
+        for chunk in chunks {
+            self.socket.send_to(&chunk, self.addr)?;
+        }
+        Ok(())
+    }
+}
+
+impl log::Log for GelfUdpLogger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        metadata.target() == self.target
+    }
+
+    fn log(&self, record: &log::Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        let timestamp = crate::clock::timestamp();
+        let entry = LogEntry::from_record(record, &self.target, &timestamp);
+        if let Err(e) = self.send(&entry) {
+            eprintln!(
+                "GelfUdpLogger {}: cannot send record to {}: {}",
+                self.target, self.addr, e
+            );
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CustomLogger, Format};
+    use log::Log;
+
+    #[test]
+    fn test_gelf_format() {
+        let path = "tests/output/gelf.log";
+        let logger = CustomLogger::new("gelf", path)
+            .format(Format::Gelf)
+            .location(true)
+            .console(false);
+        let levels = [
+            (log::Level::Error, 3),
+            (log::Level::Warn, 4),
+            (log::Level::Info, 6),
+            (log::Level::Debug, 7),
+            (log::Level::Trace, 7),
+        ];
+        for (level, _) in levels {
+            logger.log(
+                &log::Record::builder()
+                    .target("gelf")
+                    .level(level)
+                    .key_values(&[("id", 7), ("status", 200)])
+                    .line(Some(12))
+                    .args(format_args!("disk {}", level))
+                    .build(),
+            );
+        }
+
+        let contents = std::fs::read_to_string(path).unwrap();
+        let messages: Vec<Value> = contents
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(messages.len(), levels.len());
+        for (message, (level, syslog)) in messages.iter().zip(levels) {
+            assert_eq!(message["version"], "1.1");
+            assert_eq!(message["host"], hostname());
+            assert_eq!(message["short_message"], format!("disk {}", level));
+            assert!(message["timestamp"].as_f64().unwrap() > 1.6e9);
+            assert_eq!(message["level"], syslog);
+            assert_eq!(message["_target"], "gelf");
+            assert_eq!(message["_status"], 200);
+            assert_eq!(message["__id"], 7);
+            assert_eq!(message["_location_line"], 12);
+            assert!(message.get("message").is_none());
+            assert!(message.get("severity").is_none());
+        }
+    }
+
+    #[test]
+    fn test_gelf_udp_chunking() {
+        let receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let logger = GelfUdpLogger::new("gelf_udp", receiver.local_addr().unwrap())
+            .unwrap()
+            .host("test-host")
+            .chunk_size(100);
+        let long = "x".repeat(500);
+        logger.log(
+            &log::Record::builder()
+                .target("gelf_udp")
+                .level(log::Level::Warn)
+                .args(format_args!("{}", long))
+                .build(),
+        );
+
+        let mut parts = Vec::new();
+        let mut buf = [0; 200];
+        loop {
+            let len = receiver.recv(&mut buf).unwrap();
+            assert!(len <= 100);
+            assert_eq!(buf[..2], [0x1e, 0x0f]);
+            let (sequence, count) = (buf[10], buf[11]);
+            parts.push((sequence, buf[CHUNK_HEADER..len].to_vec()));
+            if parts.len() == count as usize {
+                break;
+            }
+        }
+        parts.sort_by_key(|(sequence, _)| *sequence);
+        let payload: Vec<u8> = parts.into_iter().flat_map(|(_, part)| part).collect();
+        let message: Value = serde_json::from_slice(&payload).unwrap();
+        assert_eq!(message["short_message"], long);
+        assert_eq!(message["host"], "test-host");
+        assert_eq!(message["level"], 4);
+    }
+}
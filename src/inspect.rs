@@ -0,0 +1,138 @@
+//! The hook through which [`crate::Logger::self_test`] and
+//! [`crate::Logger::export_config`] reach the loggers inside a Logger
+//!
+//! `log::Log` has no call for this and loggers are stored as
+//! `Box<dyn log::Log>`, so the hook is a call to `enabled` for the reserved
+//! target [`TARGET`] while an inspection runs on the thread. `enabled` does
+//! no I/O in any logger, so loggers of other crates are only asked a
+//! question they answer with `false` or `true`. The loggers of this crate
+//! answer the hook themselves, and those wrapping other loggers pass a probe
+//! on with [`forward`]; a logger that does not answer is reported as a
+//! failure. Descriptions are not passed on, so only a CustomLogger, or a
+//! logger that is one CustomLogger with a different constructor, describes
+//! itself.
+
+use crate::{LoggerConfig, Sink};
+use std::{cell::RefCell, io};
+
+/// the target of the metadata passed to `enabled` to inspect a logger
+const TARGET: &str = "loggers::inspect";
+
+/// the sinks probed so far, by name, with the outcome of each
+type Probes = Vec<(String, io::Result<()>)>;
+
+enum Inspection {
+    Probe(Probes),
+    Describe(Vec<LoggerConfig>),
+}
+
+thread_local! {
+    static RUNNING: RefCell<Option<Inspection>> = const { RefCell::new(None) };
+}
+
+fn hook() -> log::Metadata<'static> {
+    log::Metadata::builder()
+        .target(TARGET)
+        .level(log::Level::Error)
+        .build()
+}
+
+/// run `inspection` on `logger`, returning it with the answer of every
+/// logger reached
+fn run(logger: &dyn log::Log, inspection: Inspection) -> Inspection {
+    struct Restore(Option<Inspection>);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            RUNNING.with(|running| *running.borrow_mut() = self.0.take());
+        }
+    }
+
+    let outer = RUNNING.with(|running| running.borrow_mut().replace(inspection));
+    let restore = Restore(outer);
+    logger.enabled(&hook());
+    let inspection = RUNNING.with(|running| running.borrow_mut().take());
+    drop(restore);
+    inspection.unwrap()
+}
+
+/// probe `logger`, returning the outcome for every sink it reached
+pub(crate) fn probe(logger: &dyn log::Log) -> Probes {
+    match run(logger, Inspection::Probe(Vec::new())) {
+        Inspection::Probe(probes) => probes,
+        Inspection::Describe(_) => unreachable!(),
+    }
+}
+
+/// the configurations `logger` describes itself with
+pub(crate) fn describe(logger: &dyn log::Log) -> Vec<LoggerConfig> {
+    match run(logger, Inspection::Describe(Vec::new())) {
+        Inspection::Describe(configs) => configs,
+        Inspection::Probe(_) => unreachable!(),
+    }
+}
+
+/// whether `metadata` is the hook of a running inspection rather than a
+/// question about a record
+pub(crate) fn is_hook(metadata: &log::Metadata) -> bool {
+    metadata.target() == TARGET && RUNNING.with(|running| running.borrow().is_some())
+}
+
+/// the number of probes reported so far, if a probe is running
+fn probes_len() -> Option<usize> {
+    RUNNING.with(|running| match &*running.borrow() {
+        Some(Inspection::Probe(probes)) => Some(probes.len()),
+        _ => None,
+    })
+}
+
+/// record the outcome of probing the sink `name`, if a probe is running
+pub(crate) fn report<F: FnOnce() -> io::Result<()>>(name: &str, probe: F) {
+    if probes_len().is_none() {
+        return;
+    }
+    let result = probe();
+    RUNNING.with(|running| {
+        if let Some(Inspection::Probe(probes)) = &mut *running.borrow_mut() {
+            probes.push((name.to_string(), result));
+        }
+    });
+}
+
+/// record the outcome of probing `sink`, which cannot be probed unless it
+/// implements [`Sink::probe`]
+pub(crate) fn report_sink(name: &str, sink: &dyn Sink) {
+    report(name, || sink.probe().unwrap_or_else(|| Err(unreached())));
+}
+
+/// add the configuration of the CustomLogger being described, if a
+/// description is running
+pub(crate) fn report_config<F: FnOnce() -> LoggerConfig>(config: F) {
+    let describing =
+        RUNNING.with(|running| matches!(&*running.borrow(), Some(Inspection::Describe(_))));
+    if !describing {
+        return;
+    }
+    let config = config();
+    RUNNING.with(|running| {
+        if let Some(Inspection::Describe(configs)) = &mut *running.borrow_mut() {
+            configs.push(config);
+        }
+    });
+}
+
+/// pass a running probe on to `logger`, wrapped by another logger as
+/// `name`, reporting it as a failure if it does not answer
+pub(crate) fn forward(name: &str, logger: &dyn log::Log) {
+    let Some(before) = probes_len() else {
+        return;
+    };
+    logger.enabled(&hook());
+    if probes_len() == Some(before) {
+        report(name, || Err(unreached()));
+    }
+}
+
+fn unreached() -> io::Error {
+    io::Error::new(io::ErrorKind::Unsupported, "the logger cannot be probed")
+}
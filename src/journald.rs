@@ -0,0 +1,191 @@
+use std::{io, os::unix::net::UnixDatagram, path::Path};
+
+/// the socket systemd-journald listens on for native protocol messages
+pub const JOURNALD_SOCKET: &str = "/run/systemd/journal/socket";
+
+/// A logger that sends records to systemd-journald using its native protocol
+///
+/// Each record carries `MESSAGE`, `PRIORITY` and `TARGET`, followed by the
+/// static fields added with [`JournaldLogger::field`] and the record's
+/// structured key-value fields. Field names are upper-cased and any character
+/// journald does not accept is replaced with `_`.
+pub struct JournaldLogger {
+    target: String,
+    socket: UnixDatagram,
+    socket_path: String,
+    fields: Vec<(String, String)>,
+}
+
+impl JournaldLogger {
+    /// connect to the local journald socket
+    /// # Arguments
+    /// * `target` - The target to match
+    pub fn new(target: &str) -> io::Result<JournaldLogger> {
+        Self::with_socket(target, JOURNALD_SOCKET)
+    }
+
+    /// send to a journald socket at a custom path
+    pub fn with_socket<P: AsRef<Path>>(target: &str, path: P) -> io::Result<JournaldLogger> {
+        Ok(JournaldLogger {
+            target: target.to_string(),
+            socket: UnixDatagram::unbound()?,
+            socket_path: path.as_ref().to_string_lossy().into_owned(),
+            fields: Vec::new(),
+        })
+    }
+
+    /// add a field sent with every record
+    pub fn field(mut self, key: &str, value: &str) -> JournaldLogger {
+        self.fields.push((field_name(key), value.to_string()));
+        self
+    }
+
+    /// send a single record
+    pub fn send(&self, record: &log::Record) -> io::Result<()> {
+        let payload = self.encode(record);
+        self.socket.send_to(&payload, &self.socket_path)?;
+        Ok(())
+    }
+
+    fn encode(&self, record: &log::Record) -> Vec<u8> {
+        let mut payload = Vec::new();
+        append_field(&mut payload, "MESSAGE", &record.args().to_string());
+        append_field(
+            &mut payload,
+            "PRIORITY",
+            &priority(record.level()).to_string(),
+        );
+        append_field(&mut payload, "TARGET", record.target());
+        for (key, value) in &self.fields {
+            append_field(&mut payload, key, value);
+        }
+        for (key, value) in crate::entry::record_fields(record) {
+            let value = match value {
+                serde_json::Value::String(s) => s,
+                other => other.to_string(),
+            };
+            append_field(&mut payload, &field_name(&key), &value);
+        }
+        payload
+    }
+}
+
+/// map a log level to a syslog priority as used by journald
+pub fn priority(level: log::Level) -> u8 {
+    match level {
+        log::Level::Error => 3,
+        log::Level::Warn => 4,
+        log::Level::Info => 6,
+        log::Level::Debug | log::Level::Trace => 7,
+    }
+}
+
+/// journald field names are upper-case ASCII letters, digits and `_`, and may not start with `_`
+fn field_name(key: &str) -> String {
+    let name: String = key
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    let name = name.trim_start_matches('_');
+    if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit()) {
+        format!("F{}", name)
+    } else {
+        name.to_string()
+    }
+}
+
+/// values containing a newline use the binary length-prefixed form
+fn append_field(payload: &mut Vec<u8>, key: &str, value: &str) {
+    payload.extend_from_slice(key.as_bytes());
+    if value.contains('\n') {
+        payload.push(b'\n');
+        payload.extend_from_slice(&(value.len() as u64).to_le_bytes());
+    } else {
+        payload.push(b'=');
+    }
+    payload.extend_from_slice(value.as_bytes());
+    payload.push(b'\n');
+}
+
+impl log::Log for JournaldLogger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        metadata.target() == self.target
+    }
+
+    fn log(&self, record: &log::Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        if let Err(e) = self.send(record) {
+            eprintln!("Cannot send record to journald: {}", e);
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_priority() {
+        assert_eq!(priority(log::Level::Error), 3);
+        assert_eq!(priority(log::Level::Warn), 4);
+        assert_eq!(priority(log::Level::Info), 6);
+        assert_eq!(priority(log::Level::Debug), 7);
+        assert_eq!(priority(log::Level::Trace), 7);
+    }
+
+    #[test]
+    fn test_send() {
+        let path =
+            std::env::temp_dir().join(format!("loggers-journald-{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let server = UnixDatagram::bind(&path).unwrap();
+
+        let logger = JournaldLogger::with_socket("journald", &path)
+            .unwrap()
+            .field("service", "api");
+        let kvs = [("request-id", "abc")];
+        logger
+            .send(
+                &log::Record::builder()
+                    .target("journald")
+                    .level(log::Level::Warn)
+                    .key_values(&kvs)
+                    .args(format_args!("line one\nline two"))
+                    .build(),
+            )
+            .unwrap();
+
+        let mut buf = [0u8; 1024];
+        let n = server.recv(&mut buf).unwrap();
+        let mut expected = b"MESSAGE\n".to_vec();
+        expected.extend_from_slice(&17u64.to_le_bytes());
+        expected.extend_from_slice(
+            b"line one\nline two\nPRIORITY=4\nTARGET=journald\nSERVICE=api\nREQUEST_ID=abc\n",
+        );
+        assert_eq!(&buf[..n], &expected[..]);
+        let _ = std::fs::remove_file(&path);
+
+        if Path::new(JOURNALD_SOCKET).exists() {
+            JournaldLogger::new("journald")
+                .unwrap()
+                .send(
+                    &log::Record::builder()
+                        .target("journald")
+                        .level(log::Level::Info)
+                        .args(format_args!("loggers journald test"))
+                        .build(),
+                )
+                .unwrap();
+        }
+    }
+}
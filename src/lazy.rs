@@ -0,0 +1,100 @@
+use serde::{Serialize, Serializer};
+use std::cell::{Cell, OnceCell};
+
+/// A structured field value computed only when a record is written
+///
+/// Created with [`lazy`]. The closure runs the first time a logger
+/// serializes the record, after its target and level checks have passed,
+/// and its result is reused by every other logger the record reaches.
+pub struct Lazy<F> {
+    f: Cell<Option<F>>,
+    value: OnceCell<serde_json::Value>,
+}
+
+/// defer computing a field value until the record is actually written
+///
+/// Works as the value of a key-value field in the `log` macros and in
+/// [`jlog!`](crate::jlog).
+/// # Example
+/// ```
+/// # use loggers::*;
+/// # fn expensive_report() -> serde_json::Value { serde_json::json!({}) }
+/// log::debug!(target: "app", report = lazy(expensive_report); "state dump");
+/// jlog!(target: "app", level: Debug, { "report" => lazy(expensive_report) }, "state dump");
+/// ```
+pub fn lazy<F>(f: F) -> Lazy<F>
+where
+    F: FnOnce() -> serde_json::Value,
+{
+    Lazy {
+        f: Cell::new(Some(f)),
+        value: OnceCell::new(),
+    }
+}
+
+impl<F> Lazy<F>
+where
+    F: FnOnce() -> serde_json::Value,
+{
+    fn get(&self) -> &serde_json::Value {
+        self.value.get_or_init(|| match self.f.take() {
+            Some(f) => f(),
+            None => serde_json::Value::Null,
+        })
+    }
+}
+
+impl<F> Serialize for Lazy<F>
+where
+    F: FnOnce() -> serde_json::Value,
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.get().serialize(serializer)
+    }
+}
+
+impl<F> log::kv::ToValue for Lazy<F>
+where
+    F: FnOnce() -> serde_json::Value,
+{
+    fn to_value(&self) -> log::kv::Value<'_> {
+        log::kv::Value::from_serde(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{read_log, CustomLogger};
+    use log::Log;
+
+    #[test]
+    fn test_lazy_field() {
+        let path = "tests/output/lazy.log";
+        let logger = CustomLogger::new("lazy", path).min_level(log::Level::Warn);
+        let calls = Cell::new(0);
+        let report = || {
+            calls.set(calls.get() + 1);
+            serde_json::json!({"open": 3})
+        };
+
+        for level in [log::Level::Debug, log::Level::Warn] {
+            let kvs = [("report", lazy(report))];
+            let record = log::Record::builder()
+                .target("lazy")
+                .level(level)
+                .key_values(&kvs)
+                .args(format_args!("state"))
+                .build();
+            // every logger the record reaches shares one evaluation
+            logger.log(&record);
+            logger.log(&record);
+            assert_eq!(calls.get(), if level == log::Level::Debug { 0 } else { 1 });
+        }
+
+        let entries: Vec<_> = read_log(path, false).map(|e| e.unwrap()).collect();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].severity, "WARN");
+        assert_eq!(entries[1].fields["report"], serde_json::json!({"open": 3}));
+    }
+}
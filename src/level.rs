@@ -0,0 +1,189 @@
+use crate::CustomLogger;
+use std::{cell::Cell, marker::PhantomData};
+
+thread_local! {
+    static BOOST: Cell<Option<log::LevelFilter>> = const { Cell::new(None) };
+}
+
+/// A logger that matches records by level alone, whatever their target
+///
+/// Accepts every record at least as severe as its level, so
+/// `LevelLogger::new(Level::Error, "error.log")` collects the errors of all
+/// targets next to the target loggers of a [`Logger`](crate::Logger).
+/// Records are written to the file only, in the same format as by
+/// [`CustomLogger`].
+pub struct LevelLogger {
+    inner: CustomLogger,
+}
+
+impl LevelLogger {
+    /// create a LevelLogger
+    /// # Arguments
+    /// * `level` - The least severe level to accept
+    /// * `filepath` - The filepath to write the log
+    /// # Example
+    /// ```
+    /// # use crate::loggers::*;
+    /// let mut logger = Logger::new();
+    /// logger.add_logger(Box::new(CustomLogger::new("api", "tests/output/system.log")));
+    /// logger.add_logger(Box::new(LevelLogger::new(
+    ///     log::Level::Error,
+    ///     "tests/output/error.log",
+    /// )));
+    /// ```
+    pub fn new(level: log::Level, filepath: &str) -> LevelLogger {
+        let inner = CustomLogger::new("", filepath)
+            .any_target(true)
+            .min_level(level)
+            .console(false);
+        LevelLogger { inner }
+    }
+}
+
+impl log::Log for LevelLogger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        self.inner.enabled(metadata)
+    }
+
+    fn log(&self, record: &log::Record) {
+        self.inner.log(record);
+    }
+
+    fn flush(&self) {
+        self.inner.flush();
+    }
+}
+
+/// Keeps the level boost of [`with_boosted_level`] in effect until dropped
+#[must_use = "the boost ends as soon as the guard is dropped"]
+pub struct BoostGuard {
+    previous: Option<log::LevelFilter>,
+    _not_send: PhantomData<*const ()>,
+}
+
+/// let CustomLoggers accept records down to `level` on the current thread
+/// until the guard is dropped, whatever their own minimum level
+///
+/// The boost only lowers the least severe level accepted; a logger's target
+/// and its `max_level` still apply. Records handed to an [`AsyncLogger`]
+/// are filtered on its worker thread and are not boosted.
+/// # Example
+/// ```
+/// # use crate::loggers::*;
+/// {
+///     let _boost = with_boosted_level(log::LevelFilter::Trace);
+///     log::trace!(target: "api", "only logged inside this block");
+/// }
+/// ```
+///
+/// [`AsyncLogger`]: crate::AsyncLogger
+pub fn with_boosted_level(level: log::LevelFilter) -> BoostGuard {
+    let previous = BOOST.with(|boost| boost.replace(Some(level)));
+    BoostGuard {
+        previous,
+        _not_send: PhantomData,
+    }
+}
+
+/// the level boost in effect on the current thread
+pub(crate) fn boosted() -> Option<log::LevelFilter> {
+    BOOST.with(Cell::get)
+}
+
+/// the prefix of the environment variables that override the level of a
+/// single target, as in `LOGGERS_LEVEL_api=debug`
+pub const LEVEL_VAR_PREFIX: &str = "LOGGERS_LEVEL_";
+
+/// the level set for `target` by its `LOGGERS_LEVEL_<target>` variable
+///
+/// Characters of the target other than ASCII letters, digits and `_` are
+/// replaced by `_` in the variable name, so `app::db` is overridden by
+/// `LOGGERS_LEVEL_app__db`. A value that is not a level is reported on
+/// stderr and ignored.
+pub(crate) fn env_override(target: &str) -> Option<log::LevelFilter> {
+    if target.is_empty() {
+        return None;
+    }
+    let name: String = target
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    let var = format!("{}{}", LEVEL_VAR_PREFIX, name);
+    let value = std::env::var(&var).ok()?;
+    match value.trim().parse::<log::LevelFilter>() {
+        Ok(level) => Some(level),
+        Err(_) => {
+            eprintln!("{}: ignoring unknown level {:?}", var, value);
+            None
+        }
+    }
+}
+
+impl Drop for BoostGuard {
+    fn drop(&mut self) {
+        BOOST.with(|boost| boost.set(self.previous));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{read_log, Logger};
+
+    #[test]
+    fn test_level_logger() {
+        let errors = "tests/output/level_errors.log";
+        let api = "tests/output/level_api.log";
+        let mut logger = Logger::new();
+        logger.add_logger(Box::new(CustomLogger::new("api", api)));
+        logger.add_logger(Box::new(LevelLogger::new(log::Level::Error, errors)));
+
+        logger.log_record(log::Level::Info, "api", "request", &[]);
+        logger.log_record(log::Level::Error, "api", "api failed", &[]);
+        logger.log_record(log::Level::Error, "db", "db failed", &[]);
+        logger.log_record(log::Level::Warn, "db", "db slow", &[]);
+
+        let read = |path| -> Vec<(String, String)> {
+            read_log(path, false)
+                .map(|e| e.unwrap())
+                .map(|e| (e.target, e.message))
+                .collect()
+        };
+        assert_eq!(
+            read(errors),
+            vec![
+                ("api".to_string(), "api failed".to_string()),
+                ("db".to_string(), "db failed".to_string()),
+            ]
+        );
+        assert_eq!(read(api).len(), 2);
+    }
+
+    #[test]
+    fn test_with_boosted_level() {
+        let path = "tests/output/boosted_level.log";
+        let logger = CustomLogger::new("boosted_level", path)
+            .min_level(log::Level::Info)
+            .console(false);
+        let trace = |message: &str| {
+            log::Log::log(
+                &logger,
+                &log::Record::builder()
+                    .target("boosted_level")
+                    .level(log::Level::Trace)
+                    .args(format_args!("{}", message))
+                    .build(),
+            )
+        };
+
+        trace("before");
+        {
+            let _boost = with_boosted_level(log::LevelFilter::Trace);
+            trace("boosted");
+        }
+        trace("after");
+
+        let messages: Vec<String> = read_log(path, false).map(|e| e.unwrap().message).collect();
+        assert_eq!(messages, vec!["boosted"]);
+    }
+}
@@ -8,6 +8,7 @@
 //! # Examples
 //! ```rust
 //! use loggers::*;
+//! use log::{debug, info};
 //! let mut logger = Logger::new();
 //! logger.add_logger(Box::new(CustomLogger::new(
 //!     "test",
@@ -23,20 +24,316 @@
 //! info!(target:"test", "Hello, world!");
 //! debug!("Default");
 //! ```
+//!
+//! Records logged without an explicit target (`debug!("Default")` above) have
+//! the calling module's path as their target, so they only reach a
+//! `CustomLogger` whose target matches it exactly, or one configured with
+//! [`CustomLogger::match_module_default`] for a parent module.
+//!
+//! A fallback only receives records from targets other than its own when it is
+//! configured with [`CustomLogger::any_target`]; add `.console(false)` to archive
+//! them without printing.
+//!
+//! The [`trace_dev!`] and [`debug_dev!`] macros log like `log::trace!` and
+//! `log::debug!` when the `verbose` feature is enabled and compile to nothing
+//! otherwise, removing development logging from release builds entirely.
+//!
+//! Setting the `LOGGERS_DISABLE` environment variable to anything but `0` or
+//! `false` silences every installed [`Logger`] without a restart. The variable
+//! is re-read at most once per second ([`DISABLE_RECHECK`]).
+//!
+//! A `LOGGERS_LEVEL_<target>` variable overrides the least severe level of
+//! the `CustomLogger`s of one target, e.g. `LOGGERS_LEVEL_api=debug`; see
+//! [`CustomLogger::min_level`] for how it combines with the other filters.
+//!
+//! Disabling the default `chrono` feature drops the `chrono` dependency.
+//! Timestamps keep their RFC3339 format with milliseconds but are written in
+//! UTC, and dates, such as those of daily rotation, are UTC dates.
+//!
+//! The `s3` feature adds `S3Sink`, which uploads records in batches as
+//! NDJSON objects to an S3-compatible object store.
+
+use std::{
+    fs::File,
+    io::prelude::*,
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+        Arc, Mutex, RwLock,
+    },
+};
+
+#[macro_use]
+mod macros;
+
+mod aggregate;
+mod async_logger;
+mod chain;
+mod clock;
+mod coalesce;
+mod color;
+mod config;
+mod disable;
+mod entry;
+mod error;
+#[cfg(windows)]
+mod eventlog;
+mod field;
+mod gelf;
+mod inspect;
+#[cfg(all(feature = "journald", target_os = "linux"))]
+mod journald;
+mod lazy;
+mod level;
+mod metrics;
+#[cfg(feature = "msgpack")]
+mod msgpack;
+mod presets;
+#[cfg(feature = "protobuf")]
+mod protobuf;
+mod reader;
+mod ring;
+mod rotation;
+mod routing;
+#[cfg(feature = "s3")]
+mod s3;
+mod shard;
+mod sink;
+pub mod span;
+mod tcp;
+mod tee;
+pub mod testing;
+mod throttle;
+
+pub use async_logger::{AsyncLogger, DropPolicy};
+pub use chain::{verify_chain, verify_chain_from, GENESIS_HASH};
+pub use color::ColorMode;
+pub use config::{Config, LoggerConfig};
+pub use disable::{DISABLE_RECHECK, DISABLE_VAR};
+pub use entry::{Location, LogEntry, SCHEMA_VERSION};
+pub use error::{error_chain, Error};
+#[cfg(windows)]
+pub use eventlog::WindowsEventLogger;
+pub use field::FieldValue;
+pub use gelf::GelfUdpLogger;
+#[cfg(all(feature = "journald", target_os = "linux"))]
+pub use journald::JournaldLogger;
+pub use lazy::{lazy, Lazy};
+pub use level::{with_boosted_level, BoostGuard, LevelLogger, LEVEL_VAR_PREFIX};
+#[cfg(feature = "msgpack")]
+pub use msgpack::read_msgpack;
+pub use presets::PROD_MAX_SIZE;
+#[cfg(feature = "protobuf")]
+pub use protobuf::read_protobuf;
+pub use reader::{read_log, read_segments, repair_ndjson, LogRecord};
+pub use ring::RingBufferLogger;
+pub use routing::{FieldRouter, RoutingLogger};
+#[cfg(feature = "s3")]
+pub use s3::S3Sink;
+pub use shard::{read_shards, ShardedLogger};
+pub use sink::{FileSink, MemorySink, MultiSink, Sink, StdoutSink, WriterSink};
+pub use tcp::{Framing, TcpLogger};
+pub use tee::TeeLogger;
+
+#[doc(hidden)]
+pub use log as __log;
+
+/// the Logger installed by [`Logger::install`], if it was not reset
+static INSTALLED: RwLock<Option<Arc<Logger>>> = RwLock::new(None);
+/// whether [`INSTALLED_PROXY`] is the global `log` logger
+static PROXY_SET: AtomicBool = AtomicBool::new(false);
+static INSTALLED_PROXY: InstalledProxy = InstalledProxy;
+
+/// the global `log` logger, forwarding to the Logger in [`INSTALLED`]
+struct InstalledProxy;
+
+fn installed() -> Option<Arc<Logger>> {
+    INSTALLED.read().unwrap().clone()
+}
+
+impl log::Log for InstalledProxy {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        installed().is_some_and(|logger| logger.enabled(metadata))
+    }
+
+    fn log(&self, record: &log::Record) {
+        if let Some(logger) = installed() {
+            logger.log(record);
+        }
+    }
+
+    fn flush(&self) {
+        if let Some(logger) = installed() {
+            logger.flush();
+        }
+    }
+}
+
+/// flush the installed logger and stop its background threads
+///
+/// Call this at the end of `main` so records queued by an [`AsyncLogger`]
+/// are written before the process exits. Records logged afterwards are
+/// written synchronously.
+pub fn shutdown() {
+    if let Some(logger) = installed() {
+        log::Log::flush(logger.as_ref());
+    }
+    async_logger::shutdown_workers();
+}
+
+/// build a record from explicit parts and dispatch it to the installed `log` logger
+///
+/// See [`Logger::log_record`].
+pub fn log_record(
+    level: log::Level,
+    target: &str,
+    message: &str,
+    fields: &[(&str, serde_json::Value)],
+) {
+    dispatch_record(log::logger(), level, target, message, fields);
+}
+
+#[doc(hidden)]
+pub fn __log_struct<T: serde::Serialize + ?Sized>(
+    level: log::Level,
+    target: &str,
+    value: &T,
+    message: &str,
+) {
+    let fields = entry::struct_fields(value);
+    let fields: Vec<(&str, serde_json::Value)> = fields
+        .iter()
+        .map(|(key, value)| (key.as_str(), value.clone()))
+        .collect();
+    dispatch_record(log::logger(), level, target, message, &fields);
+}
+
+fn dispatch_record(
+    logger: &dyn log::Log,
+    level: log::Level,
+    target: &str,
+    message: &str,
+    fields: &[(&str, serde_json::Value)],
+) {
+    let kvs: Vec<(&str, log::kv::Value)> = fields
+        .iter()
+        .map(|(key, value)| (*key, log::kv::Value::from_serde(value)))
+        .collect();
+    logger.log(
+        &log::Record::builder()
+            .level(level)
+            .target(target)
+            .key_values(&kvs)
+            .args(format_args!("{}", message))
+            .build(),
+    );
+}
+
+/// Counters of what a CustomLogger has written to its file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Stats {
+    /// bytes written, including line terminators, after compression if enabled
+    pub bytes_written: u64,
+    /// records written
+    pub records_written: u64,
+}
+
+/// How a CustomLogger encodes the records it writes to its file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize)]
+pub enum Format {
+    /// one JSON object per line
+    #[default]
+    Json,
+    /// MessagePack maps, each prefixed with its length as a 4-byte
+    /// big-endian integer; read them back with [`read_msgpack`]
+    #[cfg(feature = "msgpack")]
+    MessagePack,
+    /// `LogEntry` protobuf messages as defined in `proto/loggers.proto`,
+    /// each prefixed with its length as a 4-byte big-endian integer; read
+    /// them back with [`read_protobuf`]
+    #[cfg(feature = "protobuf")]
+    Protobuf,
+    /// one GELF 1.1 message per line for Graylog, with the syslog `level`,
+    /// `short_message`, `timestamp` in seconds and `_`-prefixed fields;
+    /// see [`GelfUdpLogger`] to send them over UDP
+    Gelf,
+}
+
+/// How a CustomLogger prints records to the console
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize)]
+pub enum ConsoleFormat {
+    /// `[LEVEL] target timestamp - message`, colored if enabled
+    #[default]
+    Human,
+    /// the JSON record, one per line
+    Json,
+    /// `level=info ts=... target=... msg=... key=value` logfmt lines
+    Logfmt,
+    /// the human line followed by the location and each field of the record
+    /// but `schema_version` on their own indented lines
+    Pretty,
+}
+
+/// What a Logger does with a record no logger matched when it has no fallback
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize)]
+pub enum NoMatchPolicy {
+    /// drop the record
+    #[default]
+    Silent,
+    /// drop the record and print a warning naming its target to stderr
+    Warn,
+    /// print the record as JSON to stderr
+    Stderr,
+}
+
+/// Which of the matching loggers a Logger hands a record to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize)]
+pub enum DispatchMode {
+    /// every logger whose `enabled` returns true
+    #[default]
+    Broadcast,
+    /// only the first matching logger, in the order they were added
+    FirstMatch,
+}
 
-use chrono::{Local, SecondsFormat};
-use std::{fs::File, io::prelude::*, path::Path};
 pub struct Logger {
     loggers: Vec<Box<dyn log::Log>>,
     fallback: Option<Box<dyn log::Log>>,
+    dispatch_mode: DispatchMode,
+    run_id: Option<String>,
+    audit: Option<Box<dyn log::Log>>,
+    no_match_policy: NoMatchPolicy,
+    records: Mutex<std::collections::BTreeMap<(String, log::Level), u64>>,
 }
 
 impl Logger {
     pub fn new() -> Logger {
-        return Logger {
+        Logger {
             loggers: Vec::new(),
             fallback: None,
-        };
+            dispatch_mode: DispatchMode::Broadcast,
+            run_id: None,
+            audit: None,
+            no_match_policy: NoMatchPolicy::Silent,
+            records: Mutex::new(Default::default()),
+        }
+    }
+
+    /// create a Logger that adds a `run_id` field to every record
+    ///
+    /// The field reaches all registered loggers and the fallback, tying
+    /// together the records one process run writes across files.
+    /// # Example
+    /// ```
+    /// # use crate::loggers::*;
+    /// let mut logger = Logger::with_run_id("nightly-2024-06-01");
+    /// logger.add_logger(Box::new(CustomLogger::new("test", "system.log")));
+    /// ```
+    pub fn with_run_id(run_id: &str) -> Logger {
+        Logger {
+            run_id: Some(run_id.to_string()),
+            ..Logger::new()
+        }
     }
 
     /// add a CustomLogger to the logger
@@ -53,7 +350,51 @@ impl Logger {
         self.loggers.push(logger);
     }
 
+    /// check that the file of every CustomLogger can be written to
+    ///
+    /// Each file is opened for appending without creating it or writing a
+    /// record, which catches missing permissions or a file or directory
+    /// removed since startup. Returns the path and error of every file that
+    /// failed. The loggers, the fallback and the audit logger are probed,
+    /// including the CustomLoggers and sinks inside the loggers and sinks of
+    /// this crate that wrap them, such as [`TeeLogger`] or [`AsyncLogger`].
+    /// A logger or sink that cannot be probed, such as one from another
+    /// crate, a [`TcpLogger`] or a [`RoutingLogger`] that has not opened a
+    /// file yet, is returned as failed with [`std::io::ErrorKind::Unsupported`]
+    /// under its position, e.g. `logger 2`, or its name.
+    /// # Example
+    /// ```
+    /// # use crate::loggers::*;
+    /// let mut logger = Logger::new();
+    /// logger.add_logger(Box::new(CustomLogger::new("test", "tests/output/system.log")));
+    /// if let Err(failed) = logger.self_test() {
+    ///     for (path, e) in failed {
+    ///         eprintln!("cannot write {}: {}", path, e);
+    ///     }
+    /// }
+    /// ```
+    pub fn self_test(&self) -> Result<(), Vec<(String, std::io::Error)>> {
+        let failed: Vec<(String, std::io::Error)> = inspect::probe(self)
+            .into_iter()
+            .filter_map(|(path, result)| result.err().map(|e| (path, e)))
+            .collect();
+        if failed.is_empty() {
+            Ok(())
+        } else {
+            Err(failed)
+        }
+    }
+
     /// set a fallback logger::CustomLogger
+    ///
+    /// The fallback receives the records that no other logger matched. It
+    /// still applies its own filter, so a CustomLogger fallback needs
+    /// [`CustomLogger::any_target`] to accept records of other targets.
+    ///
+    /// Records reach the fallback with their own target and an
+    /// `unmatched: true` field, so misrouted records can be found in its
+    /// file; a CustomLogger writes the record's target unless configured
+    /// with [`TargetField::Logger`].
     /// # Arguments
     /// * `fallback::CustomLogger` - The fallback logger
     /// # Example
@@ -66,150 +407,3209 @@ impl Logger {
     pub fn set_fallback(&mut self, fallback: Box<dyn log::Log>) {
         self.fallback = Some(fallback);
     }
+
+    /// set a logger that receives a copy of every record, matched or not
+    ///
+    /// Unlike the fallback, the audit logger sees all records before they are
+    /// dispatched to the others. It still applies its own filter, so a
+    /// CustomLogger used here needs [`CustomLogger::any_target`]; adding
+    /// [`CustomLogger::hash_chain`] makes the copy tamper-evident.
+    /// # Example
+    /// ```
+    /// # use crate::loggers::*;
+    /// let mut logger = Logger::new();
+    /// logger.set_audit(Box::new(
+    ///     CustomLogger::append("audit", "tests/output/audit.log")
+    ///         .unwrap()
+    ///         .any_target(true)
+    ///         .hash_chain(true)
+    ///         .console(false),
+    /// ));
+    /// ```
+    pub fn set_audit(&mut self, audit: Box<dyn log::Log>) {
+        self.audit = Some(audit);
+    }
+
+    /// set what happens to records that match no logger while no fallback is set
+    /// # Example
+    /// ```
+    /// # use crate::loggers::*;
+    /// let mut logger = Logger::new();
+    /// logger.set_no_match_policy(NoMatchPolicy::Warn);
+    /// ```
+    pub fn set_no_match_policy(&mut self, policy: NoMatchPolicy) {
+        self.no_match_policy = policy;
+    }
+
+    /// the counters of this logger in the Prometheus text exposition format
+    ///
+    /// `loggers_records_total` counts the records dispatched by this logger
+    /// per `target` and `level`; `loggers_bytes_written_total` and
+    /// `loggers_records_written_total` count what every live CustomLogger in
+    /// the process has written, per `file`.
+    /// # Example
+    /// ```
+    /// # use crate::loggers::*;
+    /// let logger = Logger::new();
+    /// logger.log_record(log::Level::Info, "api", "request", &[]);
+    /// assert!(logger
+    ///     .prometheus_metrics()
+    ///     .contains("loggers_records_total{target=\"api\",level=\"INFO\"} 1"));
+    /// ```
+    pub fn prometheus_metrics(&self) -> String {
+        metrics::render(&self.records.lock().unwrap())
+    }
+
+    /// flush only the loggers that accept records of `target`
+    ///
+    /// A logger accepts the target if it is enabled for it at any level. The
+    /// fallback and the audit logger are flushed under the same condition.
+    /// # Example
+    /// ```
+    /// # use crate::loggers::*;
+    /// let mut logger = Logger::new();
+    /// logger.add_logger(Box::new(CustomLogger::new("audit", "tests/output/audit.log")));
+    /// logger.add_logger(Box::new(CustomLogger::new("api", "tests/output/api.log")));
+    /// logger.flush_target("audit");
+    /// ```
+    pub fn flush_target(&self, target: &str) {
+        let accepts = |logger: &dyn log::Log| {
+            [
+                log::Level::Error,
+                log::Level::Warn,
+                log::Level::Info,
+                log::Level::Debug,
+                log::Level::Trace,
+            ]
+            .into_iter()
+            .any(|level| {
+                logger.enabled(&log::Metadata::builder().target(target).level(level).build())
+            })
+        };
+        let others = self.fallback.iter().chain(self.audit.iter());
+        for logger in self.loggers.iter().chain(others) {
+            if accepts(logger.as_ref()) {
+                logger.flush();
+            }
+        }
+    }
+
+    /// set how records matching several loggers are dispatched
+    /// # Example
+    /// ```
+    /// # use crate::loggers::*;
+    /// let mut logger = Logger::new();
+    /// logger.set_dispatch_mode(DispatchMode::FirstMatch);
+    /// ```
+    pub fn set_dispatch_mode(&mut self, mode: DispatchMode) {
+        self.dispatch_mode = mode;
+    }
+
+    /// build a record from explicit parts and dispatch it to the registered loggers
+    ///
+    /// Useful for replaying events without the `log` macros. The record goes
+    /// through the same matching as any other, and `fields` become structured
+    /// fields of the record.
+    /// # Example
+    /// ```
+    /// # use crate::loggers::*;
+    /// let logger = Logger::new();
+    /// logger.log_record(
+    ///     log::Level::Info,
+    ///     "replay",
+    ///     "user logged in",
+    ///     &[("user", serde_json::json!(42))],
+    /// );
+    /// ```
+    pub fn log_record(
+        &self,
+        level: log::Level,
+        target: &str,
+        message: &str,
+        fields: &[(&str, serde_json::Value)],
+    ) {
+        dispatch_record(self, level, target, message, fields);
+    }
+
+    /// install this logger as the global `log` logger
+    ///
+    /// The max level is set to Trace so every record reaches the registered
+    /// loggers, which apply their own level filters.
+    ///
+    /// The `log` crate accepts a global logger only once per process, so
+    /// this fails if another logger is installed, including a previous
+    /// Logger, until that one is removed with [`Logger::reset`].
+    pub fn install(self) -> Result<(), log::SetLoggerError> {
+        let mut installed = INSTALLED.write().unwrap();
+        if !PROXY_SET.load(Ordering::Relaxed) || installed.is_some() {
+            log::set_logger(&INSTALLED_PROXY)?;
+            PROXY_SET.store(true, Ordering::Relaxed);
+        }
+        *installed = Some(Arc::new(self));
+        log::set_max_level(log::LevelFilter::Trace);
+        Ok(())
+    }
+
+    /// flush and remove the Logger installed by [`Logger::install`], so
+    /// another one can be installed
+    ///
+    /// Tests that each configure their own Logger in one process use this to
+    /// start fresh: the removed Logger's loggers, fallback and audit logger
+    /// are dropped and receive no more records. Records logged before the
+    /// next install are discarded.
+    /// # Example
+    /// ```
+    /// # use crate::loggers::*;
+    /// let mut logger = Logger::new();
+    /// logger.add_logger(Box::new(CustomLogger::new("old", "tests/output/old.log")));
+    /// logger.install().expect("Failed to set logger");
+    ///
+    /// Logger::reset();
+    /// let mut logger = Logger::new();
+    /// logger.add_logger(Box::new(CustomLogger::new("new", "tests/output/new.log")));
+    /// logger.install().expect("Failed to set logger");
+    /// ```
+    pub fn reset() {
+        let removed = INSTALLED.write().unwrap().take();
+        if let Some(logger) = removed {
+            log::Log::flush(logger.as_ref());
+        }
+    }
+}
+
+impl Default for Logger {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl log::Log for Logger {
-    fn enabled(&self, _: &log::Metadata) -> bool {
-        return true;
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        if inspect::is_hook(metadata) {
+            for (i, logger) in self.loggers.iter().enumerate() {
+                inspect::forward(&format!("logger {}", i), logger.as_ref());
+            }
+            if let Some(fallback) = &self.fallback {
+                inspect::forward("fallback", fallback.as_ref());
+            }
+            if let Some(audit) = &self.audit {
+                inspect::forward("audit", audit.as_ref());
+            }
+            return false;
+        }
+        !disable::disabled()
     }
 
     fn log(&self, record: &log::Record) {
+        if disable::disabled() {
+            return;
+        }
+        if testing::intercept(record) {
+            return;
+        }
+        // every logger the record reaches stamps it with the same timestamp
+        clock::with_shared_timestamp(|| match &self.run_id {
+            Some(run_id) => {
+                let key_values = ExtraField {
+                    inner: record.key_values(),
+                    key: "run_id",
+                    value: run_id.into(),
+                };
+                self.dispatch(&record.to_builder().key_values(&key_values).build());
+            }
+            None => self.dispatch(record),
+        })
+    }
+
+    fn flush(&self) {
+        for logger in &self.loggers {
+            logger.flush();
+        }
+        if let Some(fallback) = &self.fallback {
+            fallback.flush();
+        }
+        if let Some(audit) = &self.audit {
+            audit.flush();
+        }
+    }
+}
+
+/// the key-values of a record followed by one more field
+struct ExtraField<'a> {
+    inner: &'a dyn log::kv::Source,
+    key: &'a str,
+    value: log::kv::Value<'a>,
+}
+
+impl log::kv::Source for ExtraField<'_> {
+    fn visit<'kvs>(
+        &'kvs self,
+        visitor: &mut dyn log::kv::VisitSource<'kvs>,
+    ) -> Result<(), log::kv::Error> {
+        self.inner.visit(visitor)?;
+        visitor.visit_pair(self.key.into(), log::kv::ToValue::to_value(&self.value))
+    }
+}
+
+impl Logger {
+    /// hand `record` to the matching loggers, or to the fallback if none match
+    fn dispatch(&self, record: &log::Record) {
+        *self
+            .records
+            .lock()
+            .unwrap()
+            .entry((record.target().to_string(), record.level()))
+            .or_insert(0) += 1;
+        if let Some(audit) = &self.audit {
+            audit.log(record);
+        }
         let mut logged = false;
 
         for logger in &self.loggers {
             if logger.enabled(record.metadata()) {
                 logger.log(record);
                 logged = true;
+                if self.dispatch_mode == DispatchMode::FirstMatch {
+                    break;
+                }
             }
         }
 
-        if !logged {
-            if let Some(fallback) = &self.fallback {
-                fallback.log(record);
+        if logged {
+            return;
+        }
+        match &self.fallback {
+            Some(fallback) => {
+                let key_values = ExtraField {
+                    inner: record.key_values(),
+                    key: "unmatched",
+                    value: true.into(),
+                };
+                fallback.log(&record.to_builder().key_values(&key_values).build());
             }
+            None => match self.no_match_policy {
+                NoMatchPolicy::Silent => {}
+                NoMatchPolicy::Warn => eprintln!(
+                    "No logger matched target {:?}, dropping record",
+                    record.target()
+                ),
+                NoMatchPolicy::Stderr => {
+                    let timestamp = clock::timestamp();
+                    let entry = LogEntry::from_record(record, record.target(), &timestamp);
+                    eprintln!("{}", serde_json::to_string(&entry).unwrap());
+                }
+            },
         }
     }
-
-    fn flush(&self) {}
 }
 
 pub struct CustomLogger {
     target: String,
     filepath: Option<String>,
+    file: Mutex<Option<File>>,
+    location: bool,
+    function: bool,
+    module_fields: bool,
+    match_module_default: bool,
+    // the least severe level accepted, as a `log::LevelFilter`
+    min_level: AtomicUsize,
+    max_level: log::Level,
+    fsync: bool,
+    coalesce: Option<coalesce::FlushCoalescer>,
+    aggregate: Option<aggregate::Aggregator>,
+    console: bool,
+    color_mode: ColorMode,
+    color: bool,
+    // escape sequences replacing the default color of some levels
+    color_map: Vec<(log::Level, String)>,
+    max_size: Option<u64>,
+    level_case: LevelCase,
+    level_case_in_json: bool,
+    escape_mode: EscapeMode,
+    compress_active: bool,
+    target_field: TargetField,
+    hash_chain: Option<Mutex<String>>,
+    rotate_daily: bool,
+    segment_date: Mutex<Option<clock::Date>>,
+    renames: Vec<(String, String)>,
+    allowed_fields: Option<Vec<String>>,
+    max_field_len: Option<usize>,
+    message_transform: Option<MessageTransform>,
+    watch_inode: bool,
+    indent: usize,
+    sort_keys: bool,
+    split_timestamp: bool,
+    any_target: bool,
+    emit_bom: bool,
+    every_n: Option<throttle::EveryN>,
+    sampler: Option<throttle::Sampler>,
+    adaptive: Option<throttle::AdaptiveSampler>,
+    counters: std::sync::Arc<metrics::FileCounters>,
+    format: Format,
+    console_format: ConsoleFormat,
+    max_lines: Option<u64>,
+    // records in the current file, counted only when max_lines is set
+    lines: AtomicU64,
+    monotonic: bool,
+    produced_at: bool,
+    file_mode: Option<u32>,
+    trace_fields: Option<(String, String, span::IdFormat)>,
+    schema_version: u32,
+    reuse_buffers: bool,
+    on_rotate: Option<RotateCallback>,
 }
 
-impl CustomLogger {
-    pub fn new(target: &str, filepath: &str) -> CustomLogger {
-        let path = Path::new(filepath);
-        path.parent().map(|p| std::fs::create_dir_all(p).unwrap());
-        File::create(filepath).unwrap();
-        CustomLogger {
-            target: target.to_string(),
-            filepath: Some(filepath.to_string()),
+/// the fields [`LogEntry::from_record`] adds on its own, which
+/// [`CustomLogger::allowed_fields`] always keeps
+const RESERVED_FIELDS: [&str; 6] = [
+    "spans",
+    "trace_id",
+    "span_id",
+    "error",
+    "run_id",
+    "unmatched",
+];
+
+type RotateCallback = Box<dyn Fn(&Path) + Send + Sync>;
+
+type MessageTransform = Box<dyn Fn(&str) -> String + Send + Sync>;
+
+/// Which target is written to the `target` field of each record
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize)]
+pub enum TargetField {
+    /// the target of the record itself, e.g. `app::db` for a logger matching `app::...`
+    #[default]
+    Record,
+    /// the target the logger was configured with
+    Logger,
+}
+
+/// How control characters in messages and string fields are written
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize)]
+pub enum EscapeMode {
+    /// kept as they are, escaped only as JSON requires, e.g. `\u001b`
+    #[default]
+    JsonStandard,
+    /// removed
+    StripControl,
+    /// replaced by their escape sequence as plain text, e.g. `\x1b`
+    Visible,
+}
+
+/// How the level name is cased when rendered
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize)]
+pub enum LevelCase {
+    /// `INFO`
+    #[default]
+    Upper,
+    /// `info`
+    Lower,
+    /// `Info`
+    Title,
+}
+
+impl LevelCase {
+    pub fn apply(&self, level: &str) -> String {
+        match self {
+            LevelCase::Upper => level.to_uppercase(),
+            LevelCase::Lower => level.to_lowercase(),
+            LevelCase::Title => {
+                let lower = level.to_lowercase();
+                let mut chars = lower.chars();
+                match chars.next() {
+                    Some(first) => first.to_uppercase().chain(chars).collect(),
+                    None => lower,
+                }
+            }
         }
     }
 }
 
-impl log::Log for CustomLogger {
-    fn enabled(&self, metadata: &log::Metadata) -> bool {
-        if metadata.target() == self.target {
-            return true;
-        }
-        return false;
+impl CustomLogger {
+    /// create a CustomLogger writing to `filepath`, truncating any existing file
+    ///
+    /// Panics if the file cannot be created; see [`CustomLogger::try_new`].
+    pub fn new(target: &str, filepath: &str) -> CustomLogger {
+        CustomLogger::try_new(target, filepath).unwrap()
     }
 
-    fn log(&self, record: &log::Record) {
-        if !self.enabled(record.metadata()) {
-            return;
-        }
+    /// create a CustomLogger writing to `filepath`, truncating any existing file
+    /// # Example
+    /// ```
+    /// # use crate::loggers::*;
+    /// let logger = CustomLogger::try_new("test", "system.log").expect("Failed to open log file");
+    /// ```
+    pub fn try_new(target: &str, filepath: &str) -> std::io::Result<CustomLogger> {
+        OpenOptions::new().open(target, filepath)
+    }
 
-        let log_json_text = format!(
-            r#"{{"severity":"{}","timestamp":"{}","target":"{}","message":"{}"}}"#,
-            record.level(),
-            Local::now().to_rfc3339_opts(SecondsFormat::Millis, true),
-            self.target,
-            record.args(),
-        );
-        let log_print_text = format!(
-            "[{}] {} {} - {}",
-            record.level().to_string().to_uppercase(),
-            self.target,
-            Local::now().to_rfc3339_opts(SecondsFormat::Millis, true),
-            record.args(),
-        );
+    /// create a CustomLogger that appends to `filepath` instead of truncating it
+    /// # Example
+    /// ```
+    /// # use crate::loggers::*;
+    /// let logger = CustomLogger::append("test", "system.log").expect("Failed to open log file");
+    /// ```
+    pub fn append(target: &str, filepath: &str) -> std::io::Result<CustomLogger> {
+        OpenOptions::new().append(true).open(target, filepath)
+    }
 
-        match self.filepath {
-            Some(ref filepath) => {
-                let file = std::fs::OpenOptions::new()
-                    .create(true)
-                    .append(true)
-                    .open(filepath)
-                    .unwrap();
-                let mut bf = std::io::BufWriter::new(file);
+    fn with_file(target: &str, filepath: &str, file: File) -> CustomLogger {
+        let mut logger = CustomLogger::console_only(target);
+        logger.filepath = Some(filepath.to_string());
+        logger.file = Mutex::new(Some(file));
+        logger.counters = metrics::FileCounters::register(filepath);
+        logger
+    }
 
-                bf.write(log_json_text.as_bytes()).unwrap();
-                bf.write(b"\n").unwrap();
-            }
-            None => {
-                println!("Cannot open file {:?}", self.filepath);
-            }
+    /// create a CustomLogger that only prints to the console
+    ///
+    /// No file is opened and nothing is reported about a missing one. Use
+    /// [`console_format`](Self::console_format) to print JSON records instead
+    /// of human readable lines.
+    /// # Example
+    /// ```
+    /// # use crate::loggers::*;
+    /// let logger = CustomLogger::console_only("test").console_format(ConsoleFormat::Json);
+    /// ```
+    pub fn console_only(target: &str) -> CustomLogger {
+        CustomLogger {
+            target: target.to_string(),
+            filepath: None,
+            file: Mutex::new(None),
+            location: false,
+            function: false,
+            module_fields: false,
+            match_module_default: false,
+            min_level: AtomicUsize::new(
+                level::env_override(target).unwrap_or(log::LevelFilter::Trace) as usize,
+            ),
+            max_level: log::Level::Error,
+            fsync: false,
+            coalesce: None,
+            aggregate: None,
+            console: true,
+            color_mode: ColorMode::Never,
+            color: false,
+            color_map: Vec::new(),
+            max_size: None,
+            level_case: LevelCase::Upper,
+            level_case_in_json: false,
+            escape_mode: EscapeMode::JsonStandard,
+            compress_active: false,
+            target_field: TargetField::Record,
+            hash_chain: None,
+            rotate_daily: false,
+            segment_date: Mutex::new(None),
+            renames: Vec::new(),
+            allowed_fields: None,
+            max_field_len: None,
+            message_transform: None,
+            watch_inode: false,
+            indent: 0,
+            sort_keys: false,
+            split_timestamp: false,
+            any_target: false,
+            emit_bom: false,
+            every_n: None,
+            sampler: None,
+            adaptive: None,
+            counters: Default::default(),
+            format: Format::Json,
+            console_format: ConsoleFormat::Human,
+            max_lines: None,
+            lines: AtomicU64::new(0),
+            monotonic: false,
+            produced_at: false,
+            file_mode: None,
+            trace_fields: None,
+            schema_version: SCHEMA_VERSION,
+            reuse_buffers: false,
+            on_rotate: None,
         }
-        // if let Some(filepath) = &self.filepath {
-        //     let file = std::fs::OpenOptions::new()
-        //         .create(true)
-        //         .append(true)
-        //         .open(filepath)
-        //         .unwrap();
-        //     let mut bf = std::io::BufWriter::new(file);
+    }
 
-        //     bf.write(log_json_text.as_bytes()).unwrap();
-        //     bf.write(b"\n").unwrap();
-        // }
+    /// include the module, file and line of the call site in each record
+    pub fn location(mut self, location: bool) -> CustomLogger {
+        self.location = location;
+        self
+    }
 
-        println!("{}", log_print_text);
+    /// write only the structured fields named in `keys`, dropping the others
+    ///
+    /// Fields the crate adds itself, such as `spans`, `trace_id`,
+    /// `schema_version` or the `run_id` and `unmatched` fields added by a
+    /// [`Logger`], are always written. A record that lost fields gets
+    /// a `dropped_fields` field with their number. Renames apply after the
+    /// allowlist, so list the original keys.
+    /// # Example
+    /// ```
+    /// # use crate::loggers::*;
+    /// let logger = CustomLogger::new("test", "system.log").allowed_fields(&["user", "status"]);
+    /// ```
+    pub fn allowed_fields(mut self, keys: &[&str]) -> CustomLogger {
+        self.allowed_fields = Some(keys.iter().map(|k| k.to_string()).collect());
+        self
     }
 
-    fn flush(&self) {}
-}
+    /// write the calling function of records logged with [`log_fn!`] as a
+    /// `function` field (default: false)
+    ///
+    /// Records logged with the `log` macros carry no function name; see
+    /// [`function_name!`] for how it is obtained and its limitations.
+    pub fn function(mut self, function: bool) -> CustomLogger {
+        self.function = function;
+        self
+    }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use log::{debug, info};
-    use serde_json::Value;
-    use std::fs::File;
-    use std::io::Read;
-    use std::path::Path;
+    /// write the module path of the call site split into a `module` object
+    /// with its `crate` and the `path` of modules below it (default: false)
+    ///
+    /// `app::db::queries` is written as
+    /// `"module":{"crate":"app","path":["db","queries"]}`, so log viewers
+    /// can filter on the crate or on any level of the hierarchy.
+    /// # Example
+    /// ```
+    /// # use crate::loggers::*;
+    /// let logger = CustomLogger::new("test", "system.log").module_fields(true);
+    /// ```
+    pub fn module_fields(mut self, module_fields: bool) -> CustomLogger {
+        self.module_fields = module_fields;
+        self
+    }
 
-    #[test]
-    fn test_log() {
-        let log_file = Path::new("tests/output/test.log");
-        if log_file.exists() {
-            std::fs::remove_file(log_file).unwrap();
-        }
+    /// also match records whose target is a module path under the configured target
+    ///
+    /// The `log` macros default the target to the calling module's path, so
+    /// `debug!("msg")` inside `myapp::db` has the target `myapp::db`. With this
+    /// option a logger created for `"myapp"` matches `myapp` and every
+    /// `myapp::...` target instead of letting them fall through to the fallback.
+    /// # Example
+    /// ```
+    /// # use crate::loggers::*;
+    /// let logger = CustomLogger::new("myapp", "system.log").match_module_default(true);
+    /// ```
+    pub fn match_module_default(mut self, match_module_default: bool) -> CustomLogger {
+        self.match_module_default = match_module_default;
+        self
+    }
 
-        let mut logger = Logger::new();
-        logger.add_logger(Box::new(CustomLogger::new(
-            "test",
-            "tests/output/system.log",
-        )));
-        logger.set_fallback(Box::new(CustomLogger::new(
-            "default",
-            "tests/output/system.log",
-        )));
-        log::set_boxed_logger(Box::new(logger)).expect("Failed to set logger");
-        log::set_max_level(log::LevelFilter::Trace);
+    /// accept records of every target, not only the configured one (default: false)
+    ///
+    /// Required for a logger used with [`Logger::set_fallback`], which is
+    /// handed exactly the records no other logger matched. Combine it with
+    /// [`console(false)`](Self::console) for a fallback that archives those
+    /// records to its file without printing them.
+    /// # Example
+    /// ```
+    /// # use crate::loggers::*;
+    /// let mut logger = Logger::new();
+    /// logger.set_fallback(Box::new(
+    ///     CustomLogger::new("default", "system.log")
+    ///         .any_target(true)
+    ///         .console(false),
+    /// ));
+    /// ```
+    pub fn any_target(mut self, any_target: bool) -> CustomLogger {
+        self.any_target = any_target;
+        self
+    }
 
-        info!(target:"test", "Hello, world!");
-        debug!("Default");
+    /// only accept records at least as severe as `level`
+    ///
+    /// A `LOGGERS_LEVEL_<target>` environment variable, such as
+    /// `LOGGERS_LEVEL_api=debug`, takes precedence over the level set here
+    /// and over the least severe level of
+    /// [`level_range`](Self::level_range); it is read when the logger is
+    /// configured. [`set_level`](Self::set_level) still changes the level
+    /// afterwards, and the global `log::max_level` filter applies before
+    /// either, so it has to admit the overridden level too.
+    pub fn min_level(mut self, level: log::Level) -> CustomLogger {
+        self.min_level = AtomicUsize::new(self.env_level(level.to_level_filter()));
+        self.max_level = log::Level::Error;
+        self
+    }
 
-        let mut file = File::open("tests/output/system.log").unwrap();
-        let mut contents = String::new();
-        file.read_to_string(&mut contents).unwrap();
+    /// only accept records whose severity lies between `min` and `max`, inclusive
+    ///
+    /// `min` is the least severe level accepted, so
+    /// `level_range(Level::Trace, Level::Debug)` keeps Trace and Debug records
+    /// and drops Info, Warn and Error.
+    pub fn level_range(mut self, min: log::Level, max: log::Level) -> CustomLogger {
+        // log::Level orders Error < Warn < Info < Debug < Trace
+        self.min_level = AtomicUsize::new(self.env_level(min.max(max).to_level_filter()));
+        self.max_level = min.min(max);
+        self
+    }
+
+    // `level`, unless the environment overrides it for this target
+    fn env_level(&self, level: log::LevelFilter) -> usize {
+        level::env_override(&self.target).unwrap_or(level) as usize
+    }
+
+    /// change the least severe level accepted while the logger is in use
+    ///
+    /// `LevelFilter::Off` stops the logger from accepting any record. The
+    /// most severe level accepted, as set by [`level_range`](Self::level_range),
+    /// is unaffected.
+    /// # Example
+    /// ```
+    /// # use crate::loggers::*;
+    /// let logger = CustomLogger::new("test", "system.log").min_level(log::Level::Info);
+    /// logger.set_level(log::LevelFilter::Trace);
+    /// ```
+    pub fn set_level(&self, level: log::LevelFilter) {
+        self.min_level.store(level as usize, Ordering::Relaxed);
+    }
+
+    /// call `File::sync_all` after every record so it is on disk before `log` returns
+    ///
+    /// This protects records against power loss at a large cost in throughput:
+    /// every record waits for the storage device. Only enable it for low-volume
+    /// logs that must be durable, such as audit trails.
+    pub fn fsync(mut self, fsync: bool) -> CustomLogger {
+        self.fsync = fsync;
+        self
+    }
+
+    /// with [`fsync`](Self::fsync), sync the file at most once per `interval`
+    ///
+    /// A record written within `interval` of the last sync returns without
+    /// waiting for the storage device; a background thread syncs the file
+    /// when the interval has passed, so after a burst the records reach the
+    /// disk within one interval even if nothing else is logged. This bounds
+    /// the time records are at risk instead of eliminating it.
+    /// # Example
+    /// ```
+    /// # use crate::loggers::*;
+    /// let logger = CustomLogger::new("audit", "tests/output/audit.log")
+    ///     .fsync(true)
+    ///     .coalesce_flush(std::time::Duration::from_millis(100));
+    /// ```
+    pub fn coalesce_flush(mut self, interval: std::time::Duration) -> CustomLogger {
+        self.coalesce = Some(coalesce::FlushCoalescer::new(interval));
+        self
+    }
+
+    /// collapse bursts of identical records into one
+    ///
+    /// Records that differ only in their timestamp and arrive within `window`
+    /// of the first one are written once, with a `count` field and the
+    /// `first_seen`/`last_seen` timestamps of the burst. While this is enabled
+    /// every record is held back until a different record arrives, the window
+    /// closes and another record is logged, or the logger is flushed or dropped.
+    pub fn aggregate_window(mut self, window: std::time::Duration) -> CustomLogger {
+        self.aggregate = Some(aggregate::Aggregator::new(window));
+        self
+    }
+
+    /// write only the first and then every `n`th occurrence of a message
+    ///
+    /// Records are counted per level and message text. The occurrences in
+    /// between are dropped, and each periodic record carries a `skipped`
+    /// field with the number dropped since the previous one.
+    /// # Example
+    /// ```
+    /// # use crate::loggers::*;
+    /// let logger = CustomLogger::new("test", "system.log").log_every(100);
+    /// ```
+    pub fn log_every(mut self, n: u64) -> CustomLogger {
+        self.every_n = Some(throttle::EveryN::new(n));
+        self
+    }
+
+    /// write only a `rate` fraction of the records, between 0.0 and 1.0
+    ///
+    /// Kept records are spread evenly, e.g. every tenth record for `0.1`, and
+    /// carry a `sample_rate` field with the rate so consumers can scale
+    /// counts back up.
+    /// # Example
+    /// ```
+    /// # use crate::loggers::*;
+    /// let logger = CustomLogger::new("test", "system.log").sample(0.1);
+    /// ```
+    pub fn sample(mut self, rate: f64) -> CustomLogger {
+        self.sampler = Some(throttle::Sampler::new(rate));
+        self
+    }
+
+    /// sample records so that about `records_per_second` are written
+    ///
+    /// Every second the rate of incoming records is measured and the fraction
+    /// kept for the next second is set to bring it down to the target, so
+    /// sampling gets harder under overload and stops once traffic subsides.
+    /// Kept records carry a `sample_rate` field like with
+    /// [`sample`](Self::sample), and after every second in which records were
+    /// dropped a summary record with the `sample_rate`, `records` and
+    /// `dropped` counts of that second is written.
+    /// # Example
+    /// ```
+    /// # use crate::loggers::*;
+    /// let logger = CustomLogger::new("test", "system.log").adaptive_sample(1000);
+    /// ```
+    pub fn adaptive_sample(mut self, records_per_second: u64) -> CustomLogger {
+        self.adaptive = Some(throttle::AdaptiveSampler::new(records_per_second));
+        self
+    }
+
+    /// print a human readable line for each record to stdout (default: true)
+    pub fn console(mut self, console: bool) -> CustomLogger {
+        self.console = console;
+        self
+    }
+
+    /// set how records are printed to stdout (default: [`ConsoleFormat::Human`])
+    ///
+    /// The console format is independent of the file [`format`](Self::format).
+    /// [`ConsoleFormat::Json`] replaces the human line with the JSON record
+    /// for container log collectors that read stdout; add `.console(false)`
+    /// to print nothing. JSON in the file and logfmt on the console:
+    /// ```
+    /// # use loggers::*;
+    /// let logger = CustomLogger::new("test", "tests/output/system.log")
+    ///     .format(Format::Json)
+    ///     .console_format(ConsoleFormat::Logfmt);
+    /// ```
+    pub fn console_format(mut self, console_format: ConsoleFormat) -> CustomLogger {
+        self.console_format = console_format;
+        self
+    }
+
+    /// color the level of console lines with ANSI escape codes (default: false)
+    ///
+    /// Shorthand for [`ColorMode::Always`] or [`ColorMode::Never`].
+    pub fn color(self, color: bool) -> CustomLogger {
+        self.color_mode(match color {
+            true => ColorMode::Always,
+            false => ColorMode::Never,
+        })
+    }
+
+    /// set when console lines are colored (default: [`ColorMode::Never`])
+    ///
+    /// [`ColorMode::Auto`] reads the environment when this is called.
+    pub fn color_mode(mut self, color_mode: ColorMode) -> CustomLogger {
+        self.color_mode = color_mode;
+        self.color = color_mode.enabled();
+        self
+    }
+
+    /// replace the console color of some levels with ANSI color specs
+    ///
+    /// A spec is a list of SGR parameters such as `35` for magenta, `1;31`
+    /// for bold red or `38;5;208` for a 256-color orange, optionally written
+    /// as the full escape sequence. Levels not listed keep the default
+    /// palette, and an invalid spec is reported on stderr and ignored. Colors
+    /// are only used when [`color_mode`](Self::color_mode) enables them.
+    /// # Example
+    /// ```
+    /// # use crate::loggers::*;
+    /// let logger = CustomLogger::console_only("test")
+    ///     .color(true)
+    ///     .color_map(&[(log::Level::Error, "1;35"), (log::Level::Info, "38;5;250")]);
+    /// ```
+    pub fn color_map(mut self, colors: &[(log::Level, &str)]) -> CustomLogger {
+        for &(level, spec) in colors {
+            let Some(color) = color::sequence(spec) else {
+                eprintln!(
+                    "CustomLogger {}: ignoring invalid color {:?} for {}",
+                    self.target, spec, level
+                );
+                continue;
+            };
+            self.color_map.retain(|(l, _)| *l != level);
+            self.color_map.push((level, color));
+        }
+        self
+    }
+
+    /// set how the level is cased on the console (default: [`LevelCase::Upper`])
+    pub fn level_case(mut self, level_case: LevelCase) -> CustomLogger {
+        self.level_case = level_case;
+        self
+    }
+
+    /// also apply the [`CustomLogger::level_case`] to the JSON `severity` field (default: false)
+    pub fn level_case_in_json(mut self, level_case_in_json: bool) -> CustomLogger {
+        self.level_case_in_json = level_case_in_json;
+        self
+    }
+
+    /// set how control characters such as ANSI escapes or null bytes in the
+    /// message and in string fields are written (default:
+    /// [`EscapeMode::JsonStandard`])
+    ///
+    /// Newlines and tabs are control characters too, so
+    /// [`EscapeMode::StripControl`] and [`EscapeMode::Visible`] also put
+    /// multi-line messages on one line.
+    /// # Example
+    /// ```
+    /// # use crate::loggers::*;
+    /// let logger = CustomLogger::new("test", "system.log").escape_mode(EscapeMode::Visible);
+    /// ```
+    pub fn escape_mode(mut self, escape_mode: EscapeMode) -> CustomLogger {
+        self.escape_mode = escape_mode;
+        self
+    }
+
+    /// cut string values of structured fields longer than `max` bytes to at
+    /// most `max` bytes followed by `…`, so a stack trace or a large payload
+    /// does not bloat the record
+    ///
+    /// Strings nested in object and array fields are cut too. The cut falls
+    /// on a character boundary, so the value stays valid UTF-8. The message
+    /// and the fields this crate adds itself are not affected.
+    /// # Example
+    /// ```
+    /// # use crate::loggers::*;
+    /// let logger = CustomLogger::new("test", "system.log").max_field_len(1024);
+    /// ```
+    pub fn max_field_len(mut self, max: usize) -> CustomLogger {
+        self.max_field_len = Some(max);
+        self
+    }
+
+    /// write the file gzip-compressed
+    ///
+    /// Each record is written as a separate gzip member, so the file is a
+    /// valid gzip stream after every write and can be read while it grows
+    /// (e.g. with `zcat` or `flate2::read::MultiGzDecoder`). Every rotated
+    /// segment decompresses on its own. Compression is per record, so the
+    /// ratio is lower than compressing the finished file.
+    pub fn compress_active(mut self, compress_active: bool) -> CustomLogger {
+        self.compress_active = compress_active;
+        self
+    }
+
+    /// choose which target is written to the `target` field (default: [`TargetField::Record`])
+    pub fn target_field(mut self, target_field: TargetField) -> CustomLogger {
+        self.target_field = target_field;
+        self
+    }
+
+    /// chain records together with SHA-256 hashes for tamper evidence
+    ///
+    /// Each record gets a `prev_hash` field holding the `hash` of the record
+    /// before it and a `hash` field computed over the record itself, so
+    /// editing, inserting or removing a line breaks the chain. The chain
+    /// continues across rotated segments, and when the file already ends with
+    /// a chained record it continues from it. Check a file with
+    /// [`verify_chain`]; records cut off the end are only detected by
+    /// comparing the last hash with one kept elsewhere.
+    pub fn hash_chain(mut self, hash_chain: bool) -> CustomLogger {
+        self.hash_chain = hash_chain.then(|| {
+            let last = self
+                .filepath
+                .as_ref()
+                .and_then(|p| chain::last_hash(Path::new(p)));
+            Mutex::new(last.unwrap_or_else(|| GENESIS_HASH.to_string()))
+        });
+        self
+    }
+
+    /// write the field `from` under the key `to` instead
+    ///
+    /// Applies to the standard fields (e.g. `message`) as well as structured
+    /// fields. A dotted `to` such as `body.text` moves the value into a nested
+    /// object. Files written with renamed standard fields can no longer be
+    /// read back with [`read_log`].
+    /// # Example
+    /// ```
+    /// # use crate::loggers::*;
+    /// let logger = CustomLogger::new("test", "system.log")
+    ///     .rename_field("message", "msg")
+    ///     .rename_field("severity", "log.level");
+    /// ```
+    pub fn rename_field(mut self, from: &str, to: &str) -> CustomLogger {
+        self.renames.push((from.to_string(), to.to_string()));
+        self
+    }
+
+    /// rewrite every message before it is written
+    ///
+    /// The transform is applied to the rendered `record.args()` before any
+    /// other processing, so aggregation, the file and the console all see
+    /// the transformed message.
+    /// # Example
+    /// ```
+    /// # use crate::loggers::*;
+    /// let logger = CustomLogger::new("test", "system.log")
+    ///     .message_transform(Box::new(|message| message.trim().to_lowercase()));
+    /// ```
+    pub fn message_transform(mut self, transform: MessageTransform) -> CustomLogger {
+        self.message_transform = Some(transform);
+        self
+    }
+
+    /// rotate the file once it reaches `bytes`, as with [`CustomLogger::rotate`]
+    pub fn max_size(mut self, bytes: u64) -> CustomLogger {
+        self.max_size = Some(bytes);
+        self
+    }
+
+    /// rotate the file once it holds `lines` records, as with [`CustomLogger::rotate`]
+    ///
+    /// The lines already in the file count towards the limit, so appending
+    /// to an existing file after a restart rotates at the same boundary.
+    /// They are counted when this option is set, reading no further than
+    /// needed to reach the limit.
+    pub fn max_lines(mut self, lines: u64) -> CustomLogger {
+        let existing = self
+            .filepath
+            .as_ref()
+            .and_then(|path| File::open(path).ok())
+            .map(|file| count_lines(file, lines))
+            .unwrap_or(0);
+        self.max_lines = Some(lines.max(1));
+        self.lines = AtomicU64::new(existing);
+        self
+    }
+
+    /// rotate the file when the date of the records changes
+    ///
+    /// Rotated segments are named `<filepath>.<YYYY-MM-DD>.<n>` after the
+    /// date of the records they hold, where `n` counts the rotations of that
+    /// day from 1. Combined with [`CustomLogger::max_size`] the file rotates on
+    /// whichever comes first, so a busy day produces several numbered
+    /// segments. A file that already holds records is dated by its
+    /// modification time.
+    pub fn rotate_daily(mut self, rotate_daily: bool) -> CustomLogger {
+        self.rotate_daily = rotate_daily;
+        self
+    }
+
+    /// reopen the file whenever its path was renamed or deleted by another process
+    ///
+    /// Before each write the open file is compared (by device and inode on
+    /// Unix) with the file at `filepath`; when they differ, as after
+    /// `logrotate` moved the file away, `filepath` is reopened in append mode.
+    /// On other platforms only a missing file is detected.
+    pub fn watch_inode(mut self, watch_inode: bool) -> CustomLogger {
+        self.watch_inode = watch_inode;
+        self
+    }
+
+    /// set how records are encoded in the file (default: [`Format::Json`])
+    ///
+    /// The JSON-only options [`indent`](Self::indent) and
+    /// [`emit_bom`](Self::emit_bom) do not apply to binary formats.
+    pub fn format(mut self, format: Format) -> CustomLogger {
+        self.format = format;
+        self
+    }
+
+    /// pretty-print each record using `spaces` of indentation; 0 (the default)
+    /// writes each record compactly on a single line
+    ///
+    /// Pretty-printed files are meant for human inspection and cannot be read
+    /// back with [`read_log`], which expects one record per line.
+    pub fn indent(mut self, spaces: usize) -> CustomLogger {
+        self.indent = spaces;
+        self
+    }
+
+    /// write the keys of each record, including nested objects, in
+    /// alphabetical order instead of the natural
+    /// `severity`, `timestamp`, `target`, `message`, fields order
+    /// # Example
+    /// ```
+    /// # use crate::loggers::*;
+    /// let logger = CustomLogger::new("test", "system.log")
+    ///     .indent(2)
+    ///     .sort_keys(true);
+    /// ```
+    pub fn sort_keys(mut self, sort_keys: bool) -> CustomLogger {
+        self.sort_keys = sort_keys;
+        self
+    }
+
+    /// also write the timestamp as separate `date`, `time` and `epoch_ms` fields
+    ///
+    /// `date` (`2024-06-01`) and `time` (`13:45:30.123`) are in the same
+    /// offset as `timestamp`, and `epoch_ms` is milliseconds since the Unix epoch.
+    pub fn split_timestamp(mut self, split_timestamp: bool) -> CustomLogger {
+        self.split_timestamp = split_timestamp;
+        self
+    }
+
+    /// start every file this logger writes to with a UTF-8 byte order mark
+    ///
+    /// The BOM is written before the first record of an empty file, so an
+    /// existing file appended to does not get a second one, while every new
+    /// segment started by rotation begins with its own. [`read_log`] and
+    /// [`verify_chain`] skip it.
+    pub fn emit_bom(mut self, emit_bom: bool) -> CustomLogger {
+        self.emit_bom = emit_bom;
+        self
+    }
+
+    /// what this logger has written to its file since it was created
+    /// # Example
+    /// ```
+    /// # use crate::loggers::*;
+    /// let logger = CustomLogger::new("test", "system.log");
+    /// println!("{} bytes", logger.stats().bytes_written);
+    /// ```
+    pub fn stats(&self) -> Stats {
+        Stats {
+            bytes_written: self.counters.bytes_written.load(Ordering::Relaxed),
+            records_written: self.counters.records_written.load(Ordering::Relaxed),
+        }
+    }
+
+    /// the files holding this logger's records, newest first
+    ///
+    /// Lists `filepath` followed by its rotated segments: `<filepath>.N` by
+    /// ascending `N`, then `<filepath>.<date>.n` from the latest, each
+    /// possibly compressed with a `.gz` suffix. Read them all in order with
+    /// [`read_segments`].
+    pub fn segments(&self) -> Vec<std::path::PathBuf> {
+        match &self.filepath {
+            Some(filepath) => rotation::segments(Path::new(filepath)),
+            None => Vec::new(),
+        }
+    }
+
+    /// write the ids of the current [`span::set_trace`] context under
+    /// `trace_key` and `span_key` in `format`, instead of as hex `trace_id`
+    /// and `span_id` fields
+    /// # Example
+    /// ```
+    /// # use crate::loggers::*;
+    /// let logger = CustomLogger::new("test", "system.log").trace_fields(
+    ///     "dd.trace_id",
+    ///     "dd.span_id",
+    ///     span::IdFormat::Decimal,
+    /// );
+    /// ```
+    pub fn trace_fields(
+        mut self,
+        trace_key: &str,
+        span_key: &str,
+        format: span::IdFormat,
+    ) -> CustomLogger {
+        self.trace_fields = Some((trace_key.to_string(), span_key.to_string(), format));
+        self
+    }
+
+    /// set the `schema_version` field written to every record (default:
+    /// [`SCHEMA_VERSION`])
+    ///
+    /// Override it when records are post-processed into a shape of your own
+    /// that consumers need to tell apart.
+    pub fn schema_version(mut self, version: u32) -> CustomLogger {
+        self.schema_version = version;
+        self
+    }
+
+    /// encode records into a buffer kept per thread instead of allocating a
+    /// new one for each record (default: false)
+    ///
+    /// The buffer is cleared before each record and freed instead of kept
+    /// after one larger than 64 KiB, so a single huge record does not pin
+    /// its memory.
+    pub fn reuse_buffers(mut self, reuse_buffers: bool) -> CustomLogger {
+        self.reuse_buffers = reuse_buffers;
+        self
+    }
+
+    /// add a `nanos_since_start` field from a monotonic clock to every record
+    ///
+    /// The wall-clock `timestamp` can jump backwards, e.g. on an NTP
+    /// adjustment; `nanos_since_start` counts from the first record of the
+    /// process and is strictly increasing across all loggers, so it orders
+    /// records written by one process reliably.
+    pub fn monotonic(mut self, monotonic: bool) -> CustomLogger {
+        self.monotonic = monotonic;
+        self
+    }
+
+    /// add a `produced_at` field with the wall-clock time the record was
+    /// produced, in nanoseconds since the Unix epoch (default: false)
+    ///
+    /// The millisecond `timestamp` is too coarse to measure the lag to
+    /// ingestion; a pipeline can compare `produced_at` with the time it
+    /// ingests the record, e.g. by adding an `ingested_at` field of its own.
+    pub fn produced_at(mut self, produced_at: bool) -> CustomLogger {
+        self.produced_at = produced_at;
+        self
+    }
+
+    /// call `on_rotate` with the path of each segment rotation finalizes
+    ///
+    /// The callback runs once the segment has been closed and renamed, after
+    /// the logger released its file, so it can safely upload or move the
+    /// segment, or log. It runs on the thread whose record triggered the
+    /// rotation, which waits for it to return.
+    /// # Example
+    /// ```
+    /// # use crate::loggers::*;
+    /// let logger = CustomLogger::new("test", "system.log")
+    ///     .max_size(10 * 1024 * 1024)
+    ///     .on_rotate(Box::new(|segment| println!("finalized {}", segment.display())));
+    /// ```
+    pub fn on_rotate(mut self, on_rotate: Box<dyn Fn(&Path) + Send + Sync>) -> CustomLogger {
+        self.on_rotate = Some(on_rotate);
+        self
+    }
+
+    /// close the file and reopen `filepath` in append mode
+    ///
+    /// Call this from a `SIGHUP` handler to pick up a file rotated by an
+    /// external tool.
+    pub fn reopen(&self) -> std::io::Result<()> {
+        let Some(filepath) = &self.filepath else {
+            return Ok(());
+        };
+        let mut file = self.file.lock().unwrap();
+        self.close_file(filepath, &mut file);
+        *file = Some(open_append(filepath, self.file_mode)?);
+        Ok(())
+    }
+
+    /// close the current file, first syncing writes that
+    /// [`coalesce_flush`](Self::coalesce_flush) left to the background thread
+    fn close_file(&self, filepath: &str, file: &mut Option<File>) {
+        if let (Some(coalesce), Some(f)) = (&self.coalesce, file.as_ref()) {
+            if let Err(e) = coalesce.close(f) {
+                eprintln!("Cannot sync file {:?}: {}", filepath, e);
+            }
+        }
+        file.take();
+    }
+
+    /// rotate the log file now
+    ///
+    /// The current file is closed and renamed to `<filepath>.1`, shifting any
+    /// existing `<filepath>.N` to `<filepath>.N+1`, and a fresh file is opened.
+    /// Records logged concurrently are written either before or after the rotation.
+    /// If a rename fails the error is returned and records keep being
+    /// appended to `filepath`.
+    pub fn rotate(&self) -> std::io::Result<()> {
+        let Some(filepath) = &self.filepath else {
+            return Ok(());
+        };
+        let mut file = self.file.lock().unwrap();
+        let date = *self.segment_date.lock().unwrap();
+        let rotated = self.rotate_locked(filepath, &mut file, date);
+        drop(file);
+        self.notify_rotated(rotated?.as_deref());
+        Ok(())
+    }
+
+    /// run the [`on_rotate`](Self::on_rotate) callback for a finalized segment
+    fn notify_rotated(&self, segment: Option<&Path>) {
+        if let (Some(on_rotate), Some(segment)) = (&self.on_rotate, segment) {
+            on_rotate(segment);
+        }
+    }
+
+    /// rotate while holding the file lock; `date` names dated segments.
+    /// Returns the path of the finalized segment, if there was one.
+    fn rotate_locked(
+        &self,
+        filepath: &str,
+        file: &mut Option<File>,
+        date: Option<clock::Date>,
+    ) -> std::io::Result<Option<std::path::PathBuf>> {
+        self.close_file(filepath, file);
+        self.lines.store(0, Ordering::Relaxed);
+        let path = Path::new(filepath);
+        let rotated = match self.rotate_daily {
+            true => rotation::dated_segment(path, date.unwrap_or_else(clock::Date::today)),
+            false => rotation::shift_segments(path),
+        };
+        // keep writing to `filepath` even if the rename failed
+        *file = Some(open_append(filepath, self.file_mode)?);
+        rotated
+    }
+
+    /// rotate first if `entry` belongs to a later day than the current
+    /// segment, returning the finalized segment
+    fn rotate_for_date(
+        &self,
+        filepath: &str,
+        file: &mut Option<File>,
+        entry: &LogEntry,
+    ) -> Option<std::path::PathBuf> {
+        let date = clock::Timestamp::parse(&entry.timestamp)?.date;
+        let mut segment_date = self.segment_date.lock().unwrap();
+        let current = *segment_date.get_or_insert_with(|| {
+            std::fs::metadata(filepath)
+                .ok()
+                .filter(|m| m.len() > 0)
+                .and_then(|m| m.modified().ok())
+                .map(clock::Date::of)
+                .unwrap_or(date)
+        });
+        if date <= current {
+            return None;
+        }
+        *segment_date = Some(date);
+        match self.rotate_locked(filepath, file, Some(current)) {
+            Ok(rotated) => rotated,
+            Err(e) => {
+                eprintln!("Cannot rotate file {:?}: {}", filepath, e);
+                None
+            }
+        }
+    }
+
+    /// build the LogEntry written to the file for `record`
+    fn entry(&self, record: &log::Record, timestamp: &str) -> LogEntry {
+        let target = match self.target_field {
+            TargetField::Record => record.target(),
+            TargetField::Logger => &self.target,
+        };
+        let mut entry = LogEntry::from_record(record, target, timestamp);
+        if let Some(allowed) = &self.allowed_fields {
+            let before = entry.fields.len();
+            entry.fields.retain(|key, _| {
+                RESERVED_FIELDS.contains(&key.as_str()) || allowed.iter().any(|a| a == key)
+            });
+            let dropped = before - entry.fields.len();
+            if dropped > 0 {
+                entry
+                    .fields
+                    .insert("dropped_fields".to_string(), dropped.into());
+            }
+        }
+        if let Some(max) = self.max_field_len {
+            entry
+                .fields
+                .iter_mut()
+                .filter(|(key, _)| !RESERVED_FIELDS.contains(&key.as_str()))
+                .for_each(|(_, value)| entry::truncate_value(value, max));
+        }
+        entry
+            .fields
+            .shift_insert(0, "schema_version".to_string(), self.schema_version.into());
+        if self.function {
+            if let Some(function) = record.key_values().get(entry::FUNCTION_KEY.into()) {
+                entry
+                    .fields
+                    .insert("function".to_string(), function.to_string().into());
+            }
+        }
+        if let Some(module) = record.module_path().filter(|_| self.module_fields) {
+            let mut segments = module.split("::");
+            let krate = segments.next().unwrap_or_default();
+            entry.fields.insert(
+                "module".to_string(),
+                serde_json::json!({ "crate": krate, "path": segments.collect::<Vec<_>>() }),
+            );
+        }
+        if let (Some((trace_key, span_key, format)), Some(trace)) =
+            (&self.trace_fields, span::trace())
+        {
+            let fields = &mut entry.fields;
+            fields.shift_remove("trace_id");
+            fields.shift_remove("span_id");
+            fields.insert(trace_key.clone(), format.trace_id(trace.trace_id).into());
+            fields.insert(span_key.clone(), format.span_id(trace.span_id).into());
+        }
+        if self.monotonic {
+            entry.fields.insert(
+                "nanos_since_start".to_string(),
+                clock::nanos_since_start().into(),
+            );
+        }
+        if self.produced_at {
+            let nanos = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map_or(0, |d| d.as_nanos() as u64);
+            entry.fields.insert("produced_at".to_string(), nanos.into());
+        }
+        if self.split_timestamp {
+            if let Some(t) = clock::Timestamp::parse(timestamp) {
+                let fields = &mut entry.fields;
+                fields.insert("date".to_string(), t.date.to_string().into());
+                fields.insert("time".to_string(), t.time_of_day().into());
+                fields.insert("epoch_ms".to_string(), t.epoch_millis().into());
+            }
+        }
+        let mut entry = LogEntry {
+            severity: match self.level_case_in_json {
+                true => self.level_case.apply(&entry.severity),
+                false => entry.severity,
+            },
+            location: self.location.then(|| Location::from_record(record)),
+            message: match &self.message_transform {
+                Some(transform) => transform(&entry.message),
+                None => entry.message,
+            },
+            ..entry
+        };
+        if self.escape_mode != EscapeMode::JsonStandard {
+            entry.message = entry::escape(&entry.message, self.escape_mode);
+            entry
+                .fields
+                .values_mut()
+                .for_each(|value| entry::escape_value(value, self.escape_mode));
+        }
+        entry
+    }
+}
+
+/// Options controlling how a CustomLogger opens its file
+///
+/// # Example
+/// ```
+/// # use crate::loggers::*;
+/// let logger = OpenOptions::new()
+///     .create_dirs(false)
+///     .append(true)
+///     .open("test", "system.log")
+///     .expect("Failed to open log file");
+/// ```
+#[derive(Debug, Clone)]
+pub struct OpenOptions {
+    create_dirs: bool,
+    append: bool,
+    rotate_existing: bool,
+    repair: bool,
+    #[cfg(unix)]
+    dir_mode: Option<u32>,
+    file_mode: Option<u32>,
+}
+
+impl OpenOptions {
+    pub fn new() -> OpenOptions {
+        OpenOptions {
+            create_dirs: true,
+            append: false,
+            rotate_existing: false,
+            repair: false,
+            #[cfg(unix)]
+            dir_mode: None,
+            file_mode: None,
+        }
+    }
+
+    /// create missing directories with the permission bits `mode`, subject to
+    /// the umask (default: 0o777 minus the umask)
+    #[cfg(unix)]
+    pub fn dir_mode(mut self, mode: u32) -> OpenOptions {
+        self.dir_mode = Some(mode);
+        self
+    }
+
+    /// set the permission bits of the log file, and of every segment started
+    /// by rotation, to exactly `mode` (default: 0o666 minus the umask)
+    /// # Example
+    /// ```
+    /// # use crate::loggers::*;
+    /// # #[cfg(unix)]
+    /// let logger = OpenOptions::new()
+    ///     .dir_mode(0o750)
+    ///     .file_mode(0o640)
+    ///     .open("test", "system.log")
+    ///     .expect("Failed to open log file");
+    /// ```
+    #[cfg(unix)]
+    pub fn file_mode(mut self, mode: u32) -> OpenOptions {
+        self.file_mode = Some(mode);
+        self
+    }
+
+    /// create missing parent directories of the log file (default: true)
+    ///
+    /// Disable this where the directory must already exist, e.g. a mounted
+    /// volume; opening then fails instead of creating the directory.
+    pub fn create_dirs(mut self, create_dirs: bool) -> OpenOptions {
+        self.create_dirs = create_dirs;
+        self
+    }
+
+    /// keep the existing contents of the file instead of truncating it (default: false)
+    pub fn append(mut self, append: bool) -> OpenOptions {
+        self.append = append;
+        self
+    }
+
+    /// move a non-empty existing file to `<filepath>.1` before opening, shifting
+    /// existing `<filepath>.N` segments up, instead of truncating it (default: false)
+    ///
+    /// Lets a restarted service keep the previous run's records as the newest
+    /// rotated segment. Has no effect together with [`append`](Self::append).
+    /// # Example
+    /// ```
+    /// # use crate::loggers::*;
+    /// let logger = OpenOptions::new()
+    ///     .rotate_existing(true)
+    ///     .open("test", "system.log")
+    ///     .expect("Failed to open log file");
+    /// ```
+    pub fn rotate_existing(mut self, rotate_existing: bool) -> OpenOptions {
+        self.rotate_existing = rotate_existing;
+        self
+    }
+
+    /// when appending, first [`repair_ndjson`] the existing file so that a
+    /// partial last line left by a crash does not precede the new records
+    /// (default: false)
+    /// # Example
+    /// ```
+    /// # use crate::loggers::*;
+    /// let logger = OpenOptions::new()
+    ///     .append(true)
+    ///     .repair(true)
+    ///     .open("test", "system.log")
+    ///     .expect("Failed to open log file");
+    /// ```
+    pub fn repair(mut self, repair: bool) -> OpenOptions {
+        self.repair = repair;
+        self
+    }
+
+    /// open `filepath` and create a CustomLogger for `target` writing to it
+    pub fn open(&self, target: &str, filepath: &str) -> std::io::Result<CustomLogger> {
+        if self.create_dirs {
+            if let Some(p) = Path::new(filepath).parent() {
+                let mut builder = std::fs::DirBuilder::new();
+                builder.recursive(true);
+                #[cfg(unix)]
+                if let Some(mode) = self.dir_mode {
+                    use std::os::unix::fs::DirBuilderExt;
+                    builder.mode(mode);
+                }
+                builder.create(p)?;
+            }
+        }
+        let path = Path::new(filepath);
+        if !self.append
+            && self.rotate_existing
+            && std::fs::metadata(path).is_ok_and(|m| m.len() > 0)
+        {
+            rotation::shift_segments(path)?;
+        }
+        if !self.append {
+            File::create(filepath)?;
+        } else if self.repair && path.exists() {
+            let removed = reader::repair_ndjson(path)?;
+            if removed > 0 {
+                eprintln!(
+                    "Removed a partial record of {} bytes from {:?}",
+                    removed, filepath
+                );
+            }
+        }
+        let mut logger =
+            CustomLogger::with_file(target, filepath, open_append(filepath, self.file_mode)?);
+        logger.file_mode = self.file_mode;
+        Ok(logger)
+    }
+}
+
+impl Default for OpenOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// compress `data` into a complete gzip member
+fn gzip(data: &[u8]) -> Vec<u8> {
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(data).unwrap();
+    encoder.finish().unwrap()
+}
+
+/// the number of lines in `file`, counting no further than `limit`
+fn count_lines(file: File, limit: u64) -> u64 {
+    let mut lines = 0;
+    for byte in std::io::BufReader::new(file).bytes() {
+        match byte {
+            Ok(b'\n') => lines += 1,
+            Ok(_) => continue,
+            Err(_) => break,
+        }
+        if lines >= limit {
+            break;
+        }
+    }
+    lines
+}
+
+/// open `filepath` for appending, creating it if needed; on Unix the file's
+/// permissions are set to `mode` if given, regardless of the umask
+fn open_append(filepath: &str, mode: Option<u32>) -> std::io::Result<File> {
+    let file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(filepath)?;
+    #[cfg(unix)]
+    if let Some(mode) = mode {
+        use std::os::unix::fs::PermissionsExt;
+        file.set_permissions(std::fs::Permissions::from_mode(mode))?;
+    }
+    #[cfg(not(unix))]
+    let _ = mode;
+    Ok(file)
+}
+
+impl log::Log for CustomLogger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        if inspect::is_hook(metadata) {
+            match &self.filepath {
+                Some(filepath) => inspect::report(filepath, || self.probe(filepath)),
+                None => inspect::report(&format!("{} (console)", self.target), || Ok(())),
+            }
+            inspect::report_config(|| self.config());
+            return false;
+        }
+        let level = metadata.level();
+        let min_level = match level::boosted() {
+            Some(boost) => self.min_level.load(Ordering::Relaxed).max(boost as usize),
+            None => self.min_level.load(Ordering::Relaxed),
+        };
+        if level as usize > min_level || level < self.max_level {
+            return false;
+        }
+        let target = metadata.target();
+        if self.any_target || target == self.target {
+            return true;
+        }
+        self.match_module_default
+            && target
+                .strip_prefix(self.target.as_str())
+                .is_some_and(|rest| rest.starts_with("::"))
+    }
+
+    fn log(&self, record: &log::Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        let timestamp = clock::timestamp();
+        let mut entry = self.entry(record, &timestamp);
+        if let Some(every_n) = &self.every_n {
+            if !every_n.admit(&mut entry) {
+                return;
+            }
+        }
+        if let Some(sampler) = &self.sampler {
+            if !sampler.admit(&mut entry) {
+                return;
+            }
+        }
+        if let Some(adaptive) = &self.adaptive {
+            let (admit, summary) = adaptive.admit(&mut entry, std::time::Instant::now());
+            if let Some(summary) = summary {
+                self.emit(&summary);
+            }
+            if !admit {
+                return;
+            }
+        }
+        match &self.aggregate {
+            Some(aggregate) => {
+                if let Some(entry) = aggregate.push(entry) {
+                    self.emit(&entry);
+                }
+            }
+            None => self.emit(&entry),
+        }
+    }
+
+    fn flush(&self) {
+        if let Some(entry) = self.aggregate.as_ref().and_then(|a| a.take()) {
+            self.emit(&entry);
+        }
+    }
+}
+
+impl CustomLogger {
+    /// check that `filepath` can be opened for appending, without creating
+    /// or writing to it
+    fn probe(&self, filepath: &str) -> std::io::Result<()> {
+        std::fs::OpenOptions::new()
+            .append(true)
+            .open(filepath)
+            .map(drop)
+    }
+
+    /// write `entry` to the file and print it to the console
+    fn emit(&self, entry: &LogEntry) {
+        if let Some(ref filepath) = self.filepath {
+            let mut line = match self.reuse_buffers {
+                true => entry::take_buffer(),
+                false => Vec::with_capacity(256),
+            };
+            // Without renames or a hash chain the line depends on nothing but
+            // `entry`, so it is rendered before taking the file lock.
+            let fast_path = self.is_fast_path();
+            if fast_path {
+                self.encode(entry, entry, &mut line);
+            }
+            let mut file = self.file.lock().unwrap();
+            if !fast_path {
+                self.render_general(entry, &mut line);
+            }
+            if self.watch_inode
+                && file
+                    .as_ref()
+                    .is_some_and(|f| rotation::replaced(Path::new(filepath), f))
+            {
+                self.close_file(filepath, &mut file);
+                match open_append(filepath, self.file_mode) {
+                    Ok(f) => *file = Some(f),
+                    Err(e) => eprintln!("Cannot reopen file {:?}: {}", filepath, e),
+                }
+            }
+            let mut finalized = Vec::new();
+            if self.rotate_daily && file.is_some() {
+                finalized.extend(self.rotate_for_date(filepath, &mut file, entry));
+            }
+            let mut rotate = false;
+            match *file {
+                Some(ref mut f) => {
+                    if self.emit_bom
+                        && self.format == Format::Json
+                        && f.metadata().is_ok_and(|m| m.len() == 0)
+                    {
+                        line.splice(0..0, reader::BOM.bytes());
+                    }
+                    let compressed;
+                    let data = match self.compress_active {
+                        true => {
+                            compressed = gzip(&line);
+                            &compressed
+                        }
+                        false => &line,
+                    };
+                    match f.write_all(data) {
+                        Ok(()) => self.counters.add(data.len() as u64),
+                        Err(e) => eprintln!("Cannot write to file {:?}: {}", filepath, e),
+                    }
+                    if self.fsync {
+                        let synced = match &self.coalesce {
+                            Some(coalesce) => coalesce.sync(f),
+                            None => f.sync_all(),
+                        };
+                        if let Err(e) = synced {
+                            eprintln!("Cannot sync file {:?}: {}", filepath, e);
+                        }
+                    }
+                    if let Some(max_size) = self.max_size {
+                        rotate = f.metadata().map(|m| m.len() >= max_size).unwrap_or(false);
+                    }
+                    if let Some(max_lines) = self.max_lines {
+                        rotate |= self.lines.fetch_add(1, Ordering::Relaxed) + 1 >= max_lines;
+                    }
+                }
+                None => {
+                    println!("Cannot open file {:?}", self.filepath);
+                }
+            }
+            if rotate {
+                let date = *self.segment_date.lock().unwrap();
+                match self.rotate_locked(filepath, &mut file, date) {
+                    Ok(rotated) => finalized.extend(rotated),
+                    Err(e) => eprintln!("Cannot rotate file {:?}: {}", filepath, e),
+                }
+            }
+            drop(file);
+            for segment in &finalized {
+                self.notify_rotated(Some(segment));
+            }
+            if self.reuse_buffers {
+                entry::give_buffer(line);
+            }
+        }
+
+        if self.console {
+            println!("{}", self.console_line(entry));
+        }
+    }
+
+    /// whether file lines can be rendered straight from the entry
+    fn is_fast_path(&self) -> bool {
+        self.hash_chain.is_none() && self.renames.is_empty() && !self.sort_keys
+    }
+
+    /// append `record` to `out`, encoded in the configured format
+    fn encode<T: serde::Serialize>(&self, record: &T, entry: &LogEntry, out: &mut Vec<u8>) {
+        match self.format {
+            Format::Json => entry::json_line(out, record, entry, self.indent),
+            Format::Gelf => gelf::line(out, record, entry),
+            #[cfg(feature = "msgpack")]
+            Format::MessagePack => msgpack::frame(out, record, entry),
+            #[cfg(feature = "protobuf")]
+            Format::Protobuf => protobuf::frame(out, record, entry),
+        }
+    }
+
+    /// append the encoded record for `entry` to `out`, applying renames,
+    /// linking the hash chain and sorting keys. Must be called with the file
+    /// lock held so that chained records reach the file in the order they
+    /// were linked.
+    fn render_general(&self, entry: &LogEntry, out: &mut Vec<u8>) {
+        let mut record = match serde_json::to_value(entry).unwrap() {
+            serde_json::Value::Object(record) => record,
+            _ => unreachable!("LogEntry serializes to an object"),
+        };
+        for (from, to) in &self.renames {
+            entry::rename(&mut record, from, to);
+        }
+        if let Some(prev_hash) = &self.hash_chain {
+            let mut prev_hash = prev_hash.lock().unwrap();
+            *prev_hash = chain::link(&mut record, &prev_hash);
+        }
+        let mut record = serde_json::Value::Object(record);
+        if self.sort_keys {
+            entry::sort_keys(&mut record);
+        }
+        self.encode(&record, entry, out)
+    }
+
+    /// the line printed to the console for `entry` in the console format,
+    /// which is independent of the file format
+    fn console_line(&self, entry: &LogEntry) -> String {
+        match self.console_format {
+            ConsoleFormat::Human => self.human_line(entry),
+            ConsoleFormat::Json => serde_json::to_string(entry).unwrap(),
+            ConsoleFormat::Logfmt => entry::logfmt_line(entry),
+            ConsoleFormat::Pretty => self.pretty_lines(entry),
+        }
+    }
+
+    /// the human line for `entry`, then its location and fields indented
+    fn pretty_lines(&self, entry: &LogEntry) -> String {
+        let mut out = self.human_line(entry);
+        if let Some(Location {
+            file: Some(file),
+            line,
+            ..
+        }) = &entry.location
+        {
+            out.push_str("\n    at ");
+            out.push_str(file);
+            if let Some(line) = line {
+                out.push_str(&format!(":{}", line));
+            }
+        }
+        for (key, value) in &entry.fields {
+            match value {
+                _ if key == "schema_version" => {}
+                serde_json::Value::String(value) => {
+                    out.push_str(&format!("\n    {}: {}", key, value))
+                }
+                value => out.push_str(&format!("\n    {}: {}", key, value)),
+            }
+        }
+        out
+    }
+
+    /// the human readable line for `entry`
+    fn human_line(&self, entry: &LogEntry) -> String {
+        let level = self.level_case.apply(&entry.severity);
+        let level = match entry.severity.parse::<log::Level>() {
+            Ok(l) if self.color => {
+                let color = match self.color_map.iter().find(|(level, _)| *level == l) {
+                    Some((_, color)) => color.as_str(),
+                    None => color::level_color(l),
+                };
+                color::paint(color, &format!("[{}]", level))
+            }
+            _ => format!("[{}]", level),
+        };
+        format!(
+            "{} {} {} - {}",
+            level, entry.target, entry.timestamp, entry.message,
+        )
+    }
+}
+
+impl Drop for CustomLogger {
+    fn drop(&mut self) {
+        log::Log::flush(self);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use log::{debug, info};
+    use serde_json::Value;
+    use std::fs::File;
+    use std::io::Read;
+    use std::path::Path;
+
+    #[test]
+    fn test_log() {
+        let log_file = Path::new("tests/output/test.log");
+        if log_file.exists() {
+            std::fs::remove_file(log_file).unwrap();
+        }
+
+        let mut logger = Logger::new();
+        logger.add_logger(Box::new(CustomLogger::new(
+            "test",
+            "tests/output/system.log",
+        )));
+        logger.set_fallback(Box::new(CustomLogger::new(
+            "default",
+            "tests/output/system.log",
+        )));
+        log::set_boxed_logger(Box::new(logger)).expect("Failed to set logger");
+        log::set_max_level(log::LevelFilter::Trace);
+
+        info!(target:"test", "Hello, world!");
+        debug!("Default");
+
+        let mut file = File::open("tests/output/system.log").unwrap();
+        let mut contents = String::new();
+        file.read_to_string(&mut contents).unwrap();
 
         let v: Value = serde_json::from_str(&contents).unwrap();
         assert_eq!(v["severity"], "INFO");
         assert_eq!(v["target"], "test");
         assert_eq!(v["message"], "Hello, world!");
     }
+
+    fn log_to(logger: &CustomLogger, message: &str) {
+        log_at(logger, log::Level::Info, message);
+    }
+
+    fn log_at(logger: &CustomLogger, level: log::Level, message: &str) {
+        log::Log::log(
+            logger,
+            &log::Record::builder()
+                .target(&logger.target)
+                .level(level)
+                .args(format_args!("{}", message))
+                .build(),
+        );
+    }
+
+    fn read_messages(path: &str) -> Vec<String> {
+        read_log(path, false)
+            .map(|entry| entry.unwrap().message)
+            .collect()
+    }
+
+    #[test]
+    fn test_rotate() {
+        let path = "tests/output/rotate/rotate.log";
+        let _ = std::fs::remove_dir_all("tests/output/rotate");
+
+        let logger = CustomLogger::new("rotate", path);
+        log_to(&logger, "first");
+        log_to(&logger, "second");
+        logger.rotate().unwrap();
+        log_to(&logger, "third");
+
+        assert_eq!(
+            read_messages("tests/output/rotate/rotate.log.1"),
+            vec!["first", "second"]
+        );
+        assert_eq!(read_messages(path), vec!["third"]);
+
+        logger.rotate().unwrap();
+        assert_eq!(
+            read_messages("tests/output/rotate/rotate.log.1"),
+            vec!["third"]
+        );
+        assert_eq!(
+            read_messages("tests/output/rotate/rotate.log.2"),
+            vec!["first", "second"]
+        );
+        assert!(read_messages(path).is_empty());
+    }
+
+    #[test]
+    fn test_max_lines() {
+        let dir = "tests/output/max_lines";
+        let _ = std::fs::remove_dir_all(dir);
+        let path = format!("{}/app.log", dir);
+        let segment = format!("{}.1", path);
+
+        let logger = CustomLogger::new("max_lines", &path).max_lines(3);
+        for i in 1..=4 {
+            log_to(&logger, &i.to_string());
+        }
+        assert_eq!(read_messages(&segment), vec!["1", "2", "3"]);
+        assert_eq!(read_messages(&path), vec!["4"]);
+        drop(logger);
+
+        // after a restart the existing line counts towards the limit
+        let logger = CustomLogger::append("max_lines", &path)
+            .unwrap()
+            .max_lines(3);
+        log_to(&logger, "5");
+        log_to(&logger, "6");
+        assert_eq!(read_messages(&format!("{}.1", path)), vec!["4", "5", "6"]);
+        assert_eq!(read_messages(&format!("{}.2", path)), vec!["1", "2", "3"]);
+        assert!(read_messages(&path).is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_rotate_failure() {
+        let dir = "tests/output/rotate_failure";
+        let _ = std::fs::remove_dir_all(dir);
+        let path = format!("{}/app.log", dir);
+        // `app.log.1` cannot be shifted onto the dangling symlink `app.log.2`
+        std::fs::create_dir_all(format!("{}.1", path)).unwrap();
+        std::os::unix::fs::symlink("missing", format!("{}.2", path)).unwrap();
+
+        let logger = CustomLogger::new("rotate_failure", &path)
+            .max_lines(1)
+            .console(false);
+        log_to(&logger, "1");
+        assert!(logger.rotate().is_err());
+        log_to(&logger, "2");
+        assert_eq!(read_messages(&path), vec!["1", "2"]);
+    }
+
+    #[test]
+    fn test_segments() {
+        let dir = "tests/output/segments";
+        let _ = std::fs::remove_dir_all(dir);
+        let path = format!("{}/app.log", dir);
+
+        let logger = CustomLogger::new("segments", &path);
+        for message in ["1", "2"] {
+            log_to(&logger, message);
+            logger.rotate().unwrap();
+        }
+        // compress the oldest segment as an archiving tool would
+        let oldest = format!("{}.2", path);
+        std::fs::write(
+            format!("{}.gz", oldest),
+            gzip(&std::fs::read(&oldest).unwrap()),
+        )
+        .unwrap();
+        std::fs::remove_file(&oldest).unwrap();
+        // an unrelated file sharing the prefix is not a segment
+        std::fs::write(format!("{}.bak", path), "").unwrap();
+        log_to(&logger, "3");
+
+        let names: Vec<String> = logger
+            .segments()
+            .iter()
+            .map(|p| p.file_name().unwrap().to_string_lossy().into_owned())
+            .collect();
+        assert_eq!(names, vec!["app.log", "app.log.1", "app.log.2.gz"]);
+
+        let messages: Vec<String> = read_segments(&path, false)
+            .map(|e| e.unwrap().message)
+            .collect();
+        assert_eq!(messages, vec!["1", "2", "3"]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_file_mode() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = "tests/output/file_mode";
+        let _ = std::fs::remove_dir_all(dir);
+        let path = format!("{}/private/app.log", dir);
+        let mode = |path: &str| std::fs::metadata(path).unwrap().permissions().mode() & 0o777;
+
+        let logger = OpenOptions::new()
+            .dir_mode(0o700)
+            .file_mode(0o600)
+            .open("file_mode", &path)
+            .unwrap();
+        log_to(&logger, "secret");
+        assert_eq!(mode(&format!("{}/private", dir)), 0o700);
+        assert_eq!(mode(&path), 0o600);
+
+        logger.rotate().unwrap();
+        assert_eq!(mode(&path), 0o600);
+        assert_eq!(mode(&format!("{}.1", path)), 0o600);
+    }
+
+    #[test]
+    fn test_rotate_existing() {
+        let dir = "tests/output/rotate_existing";
+        let _ = std::fs::remove_dir_all(dir);
+        let path = format!("{}/app.log", dir);
+        let segment = |n: usize| format!("{}.{}", path, n);
+
+        // a previous run left the active file and one rotated segment
+        log_to(
+            &CustomLogger::new("rotate_existing", &segment(1)),
+            "older run",
+        );
+        log_to(&CustomLogger::new("rotate_existing", &path), "old run");
+
+        let logger = OpenOptions::new()
+            .rotate_existing(true)
+            .open("rotate_existing", &path)
+            .unwrap();
+        assert!(read_messages(&path).is_empty());
+        assert_eq!(read_messages(&segment(1)), vec!["old run"]);
+        assert_eq!(read_messages(&segment(2)), vec!["older run"]);
+
+        log_to(&logger, "new run");
+        logger.rotate().unwrap();
+        assert_eq!(read_messages(&segment(1)), vec!["new run"]);
+        assert_eq!(read_messages(&segment(2)), vec!["old run"]);
+        assert_eq!(read_messages(&segment(3)), vec!["older run"]);
+        drop(logger);
+
+        // appending to the existing file continues the numbering as well
+        let logger = CustomLogger::append("rotate_existing", &path).unwrap();
+        log_to(&logger, "appended");
+        logger.rotate().unwrap();
+        assert_eq!(read_messages(&segment(1)), vec!["appended"]);
+        assert_eq!(read_messages(&segment(4)), vec!["older run"]);
+    }
+
+    #[test]
+    fn test_level_range() {
+        use log::Level;
+
+        let path = "tests/output/level_range.log";
+        let logger = CustomLogger::new("level_range", path).level_range(Level::Trace, Level::Debug);
+        for level in [
+            Level::Error,
+            Level::Warn,
+            Level::Info,
+            Level::Debug,
+            Level::Trace,
+        ] {
+            log_at(&logger, level, &level.to_string());
+        }
+        assert_eq!(read_messages(path), vec!["DEBUG", "TRACE"]);
+
+        let path = "tests/output/min_level.log";
+        let logger = CustomLogger::new("min_level", path).min_level(Level::Warn);
+        for level in [
+            Level::Error,
+            Level::Warn,
+            Level::Info,
+            Level::Debug,
+            Level::Trace,
+        ] {
+            log_at(&logger, level, &level.to_string());
+        }
+        assert_eq!(read_messages(path), vec!["ERROR", "WARN"]);
+    }
+
+    #[test]
+    fn test_fsync() {
+        let path = "tests/output/fsync.log";
+        let logger = CustomLogger::new("fsync", path).fsync(true);
+        log_to(&logger, "durable");
+        assert_eq!(read_messages(path), vec!["durable"]);
+    }
+
+    /// poll `done` until it holds, failing the test after five seconds
+    fn wait_for<F: Fn() -> bool>(done: F) {
+        let started = std::time::Instant::now();
+        while !done() {
+            assert!(started.elapsed() < std::time::Duration::from_secs(5));
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn test_coalesce_flush() {
+        let path = "tests/output/coalesce_flush.log";
+        let interval = std::time::Duration::from_millis(50);
+        let logger = CustomLogger::new("coalesce_flush", path)
+            .fsync(true)
+            .coalesce_flush(interval)
+            .console(false);
+        let coalesce = || logger.coalesce.as_ref().unwrap();
+
+        let start = std::time::Instant::now();
+        for i in 0..500 {
+            log_to(&logger, &format!("record {}", i));
+        }
+        let elapsed = start.elapsed();
+        let bound = (elapsed.as_millis() / interval.as_millis()) as u64 + 2;
+        assert!(coalesce().syncs() <= bound, "{} syncs", coalesce().syncs());
+        assert_eq!(read_messages(path).len(), 500);
+
+        // the tail of the burst is synced once the interval has passed
+        let synced = coalesce().syncs();
+        wait_for(|| !coalesce().is_pending());
+        assert!(coalesce().syncs() <= synced + 1);
+    }
+
+    #[test]
+    fn test_coalesce_flush_rotation() {
+        let path = "tests/output/coalesce_rotation.log";
+        let _ = std::fs::remove_file(format!("{}.1", path));
+        let interval = std::time::Duration::from_millis(300);
+        let logger = CustomLogger::new("coalesce_rotation", path)
+            .fsync(true)
+            .coalesce_flush(interval)
+            .max_lines(2)
+            .console(false);
+        let coalesce = || logger.coalesce.as_ref().unwrap();
+
+        log_to(&logger, "first");
+        assert_eq!(coalesce().syncs(), 1);
+        // left to the background thread, then synced when rotation closes it
+        log_to(&logger, "second");
+        assert!(!coalesce().is_pending());
+        assert_eq!(coalesce().syncs(), 2);
+
+        // the new file is synced, in the background unless the interval
+        // already passed
+        log_to(&logger, "third");
+        wait_for(|| !coalesce().is_pending() && coalesce().syncs() >= 3);
+        assert_eq!(coalesce().syncs(), 3);
+        assert_eq!(
+            read_messages(&format!("{}.1", path)),
+            vec!["first", "second"]
+        );
+        assert_eq!(read_messages(path), vec!["third"]);
+    }
+
+    #[test]
+    fn test_aggregate_window() {
+        let path = "tests/output/aggregate.log";
+        let logger = CustomLogger::new("aggregate", path)
+            .aggregate_window(std::time::Duration::from_secs(60));
+        for _ in 0..5 {
+            log_to(&logger, "connection refused");
+            std::thread::sleep(std::time::Duration::from_millis(2));
+        }
+        log_to(&logger, "recovered");
+        log::Log::flush(&logger);
+
+        let entries: Vec<LogEntry> = read_log(path, false).map(|e| e.unwrap()).collect();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].message, "connection refused");
+        assert_eq!(entries[0].fields["count"], 5);
+        assert_eq!(
+            entries[0].fields["first_seen"],
+            entries[0].timestamp.as_str()
+        );
+        let first_seen = entries[0].fields["first_seen"].as_str().unwrap();
+        let last_seen = entries[0].fields["last_seen"].as_str().unwrap();
+        assert!(first_seen < last_seen);
+        assert_eq!(entries[1].message, "recovered");
+        assert!(entries[1].fields.get("count").is_none());
+    }
+
+    #[test]
+    fn test_create_dirs() {
+        let _ = std::fs::remove_dir_all("tests/output/create_dirs");
+
+        let path = "tests/output/create_dirs/missing/disabled.log";
+        let err = OpenOptions::new()
+            .create_dirs(false)
+            .open("create_dirs", path)
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), std::io::ErrorKind::NotFound);
+        assert!(!Path::new("tests/output/create_dirs/missing").exists());
+
+        let path = "tests/output/create_dirs/nested/enabled.log";
+        let logger = CustomLogger::try_new("create_dirs", path).unwrap();
+        log_to(&logger, "created");
+        assert_eq!(read_messages(path), vec!["created"]);
+    }
+
+    #[test]
+    fn test_level_case() {
+        assert_eq!(LevelCase::Upper.apply("Warn"), "WARN");
+        assert_eq!(LevelCase::Title.apply("WARN"), "Warn");
+
+        let path = "tests/output/level_case.log";
+        let logger = CustomLogger::new("level_case", path).level_case(LevelCase::Lower);
+        log_at(&logger, log::Level::Warn, "quiet");
+        let entry = read_log(path, false).next().unwrap().unwrap();
+        assert_eq!(entry.severity, "WARN");
+        assert!(logger
+            .console_line(&entry)
+            .starts_with("[warn] level_case "));
+
+        let logger = logger.level_case_in_json(true);
+        log_at(&logger, log::Level::Warn, "quieter");
+        let entry = read_log(path, false).nth(1).unwrap().unwrap();
+        assert_eq!(entry.severity, "warn");
+    }
+
+    #[test]
+    fn test_compress_active() {
+        let path = "tests/output/compress_active/active.log.gz";
+        let _ = std::fs::remove_dir_all("tests/output/compress_active");
+
+        let decompress = |path: &str| {
+            let mut text = String::new();
+            flate2::read::MultiGzDecoder::new(File::open(path).unwrap())
+                .read_to_string(&mut text)
+                .unwrap();
+            text.lines()
+                .map(|line| serde_json::from_str::<LogEntry>(line).unwrap().message)
+                .collect::<Vec<_>>()
+        };
+
+        let logger = CustomLogger::new("compress_active", path).compress_active(true);
+        log_to(&logger, "one");
+        log_to(&logger, "two");
+        assert_eq!(decompress(path), vec!["one", "two"]);
+
+        logger.rotate().unwrap();
+        log_to(&logger, "three");
+        assert_eq!(decompress(&format!("{}.1", path)), vec!["one", "two"]);
+        assert_eq!(decompress(path), vec!["three"]);
+    }
+
+    #[test]
+    fn test_target_field() {
+        let record = |args| {
+            log::Record::builder()
+                .target("app::db")
+                .level(log::Level::Info)
+                .args(args)
+                .build()
+        };
+
+        let path = "tests/output/target_field.log";
+        let logger = CustomLogger::new("app", path).match_module_default(true);
+        log::Log::log(&logger, &record(format_args!("query")));
+        let logger = logger.target_field(TargetField::Logger);
+        log::Log::log(&logger, &record(format_args!("query")));
+
+        let targets: Vec<String> = read_log(path, false).map(|e| e.unwrap().target).collect();
+        assert_eq!(targets, vec!["app::db", "app"]);
+    }
+
+    #[test]
+    fn test_log_record() {
+        let path = "tests/output/log_record.log";
+        let mut logger = Logger::new();
+        logger.add_logger(Box::new(CustomLogger::new("replay", path)));
+        logger.log_record(
+            log::Level::Warn,
+            "replay",
+            "disk almost full",
+            &[
+                ("used", serde_json::json!(0.93)),
+                ("mount", serde_json::json!("/var")),
+            ],
+        );
+
+        let entries: Vec<LogEntry> = read_log(path, false).map(|e| e.unwrap()).collect();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].severity, "WARN");
+        assert_eq!(entries[0].target, "replay");
+        assert_eq!(entries[0].message, "disk almost full");
+        assert_eq!(entries[0].fields["used"], 0.93);
+        assert_eq!(entries[0].fields["mount"], "/var");
+    }
+
+    #[test]
+    fn test_rotate_daily_and_size() {
+        let dir = "tests/output/rotate_daily";
+        let _ = std::fs::remove_dir_all(dir);
+        let path = "tests/output/rotate_daily/app.log";
+
+        let logger = CustomLogger::new("rotate_daily", path)
+            .rotate_daily(true)
+            .max_size(300)
+            .console(false);
+        let at = |timestamp: &str, message: &str| {
+            let entry = logger.entry(
+                &log::Record::builder()
+                    .target("rotate_daily")
+                    .level(log::Level::Info)
+                    .args(format_args!("{}", message))
+                    .build(),
+                timestamp,
+            );
+            logger.emit(&entry);
+        };
+        // each record is ~110 bytes, so the third of a day triggers the size rotation
+        at("2024-01-15T10:00:00.000+00:00", "day1 a");
+        at("2024-01-15T11:00:00.000+00:00", "day1 b");
+        at("2024-01-15T12:00:00.000+00:00", "day1 c");
+        at("2024-01-15T13:00:00.000+00:00", "day1 d");
+        at("2024-01-16T00:00:01.000+00:00", "day2 a");
+
+        let read = |name: &str| read_messages(&format!("{}/{}", dir, name));
+        assert_eq!(
+            read("app.log.2024-01-15.1"),
+            vec!["day1 a", "day1 b", "day1 c"]
+        );
+        assert_eq!(read("app.log.2024-01-15.2"), vec!["day1 d"]);
+        assert_eq!(read("app.log"), vec!["day2 a"]);
+        assert_eq!(std::fs::read_dir(dir).unwrap().count(), 3);
+    }
+
+    #[test]
+    fn test_fast_path() {
+        let logger = CustomLogger::console_only("fast_path").location(true);
+        assert!(logger.is_fast_path());
+        let kvs = [("user", "ferris"), ("quote", "say \"hi\"\n")];
+        let entry = logger.entry(
+            &log::Record::builder()
+                .target("fast_path")
+                .level(log::Level::Warn)
+                .key_values(&kvs)
+                .args(format_args!("fast {}", "path"))
+                .build(),
+            "2024-01-01T00:00:00.000Z",
+        );
+        let (mut fast, mut general) = (Vec::new(), Vec::new());
+        entry::json_line(&mut fast, &entry, &entry, 0);
+        logger.render_general(&entry, &mut general);
+        assert_eq!(fast, general);
+    }
+
+    #[test]
+    fn test_message_transform() {
+        fn strip_ansi(message: &str) -> String {
+            let mut clean = String::new();
+            let mut chars = message.chars();
+            while let Some(c) = chars.next() {
+                if c == '\x1b' {
+                    chars.by_ref().find(|c| c.is_ascii_alphabetic());
+                } else {
+                    clean.push(c);
+                }
+            }
+            clean
+        }
+
+        let path = "tests/output/message_transform.log";
+        let logger =
+            CustomLogger::new("message_transform", path).message_transform(Box::new(strip_ansi));
+        log_to(&logger, "\x1b[1;31mfailed\x1b[0m to \x1b[4mconnect\x1b[0m");
+        assert_eq!(read_messages(path), vec!["failed to connect"]);
+    }
+
+    #[test]
+    fn test_dispatch_mode() {
+        for (mode, name) in [
+            (DispatchMode::Broadcast, "broadcast"),
+            (DispatchMode::FirstMatch, "first_match"),
+        ] {
+            let first = format!("tests/output/dispatch_{}_first.log", name);
+            let second = format!("tests/output/dispatch_{}_second.log", name);
+            let mut logger = Logger::new();
+            logger.set_dispatch_mode(mode);
+            logger.add_logger(Box::new(CustomLogger::new("dispatch", &first)));
+            logger.add_logger(Box::new(CustomLogger::new("dispatch", &second)));
+            logger.log_record(log::Level::Info, "dispatch", "overlap", &[]);
+
+            assert_eq!(read_messages(&first), vec!["overlap"]);
+            let expected: Vec<&str> = match mode {
+                DispatchMode::Broadcast => vec!["overlap"],
+                DispatchMode::FirstMatch => vec![],
+            };
+            assert_eq!(read_messages(&second), expected);
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_watch_inode() {
+        let dir = "tests/output/watch_inode";
+        let _ = std::fs::remove_dir_all(dir);
+        let path = format!("{}/app.log", dir);
+        let moved = format!("{}/app.log.1", dir);
+
+        let logger = CustomLogger::new("watch_inode", &path).watch_inode(true);
+        log_to(&logger, "before");
+        std::fs::rename(&path, &moved).unwrap();
+        log_to(&logger, "after");
+
+        assert_eq!(read_messages(&moved), vec!["before"]);
+        assert_eq!(read_messages(&path), vec!["after"]);
+
+        let _ = std::fs::remove_dir_all(dir);
+        let logger = CustomLogger::new("watch_inode", &path);
+        std::fs::rename(&path, &moved).unwrap();
+        logger.reopen().unwrap();
+        log_to(&logger, "reopened");
+        assert_eq!(read_messages(&path), vec!["reopened"]);
+    }
+
+    #[test]
+    fn test_serialization_failed() {
+        struct Unserializable;
+
+        impl serde::Serialize for Unserializable {
+            fn serialize<S: serde::Serializer>(&self, _: S) -> Result<S::Ok, S::Error> {
+                Err(serde::ser::Error::custom("boom"))
+            }
+        }
+
+        let path = "tests/output/serialization_failed.log";
+        let logger = CustomLogger::new("serialization_failed", path);
+        let kvs = [
+            ("user", log::kv::Value::from("ferris")),
+            ("bad", log::kv::Value::from_serde(&Unserializable)),
+        ];
+        log::Log::log(
+            &logger,
+            &log::Record::builder()
+                .target("serialization_failed")
+                .level(log::Level::Info)
+                .key_values(&kvs)
+                .args(format_args!("still written"))
+                .build(),
+        );
+
+        let entries: Vec<_> = read_log(path, false).map(|e| e.unwrap()).collect();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].message, "still written");
+        assert_eq!(entries[0].fields.len(), 2);
+        assert_eq!(entries[0].fields["error"], "serialization_failed");
+        assert_eq!(entries[0].fields["schema_version"], SCHEMA_VERSION);
+    }
+
+    #[test]
+    fn test_sort_keys() {
+        fn keys(line: &str) -> Vec<String> {
+            let v: serde_json::Value = serde_json::from_str(line).unwrap();
+            v.as_object().unwrap().keys().cloned().collect()
+        }
+
+        let path = "tests/output/sort_keys.log";
+        let kvs = [("zone", "eu"), ("attempt", "2")];
+        for sort in [false, true] {
+            let logger = CustomLogger::new("sort_keys", path).sort_keys(sort);
+            log::Log::log(
+                &logger,
+                &log::Record::builder()
+                    .target("sort_keys")
+                    .level(log::Level::Info)
+                    .key_values(&kvs)
+                    .args(format_args!("sorted"))
+                    .build(),
+            );
+            let contents = std::fs::read_to_string(path).unwrap();
+            let expected = match sort {
+                false => [
+                    "severity",
+                    "timestamp",
+                    "target",
+                    "message",
+                    "schema_version",
+                    "zone",
+                    "attempt",
+                ],
+                true => [
+                    "attempt",
+                    "message",
+                    "schema_version",
+                    "severity",
+                    "target",
+                    "timestamp",
+                    "zone",
+                ],
+            };
+            assert_eq!(keys(contents.trim_end()), expected);
+        }
+
+        let logger = CustomLogger::new("sort_keys", path)
+            .indent(2)
+            .sort_keys(true);
+        log_to(&logger, "pretty");
+        let contents = std::fs::read_to_string(path).unwrap();
+        assert!(contents.starts_with("{\n  \"message\": \"pretty\",\n"));
+    }
+
+    #[test]
+    fn test_run_id() {
+        let api = "tests/output/run_id_api.log";
+        let db = "tests/output/run_id_db.log";
+        let mut logger = Logger::with_run_id("run-42");
+        logger.add_logger(Box::new(CustomLogger::new("api", api)));
+        logger.add_logger(Box::new(CustomLogger::new("db", db)));
+        logger.log_record(log::Level::Info, "api", "request", &[]);
+        logger.log_record(
+            log::Level::Info,
+            "db",
+            "query",
+            &[("rows", serde_json::json!(3))],
+        );
+
+        let api: Vec<_> = read_log(api, false).map(|e| e.unwrap()).collect();
+        let db: Vec<_> = read_log(db, false).map(|e| e.unwrap()).collect();
+        assert_eq!(api[0].fields["run_id"], "run-42");
+        assert_eq!(api[0].fields["run_id"], db[0].fields["run_id"]);
+        assert_eq!(db[0].fields["rows"], 3);
+    }
+
+    #[test]
+    fn test_fallback_unmatched() {
+        let api = "tests/output/unmatched_api.log";
+        let default = "tests/output/unmatched_default.log";
+        let mut logger = Logger::new();
+        logger.add_logger(Box::new(CustomLogger::new("api", api).console(false)));
+        logger.set_fallback(Box::new(
+            CustomLogger::new("default", default)
+                .any_target(true)
+                .console(false),
+        ));
+        logger.log_record(log::Level::Info, "api", "request", &[]);
+        logger.log_record(log::Level::Warn, "billing::typo", "misrouted", &[]);
+
+        let api = read_log(api, false).next().unwrap().unwrap();
+        assert!(!api.fields.contains_key("unmatched"));
+        let unmatched: Vec<_> = read_log(default, false).map(|e| e.unwrap()).collect();
+        assert_eq!(unmatched.len(), 1);
+        assert_eq!(unmatched[0].target, "billing::typo");
+        assert_eq!(unmatched[0].fields["unmatched"], true);
+    }
+
+    #[test]
+    fn test_set_level() {
+        let path = "tests/output/set_level.log";
+        let logger = CustomLogger::new("set_level", path).min_level(log::Level::Info);
+        log_at(&logger, log::Level::Debug, "hidden");
+        logger.set_level(log::LevelFilter::Trace);
+        log_at(&logger, log::Level::Debug, "shown");
+        logger.set_level(log::LevelFilter::Off);
+        log_at(&logger, log::Level::Error, "off");
+        assert_eq!(read_messages(path), vec!["shown"]);
+    }
+
+    #[test]
+    fn test_env_level_override() {
+        std::env::set_var("LOGGERS_LEVEL_env_level__api", "debug");
+        let api = CustomLogger::new("env_level::api", "tests/output/env_level_api.log")
+            .min_level(log::Level::Warn);
+        let db = CustomLogger::new("env_level::db", "tests/output/env_level_db.log")
+            .min_level(log::Level::Warn);
+        std::env::remove_var("LOGGERS_LEVEL_env_level__api");
+
+        let accepts = |logger: &CustomLogger, target, level| {
+            log::Log::enabled(
+                logger,
+                &log::Metadata::builder().target(target).level(level).build(),
+            )
+        };
+        assert!(accepts(&api, "env_level::api", log::Level::Debug));
+        assert!(!accepts(&api, "env_level::api", log::Level::Trace));
+        assert!(!accepts(&db, "env_level::db", log::Level::Info));
+        assert!(accepts(&db, "env_level::db", log::Level::Warn));
+    }
+
+    #[test]
+    fn test_split_timestamp() {
+        let path = "tests/output/split_timestamp.log";
+        let logger = CustomLogger::new("split_timestamp", path).split_timestamp(true);
+        log_to(&logger, "split");
+
+        let entry = read_log(path, false).next().unwrap().unwrap();
+        let date = entry.fields["date"].as_str().unwrap();
+        let time = entry.fields["time"].as_str().unwrap();
+        let epoch_ms = entry.fields["epoch_ms"].as_i64().unwrap();
+        assert!(entry.timestamp.starts_with(&format!("{}T{}", date, time)));
+
+        let offset = *chrono::DateTime::parse_from_rfc3339(&entry.timestamp)
+            .unwrap()
+            .offset();
+        let local = chrono::NaiveDateTime::parse_from_str(
+            &format!("{} {}", date, time),
+            "%Y-%m-%d %H:%M:%S%.3f",
+        )
+        .unwrap();
+        let utc = local - chrono::Duration::seconds(offset.local_minus_utc().into());
+        assert_eq!(utc.and_utc().timestamp_millis(), epoch_ms);
+    }
+
+    #[test]
+    fn test_emit_bom() {
+        let dir = "tests/output/emit_bom";
+        let _ = std::fs::remove_dir_all(dir);
+        let path = format!("{}/bom.log", dir);
+        let logger = CustomLogger::new("emit_bom", &path).emit_bom(true);
+        log_to(&logger, "first");
+        log_to(&logger, "second");
+        drop(logger);
+        let logger = CustomLogger::append("emit_bom", &path)
+            .unwrap()
+            .emit_bom(true);
+        log_to(&logger, "appended");
+
+        let bytes = std::fs::read(&path).unwrap();
+        assert!(bytes.starts_with(b"\xEF\xBB\xBF{"));
+        let bom = bytes.windows(3).filter(|w| w == b"\xEF\xBB\xBF").count();
+        assert_eq!(bom, 1);
+        assert_eq!(read_messages(&path), vec!["first", "second", "appended"]);
+
+        logger.rotate().unwrap();
+        log_to(&logger, "rotated");
+        assert!(std::fs::read(&path).unwrap().starts_with(b"\xEF\xBB\xBF{"));
+    }
+
+    #[test]
+    fn test_log_every() {
+        let path = "tests/output/log_every.log";
+        let logger = CustomLogger::new("log_every", path).log_every(3);
+        for i in 0..10 {
+            log_to(&logger, "still waiting");
+            if i == 4 {
+                log_to(&logger, "other");
+            }
+        }
+
+        let entries: Vec<_> = read_log(path, false).map(|e| e.unwrap()).collect();
+        let lines: Vec<_> = entries
+            .iter()
+            .map(|e| (e.message.as_str(), e.fields.get("skipped").cloned()))
+            .collect();
+        let skipped = Some(serde_json::json!(2));
+        assert_eq!(
+            lines,
+            vec![
+                ("still waiting", None),
+                ("still waiting", skipped.clone()),
+                ("other", None),
+                ("still waiting", skipped.clone()),
+                ("still waiting", skipped),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_stats() {
+        let path = "tests/output/stats.log";
+        let logger = CustomLogger::new("stats", path).console(false);
+        assert_eq!(logger.stats(), Stats::default());
+        for message in ["a", "bb", "ccc"] {
+            log_to(&logger, message);
+        }
+
+        let stats = logger.stats();
+        assert_eq!(stats.records_written, 3);
+        assert_eq!(stats.bytes_written, std::fs::metadata(path).unwrap().len());
+    }
+
+    #[test]
+    fn test_monotonic() {
+        let logger = CustomLogger::console_only("monotonic").monotonic(true);
+        let nanos: Vec<u64> = (0..100)
+            .map(|_| {
+                // a wall clock stuck at one instant
+                let entry = logger.entry(
+                    &log::Record::builder()
+                        .target("monotonic")
+                        .args(format_args!("tick"))
+                        .build(),
+                    "2024-01-01T00:00:00.000Z",
+                );
+                entry.fields["nanos_since_start"].as_u64().unwrap()
+            })
+            .collect();
+        assert!(nanos.windows(2).all(|w| w[0] < w[1]));
+    }
+
+    #[test]
+    fn test_audit() {
+        let audit = "tests/output/audit.log";
+        let mut logger = Logger::new();
+        logger.add_logger(Box::new(CustomLogger::new(
+            "api",
+            "tests/output/audit_api.log",
+        )));
+        logger.add_logger(Box::new(CustomLogger::new(
+            "db",
+            "tests/output/audit_db.log",
+        )));
+        logger.set_audit(Box::new(
+            CustomLogger::new("audit", audit)
+                .any_target(true)
+                .hash_chain(true)
+                .console(false),
+        ));
+        let records = [
+            ("api", "request"),
+            ("db", "query"),
+            ("other", "unmatched"),
+            ("api", "response"),
+        ];
+        for (target, message) in records {
+            logger.log_record(log::Level::Info, target, message, &[]);
+        }
+
+        let entries: Vec<_> = read_log(audit, false).map(|e| e.unwrap()).collect();
+        let audited: Vec<_> = entries
+            .iter()
+            .map(|e| (e.target.as_str(), e.message.as_str()))
+            .collect();
+        assert_eq!(audited, records);
+        verify_chain(audit).unwrap();
+    }
+
+    #[test]
+    fn test_prometheus_metrics() {
+        let path = "tests/output/prometheus.log";
+        let mut logger = Logger::new();
+        logger.add_logger(Box::new(CustomLogger::new("api", path)));
+        for level in [log::Level::Info, log::Level::Info, log::Level::Error] {
+            logger.log_record(level, "api", "request", &[]);
+        }
+        logger.log_record(log::Level::Warn, "db\"main", "slow", &[]);
+
+        let metrics = logger.prometheus_metrics();
+        for line in [
+            "# TYPE loggers_records_total counter",
+            "loggers_records_total{target=\"api\",level=\"ERROR\"} 1",
+            "loggers_records_total{target=\"api\",level=\"INFO\"} 2",
+            "loggers_records_total{target=\"db\\\"main\",level=\"WARN\"} 1",
+            "loggers_records_written_total{file=\"tests/output/prometheus.log\"} 3",
+        ] {
+            assert!(
+                metrics.lines().any(|l| l == line),
+                "{} missing in\n{}",
+                line,
+                metrics
+            );
+        }
+        let bytes = std::fs::metadata(path).unwrap().len();
+        let line = format!("loggers_bytes_written_total{{file=\"{}\"}} {}", path, bytes);
+        assert!(metrics.lines().any(|l| l == line));
+    }
+
+    #[test]
+    fn test_sample() {
+        let path = "tests/output/sample.log";
+        let logger = CustomLogger::new("sample", path).sample(0.1).console(false);
+        for i in 0..100 {
+            log_to(&logger, &i.to_string());
+        }
+
+        let entries: Vec<_> = read_log(path, false).map(|e| e.unwrap()).collect();
+        assert_eq!(entries.len(), 10);
+        assert_eq!(entries[0].message, "9");
+        assert!(entries.iter().all(|e| e.fields["sample_rate"] == 0.1));
+    }
+
+    #[test]
+    fn test_rename_field() {
+        let path = "tests/output/rename_field.log";
+        let logger = CustomLogger::new("rename_field", path)
+            .rename_field("message", "msg")
+            .rename_field("severity", "log.level");
+        log_to(&logger, "say \"hi\"");
+
+        let contents = std::fs::read_to_string(path).unwrap();
+        let v: serde_json::Value = serde_json::from_str(contents.trim_end()).unwrap();
+        assert_eq!(v["msg"], "say \"hi\"");
+        assert!(v.get("message").is_none());
+        assert_eq!(v["log"]["level"], "INFO");
+        assert!(v.get("severity").is_none());
+        assert_eq!(v["target"], "rename_field");
+    }
+
+    #[test]
+    fn test_logfmt_console() {
+        let path = "tests/output/logfmt_console.log";
+        let logger = CustomLogger::new("logfmt_console", path)
+            .format(Format::Json)
+            .console_format(ConsoleFormat::Logfmt)
+            .console(false);
+        let record = log::Record::builder()
+            .target("logfmt_console")
+            .level(log::Level::Warn)
+            .args(format_args!("disk \"sda\" full"))
+            .key_values(&[("used", 97)])
+            .build();
+        log::Log::log(&logger, &record);
+
+        let contents = std::fs::read_to_string(path).unwrap();
+        let v: serde_json::Value = serde_json::from_str(contents.trim_end()).unwrap();
+        assert_eq!(v["message"], "disk \"sda\" full");
+        assert_eq!(v["used"], 97);
+
+        let entry = logger.entry(&record, "2024-01-01T00:00:00.000Z");
+        assert_eq!(
+            logger.console_line(&entry),
+            "level=warn ts=2024-01-01T00:00:00.000Z target=logfmt_console \
+             msg=\"disk \\\"sda\\\" full\" schema_version=1 used=97"
+        );
+    }
+
+    #[test]
+    fn test_self_test() {
+        let bad = "tests/output/self_test/removed/self_test.log";
+        let mut logger = Logger::new();
+        logger.add_logger(Box::new(
+            CustomLogger::new("self_test", "tests/output/self_test.log").console(false),
+        ));
+        logger.add_logger(Box::new(CustomLogger::new("self_test", bad).console(false)));
+        // the directory disappears after the logger opened its file
+        std::fs::remove_dir_all("tests/output/self_test/removed").unwrap();
+
+        let failed = logger.self_test().unwrap_err();
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0].0, bad);
+
+        let mut logger = Logger::new();
+        logger.add_logger(Box::new(CustomLogger::new(
+            "self_test",
+            "tests/output/self_test.log",
+        )));
+        assert!(logger.self_test().is_ok());
+    }
+
+    #[test]
+    fn test_self_test_reach() {
+        /// a logger of another crate, which must not be flushed by a probe
+        struct Foreign;
+
+        impl log::Log for Foreign {
+            fn enabled(&self, _: &log::Metadata) -> bool {
+                true
+            }
+
+            fn log(&self, _: &log::Record) {}
+
+            fn flush(&self) {
+                panic!("flushed by self_test");
+            }
+        }
+
+        let (removed, queued) = (
+            "tests/output/self_test_removed.log",
+            "tests/output/self_test_async.log",
+        );
+        let mut logger = Logger::new();
+        logger.add_logger(Box::new(
+            CustomLogger::new("self_test", removed).console(false),
+        ));
+        logger.add_logger(Box::new(AsyncLogger::new(
+            "self_test",
+            Box::new(FileSink::new(queued).unwrap()),
+        )));
+        logger.add_logger(Box::new(TeeLogger::to_file(
+            Box::new(Foreign),
+            "tests/output/self_test_tee.log",
+        )));
+        std::fs::remove_file(removed).unwrap();
+        std::fs::remove_file(queued).unwrap();
+
+        let failed = logger.self_test().unwrap_err();
+        let failed: Vec<(&str, std::io::ErrorKind)> = failed
+            .iter()
+            .map(|(name, e)| (name.as_str(), e.kind()))
+            .collect();
+        assert_eq!(
+            failed,
+            vec![
+                (removed, std::io::ErrorKind::NotFound),
+                ("AsyncLogger self_test", std::io::ErrorKind::NotFound),
+                ("TeeLogger inner", std::io::ErrorKind::Unsupported),
+            ]
+        );
+        // probing does not create the files again
+        assert!(!Path::new(removed).exists());
+        assert!(!Path::new(queued).exists());
+    }
+
+    #[test]
+    fn test_shared_timestamp() {
+        let first = "tests/output/shared_timestamp_1.log";
+        let second = "tests/output/shared_timestamp_2.log";
+        let mut logger = Logger::new();
+        logger.add_logger(Box::new(
+            CustomLogger::new("shared_timestamp", first).console(false),
+        ));
+        logger.add_logger(Box::new(
+            CustomLogger::new("shared_timestamp", second).console(false),
+        ));
+        for i in 0..200 {
+            logger.log_record(log::Level::Info, "shared_timestamp", &i.to_string(), &[]);
+        }
+
+        let timestamps = |path| -> Vec<String> {
+            read_log(path, false)
+                .map(|e| e.unwrap().timestamp)
+                .collect()
+        };
+        assert_eq!(timestamps(first).len(), 200);
+        assert_eq!(timestamps(first), timestamps(second));
+    }
+
+    #[test]
+    fn test_schema_version() {
+        let path = "tests/output/schema_version.log";
+        let logger = CustomLogger::new("schema_version", path).console(false);
+        log_to(&logger, "current");
+        let logger = CustomLogger::append("schema_version", path)
+            .unwrap()
+            .schema_version(7)
+            .console(false);
+        log_to(&logger, "overridden");
+
+        let entries: Vec<_> = read_log(path, false).map(|e| e.unwrap()).collect();
+        assert_eq!(entries[0].fields["schema_version"], SCHEMA_VERSION);
+        assert_eq!(entries[1].fields["schema_version"], 7);
+    }
+
+    #[test]
+    fn test_reuse_buffers() {
+        let path = "tests/output/reuse_buffers.log";
+        let logger = CustomLogger::new("reuse_buffers", path)
+            .reuse_buffers(true)
+            .console(false);
+        // long records grow the buffer, short ones must not keep its tail
+        for i in 0..500 {
+            let message = match i % 3 {
+                0 => "x".repeat(i * 10),
+                _ => i.to_string(),
+            };
+            log_to(&logger, &message);
+        }
+
+        let messages = read_messages(path);
+        assert_eq!(messages.len(), 500);
+        for (i, message) in messages.iter().enumerate() {
+            match i % 3 {
+                0 => assert_eq!(*message, "x".repeat(i * 10)),
+                _ => assert_eq!(*message, i.to_string()),
+            }
+        }
+    }
+
+    #[test]
+    fn test_flush_target() {
+        let audit = "tests/output/flush_target_audit.log";
+        let api = "tests/output/flush_target_api.log";
+        let window = std::time::Duration::from_secs(3600);
+        let mut logger = Logger::new();
+        logger.add_logger(Box::new(
+            CustomLogger::new("flush_audit", audit)
+                .aggregate_window(window)
+                .console(false),
+        ));
+        logger.add_logger(Box::new(
+            CustomLogger::new("flush_api", api)
+                .aggregate_window(window)
+                .console(false),
+        ));
+        logger.log_record(log::Level::Warn, "flush_audit", "user deleted", &[]);
+        logger.log_record(log::Level::Info, "flush_api", "request", &[]);
+        assert!(read_messages(audit).is_empty());
+
+        logger.flush_target("flush_audit");
+        assert_eq!(read_messages(audit), vec!["user deleted"]);
+        assert!(read_messages(api).is_empty());
+    }
+
+    #[test]
+    fn test_produced_at() {
+        let path = "tests/output/produced_at.log";
+        let logger = CustomLogger::new("produced_at", path)
+            .produced_at(true)
+            .console(false);
+        let before = chrono::Utc::now().timestamp_nanos_opt().unwrap() as u64;
+        for i in 0..10 {
+            log_to(&logger, &i.to_string());
+        }
+        let after = chrono::Utc::now().timestamp_nanos_opt().unwrap() as u64;
+
+        let produced: Vec<u64> = read_log(path, false)
+            .map(|e| e.unwrap().fields["produced_at"].as_u64().unwrap())
+            .collect();
+        assert_eq!(produced.len(), 10);
+        assert!(produced.iter().all(|&t| (before..=after).contains(&t)));
+        assert!(produced.windows(2).all(|w| w[0] <= w[1]));
+        // finer than the millisecond timestamp
+        assert!(produced.iter().any(|t| t % 1_000_000 != 0));
+    }
+
+    #[test]
+    fn test_open_repair() {
+        let path = "tests/output/open_repair.log";
+        log_to(
+            &CustomLogger::new("open_repair", path).console(false),
+            "before",
+        );
+        let mut file = std::fs::OpenOptions::new().append(true).open(path).unwrap();
+        file.write_all(b"{\"sev").unwrap();
+
+        let logger = OpenOptions::new()
+            .append(true)
+            .repair(true)
+            .open("open_repair", path)
+            .unwrap()
+            .console(false);
+        log_to(&logger, "after");
+        assert_eq!(read_messages(path), vec!["before", "after"]);
+    }
+
+    #[test]
+    fn test_allowed_fields() {
+        let path = "tests/output/allowed_fields.log";
+        let logger = CustomLogger::new("allowed_fields", path)
+            .allowed_fields(&["user", "status"])
+            .console(false);
+        let kvs: [(&str, &dyn log::kv::ToValue); 4] = [
+            ("user", &"ferris"),
+            ("password", &"hunter2"),
+            ("status", &200),
+            ("body", &"a large payload"),
+        ];
+        log::Log::log(
+            &logger,
+            &log::Record::builder()
+                .target("allowed_fields")
+                .level(log::Level::Info)
+                .key_values(&kvs)
+                .args(format_args!("login"))
+                .build(),
+        );
+
+        let entry = read_log(path, false).next().unwrap().unwrap();
+        let keys: Vec<&str> = entry.fields.keys().map(String::as_str).collect();
+        assert_eq!(keys, ["schema_version", "user", "status", "dropped_fields"]);
+        assert_eq!(entry.fields["user"], "ferris");
+        assert_eq!(entry.fields["status"], 200);
+        assert_eq!(entry.fields["dropped_fields"], 2);
+    }
+
+    #[test]
+    fn test_allowed_fields_keep_logger_fields() {
+        let path = "tests/output/allowed_fields_run_id.log";
+        let mut logger = Logger::with_run_id("allowed");
+        logger.set_fallback(Box::new(
+            CustomLogger::new("default", path)
+                .any_target(true)
+                .allowed_fields(&["user"])
+                .console(false),
+        ));
+        logger.log_record(
+            log::Level::Info,
+            "unknown",
+            "unrouted",
+            &[
+                ("user", serde_json::json!("ferris")),
+                ("body", serde_json::json!("a large payload")),
+            ],
+        );
+
+        let entry = read_log(path, false).next().unwrap().unwrap();
+        assert_eq!(entry.fields["user"], "ferris");
+        assert_eq!(entry.fields["run_id"], "allowed");
+        assert_eq!(entry.fields["unmatched"], true);
+        assert_eq!(entry.fields["dropped_fields"], 1);
+    }
+
+    #[test]
+    fn test_module_fields() {
+        let path = "tests/output/module_fields.log";
+        let logger = CustomLogger::new("module_fields", path)
+            .module_fields(true)
+            .console(false);
+        for module in ["app::db::queries", "app"] {
+            log::Log::log(
+                &logger,
+                &log::Record::builder()
+                    .target("module_fields")
+                    .level(log::Level::Info)
+                    .module_path(Some(module))
+                    .args(format_args!("query"))
+                    .build(),
+            );
+        }
+
+        let modules: Vec<serde_json::Value> = read_log(path, false)
+            .map(|e| e.unwrap().fields["module"].clone())
+            .collect();
+        assert_eq!(
+            modules,
+            vec![
+                serde_json::json!({ "crate": "app", "path": ["db", "queries"] }),
+                serde_json::json!({ "crate": "app", "path": [] }),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_max_field_len() {
+        let path = "tests/output/max_field_len.log";
+        let logger = CustomLogger::new("max_field_len", path)
+            .max_field_len(8)
+            .console(false);
+        let trace = "at main.rs:1\n".repeat(100);
+        let kvs: [(&str, &dyn log::kv::ToValue); 3] = [
+            ("trace", &trace),
+            ("user", &"ferris"),
+            ("car", &"Škoda Škoda"),
+        ];
+        log::Log::log(
+            &logger,
+            &log::Record::builder()
+                .target("max_field_len")
+                .level(log::Level::Error)
+                .key_values(&kvs)
+                .args(format_args!("a message longer than eight bytes"))
+                .build(),
+        );
+
+        let entry = read_log(path, false).next().unwrap().unwrap();
+        assert_eq!(entry.fields["trace"], "at main.…");
+        assert_eq!(entry.fields["user"], "ferris");
+        // the eighth byte lies inside the second "Š", so the cut moves back before it
+        assert_eq!(entry.fields["car"], "Škoda …");
+        assert_eq!(entry.message, "a message longer than eight bytes");
+    }
+
+    #[test]
+    fn test_on_rotate() {
+        let path = "tests/output/on_rotate.log";
+        for segment in CustomLogger::new("on_rotate", path).segments() {
+            std::fs::remove_file(segment).unwrap();
+        }
+        let finalized = std::sync::Arc::new(Mutex::new(Vec::new()));
+        let seen = finalized.clone();
+        let logger = CustomLogger::new("on_rotate", path)
+            .max_lines(2)
+            .console(false)
+            .on_rotate(Box::new(move |segment| {
+                // the segment is complete by the time the callback runs
+                let messages = read_messages(segment.to_str().unwrap());
+                seen.lock().unwrap().push((segment.to_path_buf(), messages));
+            }));
+        for i in 0..5 {
+            log_to(&logger, &i.to_string());
+        }
+
+        let rotated = Path::new("tests/output/on_rotate.log.1").to_path_buf();
+        assert_eq!(
+            *finalized.lock().unwrap(),
+            vec![
+                (rotated.clone(), vec!["0".to_string(), "1".to_string()]),
+                (rotated, vec!["2".to_string(), "3".to_string()]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_escape_mode() {
+        let message = "\x1b[31mred\x1b[0m\0";
+        for (mode, expected) in [
+            (EscapeMode::JsonStandard, message.to_string()),
+            (EscapeMode::StripControl, "[31mred[0m".to_string()),
+            (EscapeMode::Visible, "\\x1b[31mred\\x1b[0m\\x00".to_string()),
+        ] {
+            let path = "tests/output/escape_mode.log";
+            let logger = CustomLogger::new("escape_mode", path)
+                .escape_mode(mode)
+                .console(false);
+            log::Log::log(
+                &logger,
+                &log::Record::builder()
+                    .target("escape_mode")
+                    .level(log::Level::Info)
+                    .key_values(&[("color", message)])
+                    .args(format_args!("{}", message))
+                    .build(),
+            );
+
+            let contents = std::fs::read_to_string(path).unwrap();
+            if mode == EscapeMode::JsonStandard {
+                assert!(contents.contains("\\u001b[31mred"));
+            }
+            let entry: LogEntry = serde_json::from_str(contents.trim_end()).unwrap();
+            assert_eq!(entry.message, expected, "{:?}", mode);
+            assert_eq!(entry.fields["color"], expected, "{:?}", mode);
+        }
+    }
 }
@@ -0,0 +1,266 @@
+/// log a record with structured fields
+///
+/// The fields are written as top-level keys of the JSON record, next to
+/// `severity`, `timestamp`, `target` and `message`. The level is any
+/// `log::Level` variant name. Without `target:` the module path is used,
+/// as with the `log` macros.
+///
+/// # Example
+/// ```
+/// # use loggers::*;
+/// let id = 42;
+/// jlog!(target: "auth", level: Info, { "user" => id, "action" => "login" }, "user {} logged in", id);
+/// jlog!(level: Warn, { "retry" => 3 }, "retrying");
+/// ```
+#[macro_export]
+macro_rules! jlog {
+    (target: $target:expr, level: $lvl:ident, { $($key:literal => $value:expr),+ $(,)? }, $($arg:tt)+) => {
+        $crate::__log::log!(target: $target, $crate::__log::Level::$lvl, $($key = $value),+; $($arg)+)
+    };
+    (target: $target:expr, level: $lvl:ident, { }, $($arg:tt)+) => {
+        $crate::__log::log!(target: $target, $crate::__log::Level::$lvl, $($arg)+)
+    };
+    (level: $lvl:ident, { $($fields:tt)* }, $($arg:tt)+) => {
+        $crate::jlog!(target: ::std::module_path!(), level: $lvl, { $($fields)* }, $($arg)+)
+    };
+}
+
+/// log any `Serialize` value as the structured fields of a record
+///
+/// The fields of a value that serializes to an object become top-level keys
+/// of the JSON record; any other value is written under a `value` key. The
+/// value is only serialized if the level is enabled.
+///
+/// # Example
+/// ```
+/// # use loggers::*;
+/// #[derive(serde::Serialize)]
+/// struct Order {
+///     id: u64,
+///     total: f64,
+/// }
+///
+/// let order = Order { id: 7, total: 12.5 };
+/// log_struct!(target: "shop", level: Info, &order, "order {} placed", order.id);
+/// log_struct!(level: Debug, &[1, 2, 3], "batch");
+/// ```
+#[macro_export]
+macro_rules! log_struct {
+    (target: $target:expr, level: $lvl:ident, $value:expr, $($arg:tt)+) => {
+        if $crate::__log::Level::$lvl <= $crate::__log::max_level() {
+            $crate::__log_struct(
+                $crate::__log::Level::$lvl,
+                $target,
+                $value,
+                &::std::format!($($arg)+),
+            )
+        }
+    };
+    (level: $lvl:ident, $value:expr, $($arg:tt)+) => {
+        $crate::log_struct!(target: ::std::module_path!(), level: $lvl, $value, $($arg)+)
+    };
+}
+
+/// the path of the function this macro is called in, e.g. `my_app::db::connect`
+///
+/// Rust has no built-in for the name of the current function, so this takes
+/// the type name of a nested item and strips it. The result is what
+/// `std::any::type_name` reports: its exact form is not guaranteed to be
+/// stable across compiler versions, closures and async blocks show up as
+/// `{{closure}}` segments (which are stripped, giving the enclosing
+/// function), and generic parameters are not included.
+///
+/// # Example
+/// ```
+/// # use loggers::*;
+/// fn connect() -> &'static str {
+///     function_name!()
+/// }
+/// assert!(connect().ends_with("connect"));
+/// ```
+#[macro_export]
+macro_rules! function_name {
+    () => {{
+        fn marker() {}
+        fn type_name_of<T>(_: T) -> &'static str {
+            ::std::any::type_name::<T>()
+        }
+        let mut name = type_name_of(marker);
+        name = name.strip_suffix("::marker").unwrap_or(name);
+        while let Some(outer) = name.strip_suffix("::{{closure}}") {
+            name = outer;
+        }
+        name
+    }};
+}
+
+/// log a record that carries the name of the calling function
+///
+/// Takes the same arguments as `log::log!` after the level, and attaches the
+/// [`function_name!`] of the call site. A CustomLogger writes it as a
+/// `function` field when [`CustomLogger::function`](crate::CustomLogger::function)
+/// is enabled and leaves it out otherwise.
+///
+/// # Example
+/// ```
+/// # use loggers::*;
+/// fn connect() {
+///     log_fn!(target: "db", level: Warn, "retrying in {}s", 5);
+/// }
+/// ```
+#[macro_export]
+macro_rules! log_fn {
+    (target: $target:expr, level: $lvl:ident, $($arg:tt)+) => {
+        $crate::__log::log!(
+            target: $target,
+            $crate::__log::Level::$lvl,
+            "loggers.function" = $crate::function_name!();
+            $($arg)+
+        )
+    };
+    (level: $lvl:ident, $($arg:tt)+) => {
+        $crate::log_fn!(target: ::std::module_path!(), level: $lvl, $($arg)+)
+    };
+}
+
+/// log an HTTP request with its response status and latency
+///
+/// The record carries `method` and `path` string fields, a numeric `status`
+/// and the latency in milliseconds as a numeric `latency_ms`, so every
+/// service logs requests in the same shape. The latency is a
+/// `std::time::Duration`. The level follows the status: Error for 5xx, Warn
+/// for 4xx and Info otherwise. Without `target:` the module path is used.
+///
+/// # Example
+/// ```
+/// # use loggers::*;
+/// let started = std::time::Instant::now();
+/// log_http!(target: "api", "GET", "/users/42", 200, started.elapsed());
+/// log_http!("POST", "/orders", 503, std::time::Duration::from_millis(1250));
+/// ```
+#[macro_export]
+macro_rules! log_http {
+    (target: $target:expr, $method:expr, $path:expr, $status:expr, $latency:expr $(,)?) => {{
+        let method: &str = $method;
+        let path: &str = $path;
+        let status: u16 = $status;
+        let latency: ::std::time::Duration = $latency;
+        let latency_ms = latency.as_secs_f64() * 1000.0;
+        let level = match status {
+            500..=599 => $crate::__log::Level::Error,
+            400..=499 => $crate::__log::Level::Warn,
+            _ => $crate::__log::Level::Info,
+        };
+        $crate::__log::log!(
+            target: $target,
+            level,
+            method = method,
+            path = path,
+            status = status,
+            latency_ms = latency_ms;
+            "{} {} {} {:.1}ms", method, path, status, latency_ms
+        )
+    }};
+    ($method:expr, $path:expr, $status:expr, $latency:expr $(,)?) => {
+        $crate::log_http!(target: ::std::module_path!(), $method, $path, $status, $latency)
+    };
+}
+
+/// log an error together with its `source()` chain
+///
+/// The record's message is the error's `Display` and the `error_chain` field
+/// holds the message of the error and of every source below it, outermost first.
+///
+/// # Example
+/// ```
+/// # use loggers::*;
+/// let err = std::fs::File::open("does/not/exist").unwrap_err();
+/// log_error!(target: "app", &err);
+/// ```
+#[macro_export]
+macro_rules! log_error {
+    (target: $target:expr, $err:expr) => {{
+        let err: &dyn ::std::error::Error = $err;
+        let chain = $crate::error_chain(err);
+        $crate::__log::error!(target: $target, error_chain:serde = chain; "{}", err)
+    }};
+    ($err:expr) => {
+        $crate::log_error!(target: ::std::module_path!(), $err)
+    };
+}
+
+/// log at Trace level only when the `verbose` feature is enabled
+///
+/// Takes the same arguments as `log::trace!`. Without the feature the macro
+/// expands to nothing, so neither the level check nor the arguments cost
+/// anything at runtime; variables used only in its arguments may then be
+/// reported as unused.
+///
+/// # Example
+/// ```
+/// # use loggers::*;
+/// trace_dev!(target: "parser", "token {:?}", "fn");
+/// ```
+#[cfg(feature = "verbose")]
+#[macro_export]
+macro_rules! trace_dev {
+    ($($arg:tt)+) => {
+        $crate::__log::trace!($($arg)+)
+    };
+}
+
+/// log at Trace level only when the `verbose` feature is enabled
+///
+/// Takes the same arguments as `log::trace!`. Without the feature the macro
+/// expands to nothing, so neither the level check nor the arguments cost
+/// anything at runtime; variables used only in its arguments may then be
+/// reported as unused.
+///
+/// # Example
+/// ```
+/// # use loggers::*;
+/// trace_dev!(target: "parser", "token {:?}", "fn");
+/// ```
+#[cfg(not(feature = "verbose"))]
+#[macro_export]
+macro_rules! trace_dev {
+    ($($arg:tt)+) => {
+        ()
+    };
+}
+
+/// log at Debug level only when the `verbose` feature is enabled
+///
+/// Takes the same arguments as `log::debug!` and, like [`trace_dev!`],
+/// expands to nothing without the feature.
+///
+/// # Example
+/// ```
+/// # use loggers::*;
+/// debug_dev!(target: "parser", "parsed {} items", 3);
+/// ```
+#[cfg(feature = "verbose")]
+#[macro_export]
+macro_rules! debug_dev {
+    ($($arg:tt)+) => {
+        $crate::__log::debug!($($arg)+)
+    };
+}
+
+/// log at Debug level only when the `verbose` feature is enabled
+///
+/// Takes the same arguments as `log::debug!` and, like [`trace_dev!`],
+/// expands to nothing without the feature.
+///
+/// # Example
+/// ```
+/// # use loggers::*;
+/// debug_dev!(target: "parser", "parsed {} items", 3);
+/// ```
+#[cfg(not(feature = "verbose"))]
+#[macro_export]
+macro_rules! debug_dev {
+    ($($arg:tt)+) => {
+        ()
+    };
+}
@@ -0,0 +1,99 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex, Weak,
+    },
+};
+
+/// the write counters of every live CustomLogger with a file
+static FILES: Mutex<Vec<Weak<FileCounters>>> = Mutex::new(Vec::new());
+
+/// What a CustomLogger has written to its file
+#[derive(Default)]
+pub(crate) struct FileCounters {
+    pub(crate) path: String,
+    pub(crate) bytes_written: AtomicU64,
+    pub(crate) records_written: AtomicU64,
+}
+
+impl FileCounters {
+    /// counters for `path`, included in [`render`] while they are alive
+    pub(crate) fn register(path: &str) -> Arc<FileCounters> {
+        let counters = Arc::new(FileCounters {
+            path: path.to_string(),
+            ..FileCounters::default()
+        });
+        let mut files = FILES.lock().unwrap();
+        files.retain(|f| f.strong_count() > 0);
+        files.push(Arc::downgrade(&counters));
+        counters
+    }
+
+    pub(crate) fn add(&self, bytes: u64) {
+        self.bytes_written.fetch_add(bytes, Ordering::Relaxed);
+        self.records_written.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// the Prometheus text exposition of `records`, counted by target and level,
+/// and of the bytes written to each file
+pub(crate) fn render(records: &BTreeMap<(String, log::Level), u64>) -> String {
+    let mut out = String::new();
+    out.push_str("# HELP loggers_records_total Records dispatched, by target and level.\n");
+    out.push_str("# TYPE loggers_records_total counter\n");
+    for ((target, level), count) in records {
+        let _ = writeln!(
+            out,
+            "loggers_records_total{{target=\"{}\",level=\"{}\"}} {}",
+            escape(target),
+            level,
+            count
+        );
+    }
+
+    let mut files: Vec<(String, u64, u64)> = FILES
+        .lock()
+        .unwrap()
+        .iter()
+        .filter_map(Weak::upgrade)
+        .map(|f| {
+            (
+                f.path.clone(),
+                f.bytes_written.load(Ordering::Relaxed),
+                f.records_written.load(Ordering::Relaxed),
+            )
+        })
+        .collect();
+    files.sort();
+    out.push_str("# HELP loggers_bytes_written_total Bytes written to each log file.\n");
+    out.push_str("# TYPE loggers_bytes_written_total counter\n");
+    for (path, bytes, _) in &files {
+        let _ = writeln!(
+            out,
+            "loggers_bytes_written_total{{file=\"{}\"}} {}",
+            escape(path),
+            bytes
+        );
+    }
+    out.push_str("# HELP loggers_records_written_total Records written to each log file.\n");
+    out.push_str("# TYPE loggers_records_written_total counter\n");
+    for (path, _, records) in &files {
+        let _ = writeln!(
+            out,
+            "loggers_records_written_total{{file=\"{}\"}} {}",
+            escape(path),
+            records
+        );
+    }
+    out
+}
+
+/// `value` escaped for use inside a quoted label value
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
@@ -0,0 +1,121 @@
+use crate::{Error, LogEntry};
+use serde::Serialize;
+use std::path::Path;
+
+/// append `record` to `out` as a MessagePack map prefixed with its length as
+/// a 4-byte big-endian integer, degrading like [`crate::entry::json_line`] if
+/// it fails to serialize
+pub(crate) fn frame<T: Serialize>(out: &mut Vec<u8>, record: &T, entry: &LogEntry) {
+    let body = rmp_serde::to_vec_named(record).unwrap_or_else(|_| {
+        let fallback = LogEntry {
+            location: None,
+            fields: crate::entry::serialization_failed(),
+            ..entry.clone()
+        };
+        rmp_serde::to_vec_named(&fallback).unwrap()
+    });
+    out.extend_from_slice(&(body.len() as u32).to_be_bytes());
+    out.extend_from_slice(&body);
+}
+
+/// read back the records of a file written with [`crate::Format::MessagePack`]
+///
+/// Yields an [`Error::Decode`] for a frame that is truncated or does not hold
+/// a record, and stops after it.
+/// # Example
+/// ```no_run
+/// # use crate::loggers::*;
+/// for record in read_msgpack("system.msgpack") {
+///     println!("{:?}", record.unwrap());
+/// }
+/// ```
+pub fn read_msgpack<P: AsRef<Path>>(path: P) -> impl Iterator<Item = Result<LogEntry, Error>> {
+    let (data, open_error) = match std::fs::read(path) {
+        Ok(data) => (data, None),
+        Err(e) => (Vec::new(), Some(Error::Io(e))),
+    };
+    let mut offset = 0;
+    let mut record = 0;
+    let frames = std::iter::from_fn(move || {
+        if offset >= data.len() {
+            return None;
+        }
+        record += 1;
+        let decoded = next_frame(&data[offset..]).map(|(entry, len)| {
+            offset += len;
+            entry
+        });
+        if decoded.is_err() {
+            offset = data.len();
+        }
+        Some(decoded.map_err(|source| Error::Decode { record, source }))
+    });
+    open_error.map(Err).into_iter().chain(frames)
+}
+
+/// the entry in the frame at the start of `data` and the frame's length
+fn next_frame(data: &[u8]) -> Result<(LogEntry, usize), Box<dyn std::error::Error + Send + Sync>> {
+    let len: [u8; 4] = data
+        .get(..4)
+        .ok_or("truncated frame length")?
+        .try_into()
+        .unwrap();
+    let end = 4 + u32::from_be_bytes(len) as usize;
+    let body = data.get(4..end).ok_or("truncated frame")?;
+    Ok((rmp_serde::from_slice(body)?, end))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CustomLogger, Format, Location};
+    use log::Log;
+
+    #[test]
+    fn test_msgpack() {
+        let path = "tests/output/msgpack.msgpack";
+        let logger = CustomLogger::new("msgpack", path)
+            .format(Format::MessagePack)
+            .location(true);
+        let kvs = [("user", "ferris")];
+        for line in 1..=3 {
+            logger.log(
+                &log::Record::builder()
+                    .target("msgpack")
+                    .level(log::Level::Warn)
+                    .key_values(&kvs)
+                    .module_path(Some("loggers::msgpack"))
+                    .line(Some(line))
+                    .args(format_args!("binary {}", line))
+                    .build(),
+            );
+        }
+
+        let entries: Vec<LogEntry> = read_msgpack(path).map(|e| e.unwrap()).collect();
+        assert_eq!(entries.len(), 3);
+        for (line, entry) in (1..=3).zip(entries) {
+            let mut fields = serde_json::Map::new();
+            fields.insert("schema_version".to_string(), crate::SCHEMA_VERSION.into());
+            fields.insert("user".to_string(), "ferris".into());
+            let expected = LogEntry {
+                severity: "WARN".to_string(),
+                timestamp: entry.timestamp.clone(),
+                target: "msgpack".to_string(),
+                message: format!("binary {}", line),
+                location: Some(Location {
+                    module: Some("loggers::msgpack".to_string()),
+                    file: None,
+                    line: Some(line),
+                }),
+                fields,
+            };
+            assert_eq!(entry, expected);
+        }
+
+        std::fs::write(path, [0, 0, 0, 9, 1]).unwrap();
+        assert!(matches!(
+            read_msgpack(path).next(),
+            Some(Err(Error::Decode { record: 1, .. }))
+        ));
+    }
+}
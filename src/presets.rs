@@ -0,0 +1,143 @@
+use crate::{ColorMode, ConsoleFormat, CustomLogger, Logger, OpenOptions};
+
+/// the size at which [`Logger::prod`] rotates its file
+pub const PROD_MAX_SIZE: u64 = 10 * 1024 * 1024;
+
+impl Logger {
+    /// install a logger for local development
+    ///
+    /// Records for `target` and its submodules are printed to the console at
+    /// every level in the [`ConsoleFormat::Pretty`] format, with their
+    /// location and colored levels when the environment allows it (see
+    /// [`ColorMode::Auto`]). Nothing is written to disk. Like
+    /// [`Logger::install`], this fails if a logger is already installed.
+    /// # Example
+    /// ```no_run
+    /// # use crate::loggers::*;
+    /// Logger::dev("myapp").expect("Failed to set logger");
+    /// ```
+    pub fn dev(target: &str) -> Result<(), log::SetLoggerError> {
+        let mut logger = Logger::new();
+        logger.add_logger(Box::new(dev_logger(target)));
+        logger.install()
+    }
+
+    /// install a logger for production
+    ///
+    /// Info and more severe records for `target` and its submodules are
+    /// appended to `filepath` as JSON lines, rotating every [`PROD_MAX_SIZE`]
+    /// bytes. Nothing is printed to the console. Fails if the file cannot be
+    /// opened, or with [`ErrorKind::AlreadyExists`](std::io::ErrorKind::AlreadyExists)
+    /// if a logger is already installed.
+    /// # Example
+    /// ```no_run
+    /// # use crate::loggers::*;
+    /// Logger::prod("myapp", "logs/myapp.log").expect("Failed to set logger");
+    /// ```
+    pub fn prod(target: &str, filepath: &str) -> std::io::Result<()> {
+        let mut logger = Logger::new();
+        logger.add_logger(Box::new(prod_logger(target, filepath)?));
+        logger
+            .install()
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::AlreadyExists, e))
+    }
+}
+
+pub(crate) fn dev_logger(target: &str) -> CustomLogger {
+    CustomLogger::console_only(target)
+        .match_module_default(true)
+        .color_mode(ColorMode::Auto)
+        .console_format(ConsoleFormat::Pretty)
+        .location(true)
+}
+
+pub(crate) fn prod_logger(target: &str, filepath: &str) -> std::io::Result<CustomLogger> {
+    Ok(OpenOptions::new()
+        .append(true)
+        .open(target, filepath)?
+        .match_module_default(true)
+        .min_level(log::Level::Info)
+        .console(false)
+        .max_size(PROD_MAX_SIZE))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{read_log, Location, LogEntry};
+    use log::Log;
+
+    fn record_for<'a>(
+        target: &'a str,
+        level: log::Level,
+        args: std::fmt::Arguments<'a>,
+    ) -> log::Record<'a> {
+        log::Record::builder()
+            .target(target)
+            .level(level)
+            .args(args)
+            .build()
+    }
+
+    #[test]
+    fn test_dev_preset() {
+        let logger = dev_logger("dev");
+        assert_eq!(logger.color_mode, ColorMode::Auto);
+        let logger = logger.color(true);
+        assert!(
+            logger.enabled(record_for("dev::db", log::Level::Trace, format_args!("")).metadata())
+        );
+
+        let mut entry = LogEntry::from_record(
+            &record_for("dev", log::Level::Error, format_args!("boom")),
+            "dev",
+            "2024-01-01T00:00:00.000Z",
+        );
+        entry.location = Some(Location {
+            module: Some("dev".to_string()),
+            file: Some("src/main.rs".to_string()),
+            line: Some(7),
+        });
+        entry.fields.insert("user".to_string(), "ferris".into());
+        entry.fields.insert("attempt".to_string(), 3.into());
+        assert_eq!(
+            logger.console_line(&entry),
+            "\x1b[31m[ERROR]\x1b[0m dev 2024-01-01T00:00:00.000Z - boom\n    \
+             at src/main.rs:7\n    user: ferris\n    attempt: 3"
+        );
+    }
+
+    #[test]
+    fn test_prod_preset() {
+        let dir = "tests/output/prod";
+        let _ = std::fs::remove_dir_all(dir);
+        let path = "tests/output/prod/prod.log";
+
+        let logger = prod_logger("prod", path).unwrap().max_size(200);
+        assert!(!logger.enabled(record_for("prod", log::Level::Debug, format_args!("")).metadata()));
+        for i in 0..6 {
+            logger.log(&record_for(
+                "prod",
+                log::Level::Info,
+                format_args!("record {}", i),
+            ));
+        }
+
+        let mut segments: Vec<String> = std::fs::read_dir(dir)
+            .unwrap()
+            .map(|e| e.unwrap().file_name().into_string().unwrap())
+            .collect();
+        segments.sort();
+        assert!(segments.len() > 1);
+        assert_eq!(segments[0], "prod.log");
+        assert_eq!(segments[1], "prod.log.1");
+
+        let mut messages = Vec::new();
+        for segment in segments[1..].iter().rev().chain(&segments[..1]) {
+            let segment = format!("{}/{}", dir, segment);
+            messages.extend(read_log(&segment, false).map(|e| e.unwrap().message));
+        }
+        let expected: Vec<String> = (0..6).map(|i| format!("record {}", i)).collect();
+        assert_eq!(messages, expected);
+    }
+}
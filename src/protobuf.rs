@@ -0,0 +1,288 @@
+//! The protobuf encoding of records, following `proto/loggers.proto`
+
+use crate::{Error, Location, LogEntry};
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::path::Path;
+
+type DecodeError = Box<dyn std::error::Error + Send + Sync>;
+
+const VARINT: u64 = 0;
+const LEN: u64 = 2;
+
+/// append `record` to `out` as a `LogEntry` message prefixed with its length
+/// as a 4-byte big-endian integer
+///
+/// Records whose standard fields were renamed no longer match the message,
+/// so they are encoded from `entry` instead.
+pub(crate) fn frame<T: Serialize>(out: &mut Vec<u8>, record: &T, entry: &LogEntry) {
+    let decoded = serde_json::to_value(record).and_then(serde_json::from_value::<LogEntry>);
+    let body = encode(decoded.as_ref().unwrap_or(entry));
+    out.extend_from_slice(&(body.len() as u32).to_be_bytes());
+    out.extend_from_slice(&body);
+}
+
+fn encode(entry: &LogEntry) -> Vec<u8> {
+    let mut out = Vec::new();
+    for (number, text) in [
+        (1, &entry.severity),
+        (2, &entry.timestamp),
+        (3, &entry.target),
+        (4, &entry.message),
+    ] {
+        if !text.is_empty() {
+            put_bytes(&mut out, number, text.as_bytes());
+        }
+    }
+    if let Some(location) = &entry.location {
+        let mut message = Vec::new();
+        if let Some(module) = &location.module {
+            put_bytes(&mut message, 1, module.as_bytes());
+        }
+        if let Some(file) = &location.file {
+            put_bytes(&mut message, 2, file.as_bytes());
+        }
+        if let Some(line) = location.line {
+            put_tag(&mut message, 3, VARINT);
+            put_varint(&mut message, line.into());
+        }
+        put_bytes(&mut out, 5, &message);
+    }
+    for (key, value) in &entry.fields {
+        let mut field = Vec::new();
+        put_bytes(&mut field, 1, key.as_bytes());
+        put_bytes(&mut field, 2, value.to_string().as_bytes());
+        put_bytes(&mut out, 6, &field);
+    }
+    out
+}
+
+fn put_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn put_tag(out: &mut Vec<u8>, number: u64, wire_type: u64) {
+    put_varint(out, number << 3 | wire_type);
+}
+
+fn put_bytes(out: &mut Vec<u8>, number: u64, bytes: &[u8]) {
+    put_tag(out, number, LEN);
+    put_varint(out, bytes.len() as u64);
+    out.extend_from_slice(bytes);
+}
+
+/// the varint or length-delimited value of a field
+enum WireValue<'a> {
+    Varint(u64),
+    Bytes(&'a [u8]),
+}
+
+/// the fields of the message `data`, skipping fixed-width ones
+fn fields(mut data: &[u8]) -> impl Iterator<Item = Result<(u64, WireValue<'_>), DecodeError>> {
+    std::iter::from_fn(move || {
+        if data.is_empty() {
+            return None;
+        }
+        let field = next_field(&mut data);
+        if field.is_err() {
+            data = &[];
+        }
+        Some(field)
+    })
+    .filter_map(Result::transpose)
+}
+
+fn next_field<'a>(data: &mut &'a [u8]) -> Result<Option<(u64, WireValue<'a>)>, DecodeError> {
+    let tag = varint(data)?;
+    let value = match tag & 7 {
+        0 => WireValue::Varint(varint(data)?),
+        2 => {
+            let len = varint(data)? as usize;
+            let bytes = data.get(..len).ok_or("truncated field")?;
+            *data = &data[len..];
+            WireValue::Bytes(bytes)
+        }
+        1 | 5 => {
+            let len = if tag & 7 == 1 { 8 } else { 4 };
+            *data = data.get(len..).ok_or("truncated field")?;
+            return Ok(None);
+        }
+        wire_type => return Err(format!("unsupported wire type {}", wire_type).into()),
+    };
+    Ok(Some((tag >> 3, value)))
+}
+
+fn varint(data: &mut &[u8]) -> Result<u64, DecodeError> {
+    let mut value = 0;
+    for shift in (0..64).step_by(7) {
+        let (&byte, rest) = data.split_first().ok_or("truncated varint")?;
+        *data = rest;
+        value |= u64::from(byte & 0x7f) << shift;
+        if byte < 0x80 {
+            return Ok(value);
+        }
+    }
+    Err("varint too long".into())
+}
+
+fn text(bytes: &[u8]) -> Result<String, DecodeError> {
+    Ok(String::from_utf8(bytes.to_vec())?)
+}
+
+/// decode a `LogEntry` message
+fn decode(data: &[u8]) -> Result<LogEntry, DecodeError> {
+    let mut entry = LogEntry {
+        severity: String::new(),
+        timestamp: String::new(),
+        target: String::new(),
+        message: String::new(),
+        location: None,
+        fields: Map::new(),
+    };
+    for field in fields(data) {
+        match field? {
+            (1, WireValue::Bytes(b)) => entry.severity = text(b)?,
+            (2, WireValue::Bytes(b)) => entry.timestamp = text(b)?,
+            (3, WireValue::Bytes(b)) => entry.target = text(b)?,
+            (4, WireValue::Bytes(b)) => entry.message = text(b)?,
+            (5, WireValue::Bytes(b)) => {
+                let mut location = Location {
+                    module: None,
+                    file: None,
+                    line: None,
+                };
+                for field in fields(b) {
+                    match field? {
+                        (1, WireValue::Bytes(b)) => location.module = Some(text(b)?),
+                        (2, WireValue::Bytes(b)) => location.file = Some(text(b)?),
+                        (3, WireValue::Varint(v)) => location.line = Some(v.try_into()?),
+                        _ => {}
+                    }
+                }
+                entry.location = Some(location);
+            }
+            (6, WireValue::Bytes(b)) => {
+                let (mut key, mut value) = (String::new(), Value::Null);
+                for field in fields(b) {
+                    match field? {
+                        (1, WireValue::Bytes(b)) => key = text(b)?,
+                        (2, WireValue::Bytes(b)) => value = serde_json::from_slice(b)?,
+                        _ => {}
+                    }
+                }
+                entry.fields.insert(key, value);
+            }
+            _ => {}
+        }
+    }
+    Ok(entry)
+}
+
+/// read back the records of a file written with [`crate::Format::Protobuf`]
+///
+/// Yields an [`Error::Decode`] for a frame that is truncated or does not hold
+/// a `LogEntry` message, and stops after it.
+/// # Example
+/// ```no_run
+/// # use crate::loggers::*;
+/// for record in read_protobuf("system.pb") {
+///     println!("{:?}", record.unwrap());
+/// }
+/// ```
+pub fn read_protobuf<P: AsRef<Path>>(path: P) -> impl Iterator<Item = Result<LogEntry, Error>> {
+    let (data, open_error) = match std::fs::read(path) {
+        Ok(data) => (data, None),
+        Err(e) => (Vec::new(), Some(Error::Io(e))),
+    };
+    let mut offset = 0;
+    let mut record = 0;
+    let frames = std::iter::from_fn(move || {
+        if offset >= data.len() {
+            return None;
+        }
+        record += 1;
+        let decoded = next_frame(&data[offset..]).map(|(entry, len)| {
+            offset += len;
+            entry
+        });
+        if decoded.is_err() {
+            offset = data.len();
+        }
+        Some(decoded.map_err(|source| Error::Decode { record, source }))
+    });
+    open_error.map(Err).into_iter().chain(frames)
+}
+
+/// the entry in the frame at the start of `data` and the frame's length
+fn next_frame(data: &[u8]) -> Result<(LogEntry, usize), DecodeError> {
+    let len: [u8; 4] = data
+        .get(..4)
+        .ok_or("truncated frame length")?
+        .try_into()
+        .unwrap();
+    let end = 4 + u32::from_be_bytes(len) as usize;
+    let body = data.get(4..end).ok_or("truncated frame")?;
+    Ok((decode(body)?, end))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CustomLogger, Format};
+    use log::Log;
+
+    #[test]
+    fn test_protobuf() {
+        let path = "tests/output/protobuf.pb";
+        let logger = CustomLogger::new("protobuf", path)
+            .format(Format::Protobuf)
+            .location(true)
+            .console(false);
+        let kvs: [(&str, &dyn log::kv::ToValue); 2] = [("user", &"ferris"), ("attempt", &3)];
+        for line in [1, 300] {
+            logger.log(
+                &log::Record::builder()
+                    .target("protobuf")
+                    .level(log::Level::Warn)
+                    .key_values(&kvs)
+                    .module_path(Some("loggers::protobuf"))
+                    .line(Some(line))
+                    .args(format_args!("binary {}", line))
+                    .build(),
+            );
+        }
+
+        let entries: Vec<LogEntry> = read_protobuf(path).map(|e| e.unwrap()).collect();
+        assert_eq!(entries.len(), 2);
+        for (line, entry) in [1, 300].into_iter().zip(entries) {
+            let mut fields = Map::new();
+            fields.insert("schema_version".to_string(), crate::SCHEMA_VERSION.into());
+            fields.insert("user".to_string(), "ferris".into());
+            fields.insert("attempt".to_string(), 3.into());
+            let expected = LogEntry {
+                severity: "WARN".to_string(),
+                timestamp: entry.timestamp.clone(),
+                target: "protobuf".to_string(),
+                message: format!("binary {}", line),
+                location: Some(Location {
+                    module: Some("loggers::protobuf".to_string()),
+                    file: None,
+                    line: Some(line),
+                }),
+                fields,
+            };
+            assert_eq!(entry, expected);
+            assert!(!entry.timestamp.is_empty());
+        }
+
+        std::fs::write(path, [0, 0, 0, 2, 0x0a, 0x05]).unwrap();
+        assert!(matches!(
+            read_protobuf(path).next(),
+            Some(Err(Error::Decode { record: 1, .. }))
+        ));
+    }
+}
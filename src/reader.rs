@@ -0,0 +1,228 @@
+use crate::{Error, LogEntry};
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader, Read, Seek, SeekFrom, Write},
+    path::Path,
+};
+
+/// A record as written to a log file by CustomLogger
+pub type LogRecord = LogEntry;
+
+/// the UTF-8 byte order mark written by [`crate::CustomLogger::emit_bom`]
+pub(crate) const BOM: &str = "\u{feff}";
+
+/// `line` without a leading byte order mark
+pub(crate) fn strip_bom(line: &str) -> &str {
+    line.strip_prefix(BOM).unwrap_or(line)
+}
+
+/// read back the records of an NDJSON log file
+///
+/// A UTF-8 byte order mark at the start of the file is ignored, and a
+/// gzip-compressed file, such as one written with
+/// [`crate::CustomLogger::compress_active`], is decompressed on the fly.
+/// # Arguments
+/// * `path` - The log file to read
+/// * `skip_malformed` - Silently skip lines that are not valid records instead of yielding an error
+/// # Example
+/// ```
+/// # use crate::loggers::*;
+/// for record in read_log("system.log", true) {
+///     println!("{:?}", record.unwrap());
+/// }
+/// ```
+pub fn read_log<P: AsRef<Path>>(
+    path: P,
+    skip_malformed: bool,
+) -> impl Iterator<Item = Result<LogRecord, Error>> {
+    let (lines, open_error) = match open(path.as_ref()) {
+        Ok(reader) => (Some(reader.lines()), None),
+        Err(e) => (None, Some(Error::Io(e))),
+    };
+
+    open_error
+        .map(Err)
+        .into_iter()
+        .chain(
+            lines
+                .into_iter()
+                .flatten()
+                .enumerate()
+                .filter_map(move |(i, line)| {
+                    let line = match line {
+                        Ok(line) => line,
+                        Err(e) => return Some(Err(Error::Io(e))),
+                    };
+                    if line.trim().is_empty() {
+                        return None;
+                    }
+                    match serde_json::from_str::<LogRecord>(strip_bom(&line)) {
+                        Ok(record) => Some(Ok(record)),
+                        Err(_) if skip_malformed => None,
+                        Err(source) => Some(Err(Error::Parse {
+                            line: i + 1,
+                            source,
+                        })),
+                    }
+                }),
+        )
+}
+
+/// read back the records of a log file and all of its rotated segments,
+/// oldest segment first
+///
+/// The segments are those listed by [`crate::CustomLogger::segments`];
+/// compressed ones are decompressed on the fly. Line numbers in errors are
+/// relative to the segment they occur in.
+/// # Example
+/// ```
+/// # use crate::loggers::*;
+/// for record in read_segments("system.log", true) {
+///     println!("{:?}", record.unwrap());
+/// }
+/// ```
+pub fn read_segments<P: AsRef<Path>>(
+    path: P,
+    skip_malformed: bool,
+) -> impl Iterator<Item = Result<LogRecord, Error>> {
+    crate::rotation::segments(path.as_ref())
+        .into_iter()
+        .rev()
+        .flat_map(move |segment| read_log(segment, skip_malformed))
+}
+
+/// repair an NDJSON log whose last line was cut short, e.g. because the
+/// process was killed in the middle of a write
+///
+/// If the file does not end with a newline, its last line is checked: a
+/// complete JSON value only gets the missing newline, anything else is
+/// truncated away. Returns the number of bytes removed. Files that are
+/// gzip-compressed, MessagePack- or protobuf-encoded are not supported.
+/// # Example
+/// ```no_run
+/// # use crate::loggers::*;
+/// let removed = repair_ndjson("system.log").unwrap();
+/// if removed > 0 {
+///     eprintln!("dropped a partial record of {} bytes", removed);
+/// }
+/// ```
+pub fn repair_ndjson<P: AsRef<Path>>(path: P) -> io::Result<u64> {
+    const CHUNK: u64 = 8192;
+
+    let mut file = std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(path)?;
+    let len = file.metadata()?.len();
+    let mut buf = vec![0; CHUNK as usize];
+    // find the start of the last line, scanning back from the end
+    let mut pos = len;
+    let mut start = 0;
+    while pos > 0 {
+        let chunk = CHUNK.min(pos);
+        pos -= chunk;
+        let buf = &mut buf[..chunk as usize];
+        file.seek(SeekFrom::Start(pos))?;
+        file.read_exact(buf)?;
+        if pos + chunk == len && buf.last() == Some(&b'\n') {
+            return Ok(0);
+        }
+        if let Some(i) = buf.iter().rposition(|&b| b == b'\n') {
+            start = pos + i as u64 + 1;
+            break;
+        }
+    }
+    if start == len {
+        return Ok(0);
+    }
+
+    let mut last = Vec::new();
+    file.seek(SeekFrom::Start(start))?;
+    file.read_to_end(&mut last)?;
+    let last = last.strip_prefix(BOM.as_bytes()).unwrap_or(&last);
+    if serde_json::from_slice::<serde_json::Value>(last).is_ok() {
+        file.seek(SeekFrom::End(0))?;
+        file.write_all(b"\n")?;
+        return Ok(0);
+    }
+    file.set_len(start)?;
+    Ok(len - start)
+}
+
+/// a buffered reader over the contents of `path`, decompressing gzip
+fn open(path: &Path) -> io::Result<Box<dyn BufRead>> {
+    let mut reader = BufReader::new(File::open(path)?);
+    if reader.fill_buf()?.starts_with(&[0x1f, 0x8b]) {
+        let decoder = flate2::bufread::MultiGzDecoder::new(reader);
+        return Ok(Box::new(BufReader::new(decoder)));
+    }
+    Ok(Box::new(reader))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CustomLogger;
+    use log::Log;
+    use std::io::Write;
+
+    #[test]
+    fn test_read_log() {
+        let path = "tests/output/reader.log";
+        let logger = CustomLogger::new("reader", path);
+        for (level, message) in [(log::Level::Info, "first"), (log::Level::Warn, "second")] {
+            logger.log(
+                &log::Record::builder()
+                    .target("reader")
+                    .level(level)
+                    .args(format_args!("{}", message))
+                    .build(),
+            );
+        }
+
+        let records: Vec<LogRecord> = read_log(path, false).map(|r| r.unwrap()).collect();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].severity, "INFO");
+        assert_eq!(records[0].target, "reader");
+        assert_eq!(records[0].message, "first");
+        assert_eq!(records[1].severity, "WARN");
+        assert_eq!(records[1].message, "second");
+
+        let mut file = std::fs::OpenOptions::new().append(true).open(path).unwrap();
+        file.write_all(b"not json\n").unwrap();
+
+        assert_eq!(read_log(path, true).filter(|r| r.is_ok()).count(), 2);
+        let last = read_log(path, false).last().unwrap();
+        assert!(matches!(last, Err(Error::Parse { line: 3, .. })));
+    }
+
+    #[test]
+    fn test_repair_ndjson() {
+        let path = "tests/output/repair_ndjson.log";
+        let logger = CustomLogger::new("repair", path).console(false);
+        for message in ["first", "second"] {
+            logger.log(
+                &log::Record::builder()
+                    .target("repair")
+                    .level(log::Level::Info)
+                    .args(format_args!("{}", message))
+                    .build(),
+            );
+        }
+        let intact = std::fs::read(path).unwrap();
+
+        let mut file = std::fs::OpenOptions::new().append(true).open(path).unwrap();
+        let partial = b"{\"severity\":\"INFO\",\"mess";
+        file.write_all(partial).unwrap();
+        assert!(read_log(path, false).any(|r| r.is_err()));
+        assert_eq!(repair_ndjson(path).unwrap(), partial.len() as u64);
+        assert_eq!(std::fs::read(path).unwrap(), intact);
+        assert_eq!(read_log(path, false).filter(|r| r.is_ok()).count(), 2);
+
+        // a complete record missing only its newline is kept
+        std::fs::write(path, &intact[..intact.len() - 1]).unwrap();
+        assert_eq!(repair_ndjson(path).unwrap(), 0);
+        assert_eq!(std::fs::read(path).unwrap(), intact);
+        assert_eq!(repair_ndjson(path).unwrap(), 0);
+    }
+}
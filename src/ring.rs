@@ -0,0 +1,177 @@
+use crate::LogEntry;
+use std::{
+    collections::VecDeque,
+    io::{self, Write},
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+/// A logger that keeps the most recent records in memory
+///
+/// Useful for crash diagnostics: install it next to the file loggers and
+/// call [`RingBufferLogger::dump`] when something goes wrong. Records are
+/// kept as rendered JSON lines; once `capacity` is reached the oldest one
+/// is discarded for each new record.
+pub struct RingBufferLogger {
+    target: String,
+    capacity: usize,
+    records: Arc<Mutex<VecDeque<String>>>,
+}
+
+impl RingBufferLogger {
+    /// create a RingBufferLogger
+    /// # Arguments
+    /// * `target` - The target to match
+    /// * `capacity` - The number of records to keep
+    pub fn new(target: &str, capacity: usize) -> RingBufferLogger {
+        RingBufferLogger {
+            target: target.to_string(),
+            capacity,
+            records: Arc::new(Mutex::new(VecDeque::with_capacity(capacity))),
+        }
+    }
+
+    /// the buffered records, oldest first
+    pub fn dump(&self) -> Vec<String> {
+        self.records.lock().unwrap().iter().cloned().collect()
+    }
+
+    /// write the buffered records to `path` as NDJSON, oldest first,
+    /// replacing the file and creating its parent directories if needed
+    ///
+    /// The file can be read back with [`crate::read_log`].
+    /// # Example
+    /// ```
+    /// # use crate::loggers::*;
+    /// let logger = RingBufferLogger::new("app", 1000);
+    /// logger.dump_to_file("tests/output/crash.log").unwrap();
+    /// ```
+    pub fn dump_to_file<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        write_dump(&self.dump(), path.as_ref())
+    }
+
+    /// also dump the buffered records to `path` when the process panics
+    ///
+    /// Installs a panic hook that writes the file like
+    /// [`dump_to_file`](Self::dump_to_file) and then runs the hook that was
+    /// installed before, so the panic message is still printed. The hook
+    /// stays installed for the rest of the process.
+    /// # Example
+    /// ```
+    /// # use crate::loggers::*;
+    /// let mut logger = Logger::new();
+    /// logger.add_logger(Box::new(
+    ///     RingBufferLogger::new("app", 1000).dump_on_panic("tests/output/crash.log"),
+    /// ));
+    /// ```
+    pub fn dump_on_panic<P: AsRef<Path>>(self, path: P) -> RingBufferLogger {
+        let records = Arc::clone(&self.records);
+        let path = path.as_ref().to_path_buf();
+        let previous = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            // the panicking thread may hold the lock; skip the dump rather than deadlock
+            let lines: Option<Vec<String>> = match records.try_lock() {
+                Ok(records) => Some(records.iter().cloned().collect()),
+                Err(std::sync::TryLockError::Poisoned(e)) => {
+                    Some(e.into_inner().iter().cloned().collect())
+                }
+                Err(std::sync::TryLockError::WouldBlock) => None,
+            };
+            if let Some(lines) = lines {
+                if let Err(e) = write_dump(&lines, &path) {
+                    eprintln!("RingBufferLogger: cannot dump to {:?}: {}", path, e);
+                }
+            }
+            previous(info);
+        }));
+        self
+    }
+}
+
+fn write_dump(lines: &[String], path: &Path) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut out = io::BufWriter::new(std::fs::File::create(path)?);
+    for line in lines {
+        out.write_all(line.as_bytes())?;
+        out.write_all(b"\n")?;
+    }
+    out.flush()
+}
+
+impl log::Log for RingBufferLogger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        if crate::inspect::is_hook(metadata) {
+            let name = format!("RingBufferLogger {}", self.target);
+            crate::inspect::report(&name, || Ok(()));
+            return false;
+        }
+        metadata.target() == self.target
+    }
+
+    fn log(&self, record: &log::Record) {
+        if !self.enabled(record.metadata()) || self.capacity == 0 {
+            return;
+        }
+
+        let timestamp = crate::clock::timestamp();
+        let line = serde_json::to_string(&LogEntry::from_record(record, &self.target, &timestamp))
+            .unwrap();
+        let mut records = self.records.lock().unwrap();
+        if records.len() == self.capacity {
+            records.pop_front();
+        }
+        records.push_back(line);
+    }
+
+    fn flush(&self) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use log::Log;
+
+    #[test]
+    fn test_ring_buffer() {
+        let logger = RingBufferLogger::new("ring", 3);
+        for i in 0..5 {
+            logger.log(
+                &log::Record::builder()
+                    .target("ring")
+                    .level(log::Level::Info)
+                    .args(format_args!("record {}", i))
+                    .build(),
+            );
+        }
+
+        let messages: Vec<String> = logger
+            .dump()
+            .iter()
+            .map(|line| serde_json::from_str::<LogEntry>(line).unwrap().message)
+            .collect();
+        assert_eq!(messages, vec!["record 2", "record 3", "record 4"]);
+    }
+
+    #[test]
+    fn test_dump_to_file() {
+        let path = "tests/output/ring_dump.log";
+        let logger = RingBufferLogger::new("ring_dump", 3);
+        for i in 0..5 {
+            logger.log(
+                &log::Record::builder()
+                    .target("ring_dump")
+                    .level(log::Level::Info)
+                    .args(format_args!("record {}", i))
+                    .build(),
+            );
+        }
+        logger.dump_to_file(path).unwrap();
+
+        let messages: Vec<String> = crate::read_log(path, false)
+            .map(|e| e.unwrap().message)
+            .collect();
+        assert_eq!(messages, vec!["record 2", "record 3", "record 4"]);
+    }
+}
@@ -0,0 +1,113 @@
+use crate::clock::Date;
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+/// the path of the `index`-th rotated segment of `path`, e.g. `system.log.1`
+pub(crate) fn rotated_path(path: &Path, index: usize) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".{}", index));
+    PathBuf::from(name)
+}
+
+/// whether `path` no longer names the file `file` was opened from, e.g.
+/// because an external tool renamed or deleted it
+pub(crate) fn replaced(path: &Path, file: &std::fs::File) -> bool {
+    let Ok(on_disk) = std::fs::metadata(path) else {
+        return true;
+    };
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        match file.metadata() {
+            Ok(open) => open.dev() != on_disk.dev() || open.ino() != on_disk.ino(),
+            Err(_) => true,
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = (file, on_disk);
+        false
+    }
+}
+
+/// the segments of the logical log `path`, newest first: `path` itself, the
+/// numbered `path.N` segments by ascending `N`, then the dated
+/// `path.YYYY-MM-DD.n` segments from the latest. Each may end in `.gz`.
+pub(crate) fn segments(path: &Path) -> Vec<PathBuf> {
+    #[derive(PartialEq, Eq, PartialOrd, Ord)]
+    enum Key {
+        Active,
+        Numbered(usize),
+        Dated(std::cmp::Reverse<(Date, usize)>),
+    }
+
+    let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+        return Vec::new();
+    };
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut segments: Vec<(Key, PathBuf)> = entries
+        .filter_map(|entry| {
+            let file_name = entry.ok()?.file_name();
+            let file_name = file_name.to_str()?;
+            let rest = file_name.strip_prefix(name)?;
+            let rest = rest.strip_suffix(".gz").unwrap_or(rest);
+            let key = match rest.strip_prefix('.') {
+                None if rest.is_empty() => Key::Active,
+                None => return None,
+                Some(suffix) => match suffix.split_once('.') {
+                    None => Key::Numbered(suffix.parse().ok()?),
+                    Some((date, index)) => {
+                        Key::Dated(std::cmp::Reverse((Date::parse(date)?, index.parse().ok()?)))
+                    }
+                },
+            };
+            Some((key, dir.join(file_name)))
+        })
+        .collect();
+    segments.sort_by(|a, b| a.0.cmp(&b.0));
+    segments.into_iter().map(|(_, path)| path).collect()
+}
+
+/// move `path` to `path.1`, shifting existing `path.N` segments to `path.N+1`;
+/// returns `path.1` if `path` existed
+pub(crate) fn shift_segments(path: &Path) -> io::Result<Option<PathBuf>> {
+    let mut last = 0;
+    while rotated_path(path, last + 1).exists() {
+        last += 1;
+    }
+    for index in (1..=last).rev() {
+        std::fs::rename(rotated_path(path, index), rotated_path(path, index + 1))?;
+    }
+    if !path.exists() {
+        return Ok(None);
+    }
+    let rotated = rotated_path(path, 1);
+    std::fs::rename(path, &rotated)?;
+    Ok(Some(rotated))
+}
+
+/// move `path` to `path.<date>.<n>`, using the first `n` from 1 that is free;
+/// returns the new path if `path` existed
+pub(crate) fn dated_segment(path: &Path, date: Date) -> io::Result<Option<PathBuf>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let mut dated = path.as_os_str().to_owned();
+    dated.push(format!(".{}", date));
+    let dated = PathBuf::from(dated);
+    let mut index = 1;
+    while rotated_path(&dated, index).exists() {
+        index += 1;
+    }
+    let rotated = rotated_path(&dated, index);
+    std::fs::rename(path, &rotated)?;
+    Ok(Some(rotated))
+}
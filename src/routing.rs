@@ -0,0 +1,493 @@
+use crate::{CustomLogger, LogEntry, Sink};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Mutex,
+};
+
+/// default upper bound on the number of files a RoutingLogger creates
+pub const DEFAULT_MAX_SINKS: usize = 64;
+
+/// default upper bound on the number of files a RoutingLogger keeps open at once
+pub const DEFAULT_MAX_OPEN_SINKS: usize = 16;
+
+type Route = Box<dyn Fn(&log::Record) -> Option<String> + Send + Sync>;
+
+/// A logger that picks the output file per record.
+///
+/// The `route` closure maps a record (including its key-value fields) to a
+/// filepath. A sink is created on first use of a path and reused by every
+/// later record routed to the same path. Records routed to `None` are dropped.
+///
+/// At most `max_open_sinks` files are open at a time; opening another one
+/// closes the least recently used, which is reopened in append mode the next
+/// time a record is routed to it.
+pub struct RoutingLogger {
+    target: String,
+    any_target: bool,
+    route: Route,
+    // `None` for templates, whose paths are not limited
+    max_sinks: Option<usize>,
+    max_open_sinks: usize,
+    sinks: Mutex<Sinks>,
+}
+
+#[derive(Default)]
+struct Sinks {
+    created: HashSet<String>,
+    open: HashMap<String, (CustomLogger, u64)>,
+    tick: u64,
+}
+
+impl RoutingLogger {
+    /// create a RoutingLogger
+    /// # Arguments
+    /// * `target` - The target to match
+    /// * `route` - Maps a record to the filepath it should be written to
+    /// # Example
+    /// ```
+    /// # use crate::loggers::*;
+    /// let logger = RoutingLogger::new("tenant", |record| {
+    ///     record
+    ///         .key_values()
+    ///         .get(log::kv::Key::from("tenant"))
+    ///         .map(|tenant| format!("logs/{}.log", tenant))
+    /// });
+    /// ```
+    pub fn new<F>(target: &str, route: F) -> RoutingLogger
+    where
+        F: Fn(&log::Record) -> Option<String> + Send + Sync + 'static,
+    {
+        RoutingLogger {
+            target: target.to_string(),
+            any_target: false,
+            route: Box::new(route),
+            max_sinks: Some(DEFAULT_MAX_SINKS),
+            max_open_sinks: DEFAULT_MAX_OPEN_SINKS,
+            sinks: Mutex::new(Sinks::default()),
+        }
+    }
+
+    /// create a RoutingLogger for records of every target, writing each to the
+    /// file named by `pattern` with its placeholders filled in
+    ///
+    /// The placeholders are `{target}`, the record's target with characters
+    /// other than letters, digits, `-`, `_` and `.` replaced by `_` (so
+    /// `db::pool` becomes `db__pool`), `{date}`, the local date as
+    /// `YYYY-MM-DD`, `{pid}`, the process id, and `{hostname}`.
+    ///
+    /// A `{date}` or `{target}` pattern produces new paths for as long as the
+    /// program runs, so the number of files is not limited by
+    /// [`max_sinks`](Self::max_sinks) unless it is set explicitly; only the
+    /// files kept open are.
+    /// # Example
+    /// ```
+    /// # use crate::loggers::*;
+    /// let logger = RoutingLogger::template("logs/{target}-{date}.log");
+    /// ```
+    pub fn template(pattern: &str) -> RoutingLogger {
+        let pattern = pattern.to_string();
+        let mut logger = RoutingLogger::new("", move |record| Some(expand(&pattern, record)));
+        logger.any_target = true;
+        logger.max_sinks = None;
+        logger
+    }
+
+    /// set the maximum number of distinct files this logger may create.
+    /// Records routed to a new file once the limit is reached are dropped.
+    pub fn max_sinks(mut self, max_sinks: usize) -> RoutingLogger {
+        self.max_sinks = Some(max_sinks);
+        self
+    }
+
+    /// set the maximum number of files kept open at once
+    pub fn max_open_sinks(mut self, max_open_sinks: usize) -> RoutingLogger {
+        self.max_open_sinks = max_open_sinks.max(1);
+        self
+    }
+
+    /// the number of files currently open
+    pub fn open_sinks(&self) -> usize {
+        self.sinks.lock().unwrap().open.len()
+    }
+}
+
+/// `pattern` with the placeholders of [`RoutingLogger::template`] filled in for `record`
+fn expand(pattern: &str, record: &log::Record) -> String {
+    let target: String = record
+        .target()
+        .chars()
+        .map(|c| match c {
+            c if c.is_alphanumeric() || c == '-' || c == '_' || c == '.' => c,
+            _ => '_',
+        })
+        .collect();
+    let mut path = pattern.replace("{target}", &target);
+    if path.contains("{date}") {
+        let date = crate::clock::Date::today().to_string();
+        path = path.replace("{date}", &date);
+    }
+    path.replace("{pid}", &std::process::id().to_string())
+        .replace("{hostname}", crate::gelf::hostname())
+}
+
+impl log::Log for RoutingLogger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        if crate::inspect::is_hook(metadata) {
+            // only the files open now can be probed
+            for (path, (sink, _)) in &self.sinks.lock().unwrap().open {
+                crate::inspect::forward(path, sink);
+            }
+            return false;
+        }
+        self.any_target || metadata.target() == self.target
+    }
+
+    fn log(&self, record: &log::Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let Some(filepath) = (self.route)(record) else {
+            return;
+        };
+
+        let mut sinks = self.sinks.lock().unwrap();
+        let sinks = &mut *sinks;
+        if let Some(max_sinks) = self.max_sinks {
+            if !sinks.created.contains(&filepath) && sinks.created.len() >= max_sinks {
+                eprintln!(
+                    "RoutingLogger {}: max sinks ({}) reached, dropping record for {}",
+                    self.target, max_sinks, filepath
+                );
+                return;
+            }
+        }
+        if !sinks.open.contains_key(&filepath) {
+            if sinks.open.len() >= self.max_open_sinks {
+                let lru = sinks
+                    .open
+                    .iter()
+                    .min_by_key(|(_, (_, used))| *used)
+                    .map(|(path, _)| path.clone());
+                if let Some(lru) = lru {
+                    sinks.open.remove(&lru);
+                }
+            }
+            match CustomLogger::append(&self.target, &filepath) {
+                Ok(sink) => {
+                    let sink = sink.any_target(self.any_target);
+                    if self.max_sinks.is_some() {
+                        sinks.created.insert(filepath.clone());
+                    }
+                    sinks.open.insert(filepath.clone(), (sink, 0));
+                }
+                Err(e) => {
+                    eprintln!("Cannot open file {:?}: {}", filepath, e);
+                    return;
+                }
+            }
+        }
+        sinks.tick += 1;
+        let (sink, used) = sinks.open.get_mut(&filepath).unwrap();
+        *used = sinks.tick;
+        sink.log(record);
+    }
+
+    fn flush(&self) {
+        for (sink, _) in self.sinks.lock().unwrap().open.values() {
+            sink.flush();
+        }
+    }
+}
+
+/// A logger that picks the sink per record from the value of one field
+///
+/// A declarative alternative to [`RoutingLogger`]: records whose `field`
+/// holds a value added with [`add_route`](FieldRouter::add_route) go to that
+/// value's sink, and all other records, including those without the field,
+/// go to the default sink if one is set.
+pub struct FieldRouter {
+    target: String,
+    field: String,
+    routes: HashMap<String, Box<dyn Sink>>,
+    default: Option<Box<dyn Sink>>,
+}
+
+impl FieldRouter {
+    /// create a FieldRouter
+    /// # Arguments
+    /// * `target` - The target to match
+    /// * `field` - The key-value field whose value selects the sink
+    pub fn new(target: &str, field: &str) -> FieldRouter {
+        FieldRouter {
+            target: target.to_string(),
+            field: field.to_string(),
+            routes: HashMap::new(),
+            default: None,
+        }
+    }
+
+    /// send records whose field equals `value` to `sink`
+    /// # Example
+    /// ```
+    /// # use crate::loggers::*;
+    /// let mut logger = FieldRouter::new("app", "stream");
+    /// logger.add_route(
+    ///     "audit",
+    ///     Box::new(FileSink::new("tests/output/audit.log").unwrap()),
+    /// );
+    /// logger.set_default(Box::new(FileSink::new("tests/output/system.log").unwrap()));
+    /// ```
+    pub fn add_route(&mut self, value: &str, sink: Box<dyn Sink>) {
+        self.routes.insert(value.to_string(), sink);
+    }
+
+    /// send records that match no route to `sink`
+    pub fn set_default(&mut self, sink: Box<dyn Sink>) {
+        self.default = Some(sink);
+    }
+}
+
+impl log::Log for FieldRouter {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        if crate::inspect::is_hook(metadata) {
+            for (value, sink) in &self.routes {
+                let name = format!("FieldRouter {} route {}", self.target, value);
+                crate::inspect::report_sink(&name, sink.as_ref());
+            }
+            if let Some(sink) = &self.default {
+                let name = format!("FieldRouter {} default", self.target);
+                crate::inspect::report_sink(&name, sink.as_ref());
+            }
+            return false;
+        }
+        metadata.target() == self.target
+    }
+
+    fn log(&self, record: &log::Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let value = record
+            .key_values()
+            .get(log::kv::Key::from(self.field.as_str()))
+            .map(|value| value.to_string());
+        let Some(sink) = value
+            .and_then(|value| self.routes.get(&value))
+            .or(self.default.as_ref())
+        else {
+            return;
+        };
+
+        let timestamp = crate::clock::timestamp();
+        let line = serde_json::to_string(&LogEntry::from_record(record, &self.target, &timestamp))
+            .unwrap();
+        if let Err(e) = sink.write_line(&line) {
+            eprintln!("FieldRouter {}: sink failed: {}", self.target, e);
+        }
+    }
+
+    fn flush(&self) {
+        for sink in self.routes.values().chain(&self.default) {
+            if let Err(e) = sink.flush() {
+                eprintln!("FieldRouter {}: sink failed to flush: {}", self.target, e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use log::Log;
+    use serde_json::Value;
+    use std::path::Path;
+
+    fn tenant_path(record: &log::Record) -> Option<String> {
+        record
+            .key_values()
+            .get(log::kv::Key::from("tenant"))
+            .map(|tenant| format!("tests/output/routing/{}.log", tenant))
+    }
+
+    fn log_tenant(logger: &RoutingLogger, tenant: &str, message: &str) {
+        let kvs = [("tenant", tenant)];
+        logger.log(
+            &log::Record::builder()
+                .target("tenant")
+                .level(log::Level::Info)
+                .key_values(&kvs)
+                .args(format_args!("{}", message))
+                .build(),
+        );
+    }
+
+    fn read_messages(path: &str) -> Vec<String> {
+        std::fs::read_to_string(path)
+            .unwrap()
+            .lines()
+            .map(|line| {
+                let v: Value = serde_json::from_str(line).unwrap();
+                v["message"].as_str().unwrap().to_string()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_routing() {
+        for tenant in ["acme", "globex", "initech"] {
+            let _ = std::fs::remove_file(format!("tests/output/routing/{}.log", tenant));
+        }
+
+        let logger = RoutingLogger::new("tenant", tenant_path).max_sinks(2);
+        log_tenant(&logger, "acme", "acme 1");
+        log_tenant(&logger, "globex", "globex 1");
+        log_tenant(&logger, "acme", "acme 2");
+        log_tenant(&logger, "initech", "dropped");
+
+        assert_eq!(
+            read_messages("tests/output/routing/acme.log"),
+            vec!["acme 1", "acme 2"]
+        );
+        assert_eq!(
+            read_messages("tests/output/routing/globex.log"),
+            vec!["globex 1"]
+        );
+        assert!(!Path::new("tests/output/routing/initech.log").exists());
+    }
+
+    #[test]
+    fn test_field_router() {
+        let dir = "tests/output/field_router";
+        let _ = std::fs::remove_dir_all(dir);
+        let sink =
+            |name: &str| Box::new(crate::FileSink::new(&format!("{}/{}.log", dir, name)).unwrap());
+
+        let mut logger = FieldRouter::new("app", "stream");
+        logger.add_route("audit", sink("audit"));
+        logger.add_route("app", sink("app"));
+        logger.set_default(sink("other"));
+        for (stream, message) in [
+            (Some("audit"), "user deleted"),
+            (Some("app"), "cache warmed"),
+            (Some("metrics"), "unrouted"),
+            (None, "no stream"),
+        ] {
+            let kvs: Vec<_> = stream.map(|s| ("stream", s)).into_iter().collect();
+            logger.log(
+                &log::Record::builder()
+                    .target("app")
+                    .level(log::Level::Info)
+                    .key_values(&kvs)
+                    .args(format_args!("{}", message))
+                    .build(),
+            );
+        }
+
+        let read = |name: &str| read_messages(&format!("{}/{}.log", dir, name));
+        assert_eq!(read("audit"), vec!["user deleted"]);
+        assert_eq!(read("app"), vec!["cache warmed"]);
+        assert_eq!(read("other"), vec!["unrouted", "no stream"]);
+    }
+
+    #[test]
+    fn test_template() {
+        let dir = Path::new("tests/output/template");
+        let _ = std::fs::remove_dir_all(dir);
+        let logger = RoutingLogger::template("tests/output/template/{target}-{pid}.log");
+        for (target, message) in [("billing", "charged"), ("auth::login", "signed in")] {
+            logger.log(
+                &log::Record::builder()
+                    .target(target)
+                    .level(log::Level::Info)
+                    .args(format_args!("{}", message))
+                    .build(),
+            );
+        }
+        logger.flush();
+
+        let pid = std::process::id();
+        let mut files: Vec<String> = std::fs::read_dir(dir)
+            .unwrap()
+            .map(|e| e.unwrap().file_name().into_string().unwrap())
+            .collect();
+        files.sort();
+        assert_eq!(
+            files,
+            vec![
+                format!("auth__login-{}.log", pid),
+                format!("billing-{}.log", pid)
+            ]
+        );
+        let billing: Vec<LogEntry> =
+            crate::read_log(dir.join(format!("billing-{}.log", pid)), false)
+                .map(|e| e.unwrap())
+                .collect();
+        assert_eq!(billing.len(), 1);
+        assert_eq!(billing[0].target, "billing");
+        assert_eq!(billing[0].message, "charged");
+        let auth = crate::read_log(dir.join(format!("auth__login-{}.log", pid)), false);
+        assert_eq!(
+            auth.map(|e| e.unwrap().target).collect::<Vec<_>>(),
+            vec!["auth::login"]
+        );
+    }
+
+    #[test]
+    fn test_template_beyond_max_sinks() {
+        let dir = Path::new("tests/output/template_many");
+        let _ = std::fs::remove_dir_all(dir);
+        let logger =
+            RoutingLogger::template("tests/output/template_many/{target}.log").max_open_sinks(4);
+        let targets: Vec<String> = (0..DEFAULT_MAX_SINKS + 6)
+            .map(|i| format!("job{}", i))
+            .collect();
+        for target in &targets {
+            logger.log(
+                &log::Record::builder()
+                    .target(target)
+                    .level(log::Level::Info)
+                    .args(format_args!("{} done", target))
+                    .build(),
+            );
+            assert!(logger.open_sinks() <= 4);
+        }
+        logger.flush();
+
+        for target in &targets {
+            assert_eq!(
+                read_messages(&format!("{}/{}.log", dir.display(), target)),
+                vec![format!("{} done", target)]
+            );
+        }
+    }
+
+    #[test]
+    fn test_routing_lru() {
+        let dir = "tests/output/routing_lru";
+        let _ = std::fs::remove_dir_all(dir);
+
+        let logger = RoutingLogger::new("tenant", move |record| {
+            record
+                .key_values()
+                .get(log::kv::Key::from("tenant"))
+                .map(|tenant| format!("{}/{}.log", dir, tenant))
+        })
+        .max_open_sinks(2);
+        let tenants = ["a", "b", "c", "d"];
+        for round in 0..3 {
+            for tenant in tenants {
+                log_tenant(&logger, tenant, &format!("{} {}", tenant, round));
+                assert!(logger.open_sinks() <= 2);
+            }
+        }
+
+        for tenant in tenants {
+            assert_eq!(
+                read_messages(&format!("{}/{}.log", dir, tenant)),
+                (0..3)
+                    .map(|round| format!("{} {}", tenant, round))
+                    .collect::<Vec<_>>()
+            );
+        }
+    }
+}
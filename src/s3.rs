@@ -0,0 +1,704 @@
+use crate::{clock, gelf::hostname, Sink};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::VecDeque,
+    io::{self, Read, Write},
+    net::{TcpStream, ToSocketAddrs},
+    sync::{Arc, Condvar, Mutex},
+    thread::JoinHandle,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+/// How and where batches are uploaded
+#[derive(Clone)]
+struct Options {
+    // `host[:port]` of the endpoint
+    authority: String,
+    bucket: String,
+    region: String,
+    credentials: Option<(String, String)>,
+    key_template: String,
+    gzip: bool,
+    max_records: usize,
+    max_bytes: usize,
+    max_age: Duration,
+    retries: u32,
+    retry_delay: Duration,
+}
+
+struct State {
+    // the lines of the batch being filled, and when its first line arrived
+    batch: Vec<u8>,
+    records: usize,
+    started: Option<Instant>,
+    // the key and body of each batch waiting to be uploaded
+    sealed: VecDeque<(String, Vec<u8>)>,
+    uploading: bool,
+    seq: u64,
+    stop: bool,
+}
+
+struct Shared {
+    state: Mutex<State>,
+    wake: Condvar,
+    idle: Condvar,
+}
+
+/// A sink that uploads records to an S3-compatible object store in batches
+///
+/// Lines are collected into a batch until it holds
+/// [`max_records`](Self::max_records) records or
+/// [`max_bytes`](Self::max_bytes) bytes, or its first record is
+/// [`max_age`](Self::max_age) old. Each batch is then uploaded by a
+/// background thread with a `PUT` request as one NDJSON object, gzipped with
+/// [`gzip`](Self::gzip), under a key rendered from
+/// [`key_template`](Self::key_template). Failed uploads are retried; a
+/// batch that still fails is reported on stderr and dropped. Flushing
+/// uploads the partial batch and waits for every upload to finish, and
+/// dropping the sink does the same.
+///
+/// Requests are signed with AWS Signature Version 4 when
+/// [`credentials`](Self::credentials) are set and use path-style URLs
+/// (`http://endpoint/bucket/key`), which S3, MinIO and most compatible stores
+/// accept. Only plain `http://` endpoints are supported; reach a TLS
+/// endpoint through a local proxy.
+/// # Example
+/// ```no_run
+/// # use crate::loggers::*;
+/// let mut logger = MultiSink::new("api");
+/// logger.add_sink(Box::new(
+///     S3Sink::new("http://127.0.0.1:9000", "logs")
+///         .unwrap()
+///         .credentials("minioadmin", "minioadmin")
+///         .gzip(true),
+/// ));
+/// ```
+pub struct S3Sink {
+    options: Options,
+    shared: Arc<Shared>,
+    worker: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl S3Sink {
+    /// upload to `bucket` at `endpoint`, e.g. `http://127.0.0.1:9000`
+    pub fn new(endpoint: &str, bucket: &str) -> io::Result<S3Sink> {
+        let authority = endpoint
+            .strip_prefix("http://")
+            .map(|rest| rest.trim_end_matches('/'))
+            .filter(|authority| !authority.is_empty() && !authority.contains('/'))
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!(
+                        "unsupported S3 endpoint {:?}, expected http://host[:port]",
+                        endpoint
+                    ),
+                )
+            })?;
+        Ok(S3Sink {
+            options: Options {
+                authority: authority.to_string(),
+                bucket: bucket.to_string(),
+                region: "us-east-1".to_string(),
+                credentials: None,
+                key_template: "logs/{date}/{host}/{millis}-{seq}.ndjson".to_string(),
+                gzip: false,
+                max_records: 1000,
+                max_bytes: 1 << 20,
+                max_age: Duration::from_secs(60),
+                retries: 3,
+                retry_delay: Duration::from_millis(500),
+            },
+            shared: Arc::new(Shared {
+                state: Mutex::new(State {
+                    batch: Vec::new(),
+                    records: 0,
+                    started: None,
+                    sealed: VecDeque::new(),
+                    uploading: false,
+                    seq: 0,
+                    stop: false,
+                }),
+                wake: Condvar::new(),
+                idle: Condvar::new(),
+            }),
+            worker: Mutex::new(None),
+        })
+    }
+
+    /// set the region requests are signed for, `us-east-1` by default
+    pub fn region(mut self, region: &str) -> S3Sink {
+        self.options.region = region.to_string();
+        self
+    }
+
+    /// sign requests with an access key; without credentials requests are
+    /// sent unsigned, which only a bucket that allows anonymous writes
+    /// accepts
+    pub fn credentials(mut self, access_key: &str, secret_key: &str) -> S3Sink {
+        self.options.credentials = Some((access_key.to_string(), secret_key.to_string()));
+        self
+    }
+
+    /// set the key each batch is uploaded under
+    ///
+    /// The placeholders `{date}`, the local date as `YYYY-MM-DD`, `{host}`,
+    /// the name of this machine, `{millis}`, the Unix time in milliseconds
+    /// when the batch was completed, and `{seq}`, the number of the batch
+    /// counted from `000000`, are replaced. The default is
+    /// `logs/{date}/{host}/{millis}-{seq}.ndjson`. With [`gzip`](Self::gzip)
+    /// `.gz` is appended.
+    ///
+    /// `{seq}` starts again from `000000` in every process, so a template
+    /// without `{millis}` overwrites the objects uploaded by an earlier run
+    /// of the program on the same day.
+    /// # Example
+    /// ```no_run
+    /// # use crate::loggers::*;
+    /// let sink = S3Sink::new("http://127.0.0.1:9000", "logs")
+    ///     .unwrap()
+    ///     .key_template("api/{host}/{date}/{seq}.ndjson");
+    /// ```
+    pub fn key_template(mut self, template: &str) -> S3Sink {
+        self.options.key_template = template.to_string();
+        self
+    }
+
+    /// gzip each object and upload it with `Content-Encoding: gzip`
+    pub fn gzip(mut self, gzip: bool) -> S3Sink {
+        self.options.gzip = gzip;
+        self
+    }
+
+    /// complete a batch once it holds `records` records, 1000 by default
+    pub fn max_records(mut self, records: usize) -> S3Sink {
+        self.options.max_records = records.max(1);
+        self
+    }
+
+    /// complete a batch once it holds `bytes` bytes of NDJSON, 1 MiB by default
+    pub fn max_bytes(mut self, bytes: usize) -> S3Sink {
+        self.options.max_bytes = bytes.max(1);
+        self
+    }
+
+    /// complete a batch once its first record is `age` old, 60 seconds by
+    /// default, so records logged slowly still reach the store
+    pub fn max_age(mut self, age: Duration) -> S3Sink {
+        self.options.max_age = age;
+        self
+    }
+
+    /// retry a failed upload up to `retries` times, 3 by default, waiting
+    /// [`retry_delay`](Self::retry_delay) before the first retry and twice
+    /// as long before each following one
+    ///
+    /// Connection errors, `429 Too Many Requests` and `5xx` responses are
+    /// retried; other responses, such as a rejected signature, are not.
+    pub fn retries(mut self, retries: u32) -> S3Sink {
+        self.options.retries = retries;
+        self
+    }
+
+    /// set the delay before the first retry, 500 milliseconds by default
+    pub fn retry_delay(mut self, delay: Duration) -> S3Sink {
+        self.options.retry_delay = delay;
+        self
+    }
+
+    /// start the upload thread unless it is running
+    fn spawn_worker(&self) -> io::Result<()> {
+        let mut worker = self.worker.lock().unwrap();
+        if worker.is_none() {
+            let shared = self.shared.clone();
+            let options = self.options.clone();
+            *worker = Some(
+                std::thread::Builder::new()
+                    .name("loggers-s3".to_string())
+                    .spawn(move || run(&shared, &options))?,
+            );
+        }
+        Ok(())
+    }
+}
+
+impl Sink for S3Sink {
+    fn write_line(&self, line: &str) -> io::Result<()> {
+        let mut state = self.shared.state.lock().unwrap();
+        state.batch.extend_from_slice(line.as_bytes());
+        state.batch.push(b'\n');
+        state.records += 1;
+        state.started.get_or_insert_with(Instant::now);
+        let first = state.records == 1;
+        if state.records >= self.options.max_records || state.batch.len() >= self.options.max_bytes
+        {
+            seal(&mut state, &self.options);
+        } else if !first {
+            return Ok(());
+        }
+        drop(state);
+        self.spawn_worker()?;
+        self.shared.wake.notify_one();
+        Ok(())
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        let mut state = self.shared.state.lock().unwrap();
+        seal(&mut state, &self.options);
+        if state.sealed.is_empty() && !state.uploading {
+            return Ok(());
+        }
+        drop(state);
+        self.spawn_worker()?;
+        self.shared.wake.notify_one();
+        let mut state = self.shared.state.lock().unwrap();
+        while !state.sealed.is_empty() || state.uploading {
+            state = self.shared.idle.wait(state).unwrap();
+        }
+        Ok(())
+    }
+}
+
+impl Drop for S3Sink {
+    fn drop(&mut self) {
+        self.shared.state.lock().unwrap().stop = true;
+        self.shared.wake.notify_one();
+        if let Some(worker) = self.worker.lock().unwrap().take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+/// move the batch being filled, if any, to the upload queue
+fn seal(state: &mut State, options: &Options) {
+    if state.records == 0 {
+        return;
+    }
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+    let mut key = options
+        .key_template
+        .replace("{date}", &clock::Date::today().to_string())
+        .replace("{host}", hostname())
+        .replace("{millis}", &millis.to_string())
+        .replace("{seq}", &format!("{:06}", state.seq));
+    if options.gzip {
+        key.push_str(".gz");
+    }
+    let body = std::mem::take(&mut state.batch);
+    state.sealed.push_back((key, body));
+    state.records = 0;
+    state.started = None;
+    state.seq += 1;
+}
+
+/// upload completed batches and complete expired ones, until stopped
+fn run(shared: &Shared, options: &Options) {
+    let mut state = shared.state.lock().unwrap();
+    loop {
+        if let Some((key, body)) = state.sealed.pop_front() {
+            state.uploading = true;
+            drop(state);
+            if let Err(e) = upload(options, &key, body) {
+                eprintln!(
+                    "S3Sink: cannot upload {} to bucket {}: {}",
+                    key, options.bucket, e
+                );
+            }
+            state = shared.state.lock().unwrap();
+            state.uploading = false;
+            shared.idle.notify_all();
+            continue;
+        }
+        let due = state.started.map(|started| started + options.max_age);
+        match due {
+            Some(due) if state.stop || Instant::now() >= due => seal(&mut state, options),
+            Some(due) => {
+                let wait = due.saturating_duration_since(Instant::now());
+                state = shared.wake.wait_timeout(state, wait).unwrap().0;
+            }
+            None if state.stop => return,
+            None => state = shared.wake.wait(state).unwrap(),
+        }
+    }
+}
+
+/// upload one object, retrying as configured
+fn upload(options: &Options, key: &str, body: Vec<u8>) -> io::Result<()> {
+    let body = if options.gzip {
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(&body)?;
+        encoder.finish()?
+    } else {
+        body
+    };
+    let mut delay = options.retry_delay;
+    let mut attempt = 0;
+    loop {
+        match put(options, key, &body) {
+            Ok(()) => return Ok(()),
+            Err((e, retryable)) if !retryable || attempt >= options.retries => return Err(e),
+            Err(_) => {
+                std::thread::sleep(delay);
+                delay *= 2;
+                attempt += 1;
+            }
+        }
+    }
+}
+
+/// how long a request may wait to connect, or for each write or read
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// connect to `authority`, on port 80 unless it names one, trying each of
+/// its addresses in turn
+fn connect(authority: &str) -> io::Result<TcpStream> {
+    let addr = match authority.rsplit_once(':') {
+        Some((_, port)) if port.parse::<u16>().is_ok() => authority.to_string(),
+        _ => format!("{}:80", authority),
+    };
+    let mut last = None;
+    for addr in addr.to_socket_addrs()? {
+        match TcpStream::connect_timeout(&addr, TIMEOUT) {
+            Ok(stream) => return Ok(stream),
+            Err(e) => last = Some(e),
+        }
+    }
+    Err(last.unwrap_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("no address found for {:?}", authority),
+        )
+    }))
+}
+
+/// send one `PUT` request; an error comes with whether it is worth retrying
+fn put(options: &Options, key: &str, body: &[u8]) -> Result<(), (io::Error, bool)> {
+    let path = format!("/{}/{}", uri_encode(&options.bucket), uri_encode(key));
+    let mut headers = vec![
+        ("content-length", body.len().to_string()),
+        ("content-type", "application/x-ndjson".to_string()),
+        ("host", options.authority.clone()),
+        ("x-amz-content-sha256", hex(&Sha256::digest(body))),
+        ("x-amz-date", clock::utc_basic(SystemTime::now())),
+    ];
+    if options.gzip {
+        headers.insert(0, ("content-encoding", "gzip".to_string()));
+    }
+    if let Some((access_key, secret_key)) = &options.credentials {
+        let authorization = authorization(&path, &headers, &options.region, access_key, secret_key);
+        headers.push(("authorization", authorization));
+    }
+
+    let mut request = format!("PUT {} HTTP/1.1\r\n", path);
+    for (name, value) in &headers {
+        request.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    request.push_str("connection: close\r\n\r\n");
+
+    let retryable = |e: io::Error| (e, true);
+    let mut stream = connect(&options.authority).map_err(retryable)?;
+    stream.set_write_timeout(Some(TIMEOUT)).map_err(retryable)?;
+    stream.set_read_timeout(Some(TIMEOUT)).map_err(retryable)?;
+    stream.write_all(request.as_bytes()).map_err(retryable)?;
+    stream.write_all(body).map_err(retryable)?;
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response).map_err(retryable)?;
+
+    let response = String::from_utf8_lossy(&response);
+    let status_line = response.lines().next().unwrap_or_default();
+    let status: u16 = status_line
+        .split_whitespace()
+        .nth(1)
+        .and_then(|code| code.parse().ok())
+        .unwrap_or(0);
+    match status {
+        200..=299 => Ok(()),
+        _ => Err((
+            io::Error::other(format!("unexpected response {:?}", status_line)),
+            status == 0 || status == 429 || status >= 500,
+        )),
+    }
+}
+
+/// the `Authorization` header of AWS Signature Version 4 for a `PUT` of
+/// `path` with `headers`, which must be lowercase, sorted and include
+/// `x-amz-date` and `x-amz-content-sha256`
+fn authorization(
+    path: &str,
+    headers: &[(&str, String)],
+    region: &str,
+    access_key: &str,
+    secret_key: &str,
+) -> String {
+    let header = |name| {
+        headers
+            .iter()
+            .find(|(n, _)| *n == name)
+            .map(|(_, value)| value.as_str())
+            .unwrap_or_default()
+    };
+    let amz_date = header("x-amz-date");
+    let date = &amz_date[..amz_date.len().min(8)];
+    let scope = format!("{}/{}/s3/aws4_request", date, region);
+
+    let canonical_headers: String = headers
+        .iter()
+        .map(|(name, value)| format!("{}:{}\n", name, value.trim()))
+        .collect();
+    let signed_headers = headers
+        .iter()
+        .map(|(name, _)| *name)
+        .collect::<Vec<_>>()
+        .join(";");
+    let canonical_request = format!(
+        "PUT\n{}\n\n{}\n{}\n{}",
+        path,
+        canonical_headers,
+        signed_headers,
+        header("x-amz-content-sha256")
+    );
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+        amz_date,
+        scope,
+        hex(&Sha256::digest(canonical_request.as_bytes()))
+    );
+    let key = signing_key(secret_key, date, region, "s3");
+    format!(
+        "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+        access_key,
+        scope,
+        signed_headers,
+        hex(&hmac(&key, string_to_sign.as_bytes()))
+    )
+}
+
+fn signing_key(secret_key: &str, date: &str, region: &str, service: &str) -> [u8; 32] {
+    let key = hmac(format!("AWS4{}", secret_key).as_bytes(), date.as_bytes());
+    let key = hmac(&key, region.as_bytes());
+    let key = hmac(&key, service.as_bytes());
+    hmac(&key, b"aws4_request")
+}
+
+/// HMAC-SHA256 as defined in RFC 2104
+fn hmac(key: &[u8], data: &[u8]) -> [u8; 32] {
+    let mut block = [0u8; 64];
+    if key.len() > block.len() {
+        block[..32].copy_from_slice(&Sha256::digest(key));
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+    let inner = Sha256::new()
+        .chain_update(block.map(|b| b ^ 0x36))
+        .chain_update(data)
+        .finalize();
+    Sha256::new()
+        .chain_update(block.map(|b| b ^ 0x5c))
+        .chain_update(inner)
+        .finalize()
+        .into()
+}
+
+/// percent-encode everything but unreserved characters and `/`
+fn uri_encode(text: &str) -> String {
+    let mut encoded = String::with_capacity(text.len());
+    for b in text.bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => {
+                encoded.push(b as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", b)),
+        }
+    }
+    encoded
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{LogEntry, MultiSink};
+    use log::Log;
+    use std::{collections::HashMap, io::BufRead, net::TcpListener};
+
+    struct Request {
+        path: String,
+        headers: HashMap<String, String>,
+        body: Vec<u8>,
+    }
+
+    /// a local stand-in for an S3 endpoint that records every `PUT` and
+    /// answers the first `failures` of them with `503`
+    fn mock_s3(failures: usize) -> (String, Arc<Mutex<Vec<Request>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let endpoint = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let received = requests.clone();
+        std::thread::spawn(move || {
+            for (n, conn) in listener.incoming().enumerate() {
+                let mut conn = io::BufReader::new(conn.unwrap());
+                let mut line = String::new();
+                conn.read_line(&mut line).unwrap();
+                let path = line.split_whitespace().nth(1).unwrap().to_string();
+                let mut headers = HashMap::new();
+                loop {
+                    line.clear();
+                    conn.read_line(&mut line).unwrap();
+                    match line.trim_end().split_once(": ") {
+                        Some((name, value)) => headers.insert(name.to_string(), value.to_string()),
+                        None => break,
+                    };
+                }
+                let mut body = vec![0; headers["content-length"].parse().unwrap()];
+                conn.read_exact(&mut body).unwrap();
+                let status = if n < failures {
+                    "503 Service Unavailable"
+                } else {
+                    "200 OK"
+                };
+                received.lock().unwrap().push(Request {
+                    path,
+                    headers,
+                    body,
+                });
+                let response = format!("HTTP/1.1 {}\r\ncontent-length: 0\r\n\r\n", status);
+                conn.get_mut().write_all(response.as_bytes()).unwrap();
+            }
+        });
+        (endpoint, requests)
+    }
+
+    fn log_to(logger: &MultiSink, message: &str) {
+        logger.log(
+            &log::Record::builder()
+                .target("s3")
+                .level(log::Level::Info)
+                .args(format_args!("{}", message))
+                .build(),
+        );
+    }
+
+    fn messages(ndjson: &[u8]) -> Vec<String> {
+        std::str::from_utf8(ndjson)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str::<LogEntry>(line).unwrap().message)
+            .collect()
+    }
+
+    #[test]
+    fn test_s3_sink() {
+        let (endpoint, requests) = mock_s3(1);
+        let sink = S3Sink::new(&endpoint, "logs")
+            .unwrap()
+            .credentials("AKID", "secret")
+            .key_template("api/{date}/{host}/{seq}.ndjson")
+            .gzip(true)
+            .max_records(2)
+            .retry_delay(Duration::from_millis(10));
+        let mut logger = MultiSink::new("s3");
+        logger.add_sink(Box::new(sink));
+        for message in ["first", "second", "third"] {
+            log_to(&logger, message);
+        }
+        drop(logger);
+
+        let requests = requests.lock().unwrap();
+        // the first upload is retried after the 503
+        assert_eq!(requests.len(), 3);
+        let prefix = format!("/logs/api/{}/{}/", clock::Date::today(), hostname());
+        let paths: Vec<&str> = requests.iter().map(|r| r.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            vec![
+                format!("{}000000.ndjson.gz", prefix),
+                format!("{}000000.ndjson.gz", prefix),
+                format!("{}000001.ndjson.gz", prefix),
+            ]
+        );
+        assert_eq!(requests[0].body, requests[1].body);
+
+        let objects: Vec<Vec<String>> = requests[1..]
+            .iter()
+            .map(|request| {
+                assert_eq!(request.headers["content-encoding"], "gzip");
+                assert_eq!(
+                    request.headers["x-amz-content-sha256"],
+                    hex(&Sha256::digest(&request.body))
+                );
+                let credential = format!(
+                    "AWS4-HMAC-SHA256 Credential=AKID/{}/us-east-1/s3/aws4_request, ",
+                    &request.headers["x-amz-date"][..8]
+                );
+                assert!(request.headers["authorization"].starts_with(&credential));
+                let mut ndjson = Vec::new();
+                flate2::read::GzDecoder::new(&request.body[..])
+                    .read_to_end(&mut ndjson)
+                    .unwrap();
+                messages(&ndjson)
+            })
+            .collect();
+        assert_eq!(objects, vec![vec!["first", "second"], vec!["third"]]);
+    }
+
+    #[test]
+    fn test_max_age() {
+        let (endpoint, requests) = mock_s3(0);
+        let sink = S3Sink::new(&endpoint, "logs")
+            .unwrap()
+            .max_age(Duration::from_millis(50));
+        let mut logger = MultiSink::new("s3");
+        logger.add_sink(Box::new(sink));
+        log_to(&logger, "slow");
+
+        // uploaded without a flush once the batch is old enough
+        let started = Instant::now();
+        while requests.lock().unwrap().is_empty() {
+            assert!(started.elapsed() < Duration::from_secs(5));
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        let requests = requests.lock().unwrap();
+        assert!(requests[0].path.ends_with("-000000.ndjson"));
+        assert!(!requests[0].headers.contains_key("authorization"));
+        assert_eq!(messages(&requests[0].body), vec!["slow"]);
+    }
+
+    #[test]
+    fn test_signing() {
+        // the example from the AWS Signature Version 4 documentation
+        let key = signing_key(
+            "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY",
+            "20120215",
+            "us-east-1",
+            "iam",
+        );
+        assert_eq!(
+            hex(&key),
+            "f4780e2d9f65fa895f9c67b32ce1baf0b0d8a43505a000a1a9e090d414db404d"
+        );
+        assert_eq!(
+            clock::utc_basic(UNIX_EPOCH + Duration::from_secs(1_329_305_445)),
+            "20120215T113045Z"
+        );
+        let headers = [
+            ("content-length", "3".to_string()),
+            ("host", "127.0.0.1:9000".to_string()),
+            ("x-amz-content-sha256", hex(&Sha256::digest(b"{}\n"))),
+            ("x-amz-date", "20240601T120000Z".to_string()),
+        ];
+        assert_eq!(
+            authorization("/logs/a.ndjson", &headers, "us-east-1", "AKID", "secret"),
+            "AWS4-HMAC-SHA256 Credential=AKID/20240601/us-east-1/s3/aws4_request, \
+             SignedHeaders=content-length;host;x-amz-content-sha256;x-amz-date, \
+             Signature=1949449585b90d60f9eee3b07de352f66655b0f7a84fc77d5aaa102826fb2797"
+        );
+        assert_eq!(uri_encode("logs/a b+c.ndjson"), "logs/a%20b%2Bc.ndjson");
+        assert!(S3Sink::new("https://s3.amazonaws.com", "logs").is_err());
+    }
+}
@@ -0,0 +1,170 @@
+use crate::{read_log, CustomLogger, Error, LogRecord};
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+    path::{Path, PathBuf},
+};
+
+/// the file of shard `index` of `filepath`, e.g. `system.log.shard0`
+fn shard_path(filepath: &str, index: usize) -> String {
+    format!("{}.shard{}", filepath, index)
+}
+
+/// A logger that spreads records over several files to reduce contention
+///
+/// Each thread always writes to the same shard, chosen by hashing its id, so
+/// threads on different shards never wait on each other's file lock and the
+/// records of one thread stay in order within its shard. Shard `i` of
+/// `system.log` is `system.log.shard<i>`; read all of them back merged by
+/// timestamp with [`read_shards`].
+pub struct ShardedLogger {
+    shards: Vec<CustomLogger>,
+}
+
+impl ShardedLogger {
+    /// create a ShardedLogger writing to `shards` files next to `filepath`,
+    /// truncating any existing ones
+    /// # Arguments
+    /// * `target` - The target to match
+    /// * `filepath` - The path the shard files are named after
+    /// * `shards` - The number of shard files, at least 1
+    /// # Example
+    /// ```
+    /// # use crate::loggers::*;
+    /// let logger = ShardedLogger::new("api", "tests/output/api.log", 4)
+    ///     .unwrap()
+    ///     .configure(|shard| shard.console(false).location(true));
+    /// ```
+    pub fn new(target: &str, filepath: &str, shards: usize) -> std::io::Result<ShardedLogger> {
+        let shards = (0..shards.max(1))
+            .map(|i| CustomLogger::try_new(target, &shard_path(filepath, i)))
+            .collect::<std::io::Result<_>>()?;
+        Ok(ShardedLogger { shards })
+    }
+
+    /// apply the same CustomLogger options to every shard
+    pub fn configure<F: FnMut(CustomLogger) -> CustomLogger>(self, f: F) -> ShardedLogger {
+        ShardedLogger {
+            shards: self.shards.into_iter().map(f).collect(),
+        }
+    }
+
+    /// the shard the current thread writes to
+    fn shard(&self) -> &CustomLogger {
+        let mut hasher = DefaultHasher::new();
+        std::thread::current().id().hash(&mut hasher);
+        &self.shards[hasher.finish() as usize % self.shards.len()]
+    }
+}
+
+impl log::Log for ShardedLogger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        if crate::inspect::is_hook(metadata) {
+            for (i, shard) in self.shards.iter().enumerate() {
+                crate::inspect::forward(&format!("shard {}", i), shard);
+            }
+            return false;
+        }
+        self.shards[0].enabled(metadata)
+    }
+
+    fn log(&self, record: &log::Record) {
+        self.shard().log(record);
+    }
+
+    fn flush(&self) {
+        for shard in &self.shards {
+            shard.flush();
+        }
+    }
+}
+
+/// read back the records written by a [`ShardedLogger`] to `filepath`,
+/// merged across shards in timestamp order
+///
+/// Records with equal timestamps keep their order within a shard, those of
+/// lower-numbered shards first. All records are held in memory to
+/// sort them; the first unreadable line fails the whole read unless
+/// `skip_malformed` is set.
+/// # Example
+/// ```no_run
+/// # use crate::loggers::*;
+/// for record in read_shards("api.log", true).unwrap() {
+///     println!("{}", record.message);
+/// }
+/// ```
+pub fn read_shards<P: AsRef<Path>>(
+    filepath: P,
+    skip_malformed: bool,
+) -> Result<Vec<LogRecord>, Error> {
+    let filepath = filepath.as_ref().to_string_lossy();
+    let mut records = Vec::new();
+    for index in 0.. {
+        let path = PathBuf::from(shard_path(&filepath, index));
+        if !path.exists() {
+            break;
+        }
+        for record in read_log(&path, skip_malformed) {
+            records.push(record?);
+        }
+    }
+    records.sort_by_cached_key(|record| {
+        crate::clock::Timestamp::parse(&record.timestamp).map(|t| t.epoch_micros())
+    });
+    Ok(records)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use log::Log;
+
+    #[test]
+    fn test_sharded_logger() {
+        let path = "tests/output/sharded.log";
+        let logger = ShardedLogger::new("sharded", path, 2)
+            .unwrap()
+            .configure(|shard| shard.console(false));
+        std::thread::scope(|scope| {
+            for thread in 0..8 {
+                let logger = &logger;
+                scope.spawn(move || {
+                    for i in 0..25 {
+                        logger.log(
+                            &log::Record::builder()
+                                .target("sharded")
+                                .level(log::Level::Info)
+                                .args(format_args!("{}-{}", thread, i))
+                                .build(),
+                        );
+                    }
+                });
+            }
+        });
+
+        let per_shard: Vec<usize> = (0..2)
+            .map(|i| read_log(shard_path(path, i), false).count())
+            .collect();
+        assert_eq!(per_shard.iter().sum::<usize>(), 200);
+
+        let records = read_shards(path, false).unwrap();
+        assert_eq!(records.len(), 200);
+        let mut messages: Vec<&str> = records.iter().map(|r| r.message.as_str()).collect();
+        assert!(records.windows(2).all(|w| {
+            let t = |r: &LogRecord| chrono::DateTime::parse_from_rfc3339(&r.timestamp).unwrap();
+            t(&w[0]) <= t(&w[1])
+        }));
+        // each thread's records stay in the order it logged them
+        for thread in 0..8 {
+            let prefix = format!("{}-", thread);
+            let own: Vec<usize> = messages
+                .iter()
+                .filter_map(|m| m.strip_prefix(&prefix)?.parse().ok())
+                .collect();
+            assert_eq!(own, (0..25).collect::<Vec<_>>());
+        }
+        messages.sort();
+        messages.dedup();
+        assert_eq!(messages.len(), 200);
+    }
+}
@@ -0,0 +1,284 @@
+use crate::LogEntry;
+use std::{
+    fs::File,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+/// A destination for rendered log lines
+pub trait Sink: Send + Sync {
+    /// write a single rendered record, without the line terminator
+    fn write_line(&self, line: &str) -> io::Result<()>;
+
+    fn flush(&self) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// check that the sink can be written to without writing to it, for
+    /// [`Logger::self_test`](crate::Logger::self_test); `None` if it cannot
+    /// be checked
+    fn probe(&self) -> Option<io::Result<()>> {
+        None
+    }
+}
+
+/// Appends lines to a file
+pub struct FileSink {
+    path: PathBuf,
+    file: Mutex<File>,
+}
+
+impl FileSink {
+    /// open `filepath` for appending, creating it and its parent directories if needed
+    pub fn new(filepath: &str) -> io::Result<FileSink> {
+        if let Some(p) = Path::new(filepath).parent() {
+            std::fs::create_dir_all(p)?;
+        }
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(filepath)?;
+        Ok(FileSink {
+            path: PathBuf::from(filepath),
+            file: Mutex::new(file),
+        })
+    }
+}
+
+impl Sink for FileSink {
+    fn write_line(&self, line: &str) -> io::Result<()> {
+        self.file
+            .lock()
+            .unwrap()
+            .write_all(format!("{}\n", line).as_bytes())
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        self.file.lock().unwrap().flush()
+    }
+
+    fn probe(&self) -> Option<io::Result<()>> {
+        let file = std::fs::OpenOptions::new().append(true).open(&self.path);
+        Some(file.map(drop))
+    }
+}
+
+/// Prints lines to stdout
+pub struct StdoutSink;
+
+impl Sink for StdoutSink {
+    fn write_line(&self, line: &str) -> io::Result<()> {
+        writeln!(io::stdout().lock(), "{}", line)
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        io::stdout().flush()
+    }
+
+    fn probe(&self) -> Option<io::Result<()>> {
+        Some(Ok(()))
+    }
+}
+
+/// Keeps lines in memory; clones share the same buffer
+#[derive(Clone, Default)]
+pub struct MemorySink {
+    lines: Arc<Mutex<Vec<String>>>,
+}
+
+impl MemorySink {
+    pub fn new() -> MemorySink {
+        MemorySink::default()
+    }
+
+    /// the lines written so far
+    pub fn lines(&self) -> Vec<String> {
+        self.lines.lock().unwrap().clone()
+    }
+}
+
+impl Sink for MemorySink {
+    fn write_line(&self, line: &str) -> io::Result<()> {
+        self.lines.lock().unwrap().push(line.to_string());
+        Ok(())
+    }
+
+    fn probe(&self) -> Option<io::Result<()>> {
+        Some(Ok(()))
+    }
+}
+
+/// Writes lines to a writer owned by the caller, such as the buffer behind a
+/// log pane
+///
+/// # Example
+/// ```
+/// # use crate::loggers::*;
+/// use std::sync::{Arc, Mutex};
+///
+/// let pane = Arc::new(Mutex::new(Vec::<u8>::new()));
+/// let mut logger = MultiSink::new("gui");
+/// logger.add_sink(Box::new(WriterSink::new(pane.clone())));
+/// ```
+pub struct WriterSink {
+    writer: Arc<Mutex<dyn Write + Send>>,
+}
+
+impl WriterSink {
+    /// write into `writer`, which the caller keeps a handle to
+    pub fn new(writer: Arc<Mutex<dyn Write + Send>>) -> WriterSink {
+        WriterSink { writer }
+    }
+}
+
+impl Sink for WriterSink {
+    fn write_line(&self, line: &str) -> io::Result<()> {
+        let mut writer = self.writer.lock().unwrap();
+        writer.write_all(line.as_bytes())?;
+        writer.write_all(b"\n")
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        self.writer.lock().unwrap().flush()
+    }
+}
+
+/// A logger that writes each record matching its target to several sinks
+///
+/// The record is rendered once and handed to every sink. A sink that fails
+/// is reported on stderr and does not prevent the others from receiving it.
+pub struct MultiSink {
+    target: String,
+    sinks: Vec<Box<dyn Sink>>,
+}
+
+impl MultiSink {
+    pub fn new(target: &str) -> MultiSink {
+        MultiSink {
+            target: target.to_string(),
+            sinks: Vec::new(),
+        }
+    }
+
+    /// add a sink
+    /// # Arguments
+    /// * `sink` - The sink to add
+    /// # Example
+    /// ```
+    /// # use crate::loggers::*;
+    /// let mut logger = MultiSink::new("test");
+    /// logger.add_sink(Box::new(FileSink::new("system.log").unwrap()));
+    /// logger.add_sink(Box::new(StdoutSink));
+    /// ```
+    pub fn add_sink(&mut self, sink: Box<dyn Sink>) {
+        self.sinks.push(sink);
+    }
+}
+
+impl log::Log for MultiSink {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        if crate::inspect::is_hook(metadata) {
+            for (i, sink) in self.sinks.iter().enumerate() {
+                let name = format!("MultiSink {} sink {}", self.target, i);
+                crate::inspect::report_sink(&name, sink.as_ref());
+            }
+            return false;
+        }
+        metadata.target() == self.target
+    }
+
+    fn log(&self, record: &log::Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        let timestamp = crate::clock::timestamp();
+        let line = serde_json::to_string(&LogEntry::from_record(record, &self.target, &timestamp))
+            .unwrap();
+        for (i, sink) in self.sinks.iter().enumerate() {
+            if let Err(e) = sink.write_line(&line) {
+                eprintln!("MultiSink {}: sink {} failed: {}", self.target, i, e);
+            }
+        }
+    }
+
+    fn flush(&self) {
+        for (i, sink) in self.sinks.iter().enumerate() {
+            if let Err(e) = sink.flush() {
+                eprintln!(
+                    "MultiSink {}: sink {} failed to flush: {}",
+                    self.target, i, e
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::read_log;
+    use log::Log;
+
+    struct FailingSink;
+
+    impl Sink for FailingSink {
+        fn write_line(&self, _: &str) -> io::Result<()> {
+            Err(io::Error::other("unavailable"))
+        }
+    }
+
+    #[test]
+    fn test_multi_sink() {
+        let path = "tests/output/multi_sink.log";
+        let _ = std::fs::remove_file(path);
+        let memory = MemorySink::new();
+
+        let mut logger = MultiSink::new("multi");
+        logger.add_sink(Box::new(FailingSink));
+        logger.add_sink(Box::new(FileSink::new(path).unwrap()));
+        logger.add_sink(Box::new(memory.clone()));
+        logger.log(
+            &log::Record::builder()
+                .target("multi")
+                .level(log::Level::Info)
+                .args(format_args!("fan out"))
+                .build(),
+        );
+
+        let from_file: Vec<LogEntry> = read_log(path, false).map(|e| e.unwrap()).collect();
+        assert_eq!(from_file.len(), 1);
+        assert_eq!(from_file[0].message, "fan out");
+
+        let from_memory = memory.lines();
+        assert_eq!(from_memory.len(), 1);
+        assert_eq!(
+            serde_json::from_str::<LogEntry>(&from_memory[0]).unwrap(),
+            from_file[0]
+        );
+    }
+
+    #[test]
+    fn test_writer_sink() {
+        let pane = Arc::new(Mutex::new(Vec::<u8>::new()));
+        let mut logger = MultiSink::new("writer_sink");
+        logger.add_sink(Box::new(WriterSink::new(pane.clone())));
+        for message in ["opened", "saved"] {
+            logger.log(
+                &log::Record::builder()
+                    .target("writer_sink")
+                    .level(log::Level::Info)
+                    .args(format_args!("{}", message))
+                    .build(),
+            );
+        }
+
+        let written = String::from_utf8(pane.lock().unwrap().clone()).unwrap();
+        let messages: Vec<String> = written
+            .lines()
+            .map(|line| serde_json::from_str::<LogEntry>(line).unwrap().message)
+            .collect();
+        assert_eq!(messages, vec!["opened", "saved"]);
+    }
+}
@@ -0,0 +1,224 @@
+//! A lightweight, thread-local stack of named spans
+//!
+//! While a [`SpanGuard`] is alive its name is on the current thread's span
+//! stack, and every record logged on that thread carries the stack, outermost
+//! first, as a `spans` array field.
+//!
+//! ```
+//! use loggers::span;
+//!
+//! let _request = span::enter("request");
+//! {
+//!     let _db = span::enter("db");
+//!     // records logged here have "spans": ["request", "db"]
+//! }
+//! // and here "spans": ["request"]
+//! ```
+//!
+//! A distributed trace context set with [`set_trace`] likewise adds
+//! `trace_id` and `span_id` fields, as lowercase hex, to every record logged
+//! on the thread until its guard is dropped. A
+//! [`CustomLogger`](crate::CustomLogger) can write them under other keys or in
+//! decimal with [`trace_fields`](crate::CustomLogger::trace_fields).
+//!
+//! ```
+//! use loggers::span;
+//!
+//! let _trace = span::set_trace(0x4bf92f3577b34da6a3ce929d0e0e4736, 0x00f067aa0ba902b7);
+//! // records logged here have "trace_id": "4bf92f3577b34da6a3ce929d0e0e4736"
+//! ```
+
+use std::{
+    cell::{Cell, RefCell},
+    marker::PhantomData,
+};
+
+thread_local! {
+    static SPANS: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+    static TRACE: Cell<Option<TraceContext>> = const { Cell::new(None) };
+}
+
+/// The distributed trace a thread is currently working on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TraceContext {
+    pub trace_id: u128,
+    pub span_id: u64,
+}
+
+/// How trace and span ids are written
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum IdFormat {
+    /// zero-padded lowercase hex, 32 digits for trace ids and 16 for span ids,
+    /// as in W3C Trace Context
+    #[default]
+    Hex,
+    /// decimal strings
+    Decimal,
+}
+
+impl IdFormat {
+    pub(crate) fn trace_id(self, id: u128) -> String {
+        match self {
+            IdFormat::Hex => format!("{:032x}", id),
+            IdFormat::Decimal => id.to_string(),
+        }
+    }
+
+    pub(crate) fn span_id(self, id: u64) -> String {
+        match self {
+            IdFormat::Hex => format!("{:016x}", id),
+            IdFormat::Decimal => id.to_string(),
+        }
+    }
+}
+
+/// Keeps a trace context current until dropped, then restores the previous one
+#[must_use = "the trace context is cleared as soon as the guard is dropped"]
+pub struct TraceGuard {
+    previous: Option<TraceContext>,
+    _not_send: PhantomData<*const ()>,
+}
+
+/// make `trace_id`/`span_id` the current thread's trace context
+pub fn set_trace(trace_id: u128, span_id: u64) -> TraceGuard {
+    let previous = TRACE.with(|trace| trace.replace(Some(TraceContext { trace_id, span_id })));
+    TraceGuard {
+        previous,
+        _not_send: PhantomData,
+    }
+}
+
+/// the current thread's trace context
+pub fn trace() -> Option<TraceContext> {
+    TRACE.with(Cell::get)
+}
+
+impl Drop for TraceGuard {
+    fn drop(&mut self) {
+        TRACE.with(|trace| trace.set(self.previous));
+    }
+}
+
+/// Keeps a span on the stack until dropped
+///
+/// Dropping a guard also removes any spans entered after it that are still
+/// on the stack, so the stack stays consistent whatever order guards are
+/// dropped in.
+#[must_use = "the span is exited as soon as the guard is dropped"]
+pub struct SpanGuard {
+    depth: usize,
+    // the guard refers to this thread's stack
+    _not_send: PhantomData<*const ()>,
+}
+
+/// push `name` onto the current thread's span stack
+pub fn enter(name: &str) -> SpanGuard {
+    let depth = SPANS.with(|spans| {
+        let mut spans = spans.borrow_mut();
+        spans.push(name.to_string());
+        spans.len() - 1
+    });
+    SpanGuard {
+        depth,
+        _not_send: PhantomData,
+    }
+}
+
+/// the current thread's span stack, outermost first
+pub fn current() -> Vec<String> {
+    SPANS.with(|spans| spans.borrow().clone())
+}
+
+impl Drop for SpanGuard {
+    fn drop(&mut self) {
+        SPANS.with(|spans| spans.borrow_mut().truncate(self.depth));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{read_log, CustomLogger};
+    use log::Log;
+
+    fn log_to(logger: &CustomLogger, message: &str) {
+        logger.log(
+            &log::Record::builder()
+                .target("span")
+                .level(log::Level::Info)
+                .args(format_args!("{}", message))
+                .build(),
+        );
+    }
+
+    #[test]
+    fn test_spans() {
+        let path = "tests/output/span.log";
+        let logger = CustomLogger::new("span", path);
+
+        log_to(&logger, "outside");
+        {
+            let _request = enter("request");
+            {
+                let _db = enter("db");
+                log_to(&logger, "query");
+            }
+            log_to(&logger, "respond");
+        }
+        log_to(&logger, "done");
+
+        let outer = enter("outer");
+        let inner = enter("inner");
+        drop(outer);
+        assert!(current().is_empty());
+        drop(inner);
+        assert!(current().is_empty());
+
+        let spans: Vec<Option<serde_json::Value>> = read_log(path, false)
+            .map(|e| e.unwrap().fields.get("spans").cloned())
+            .collect();
+        assert_eq!(
+            spans,
+            vec![
+                None,
+                Some(serde_json::json!(["request", "db"])),
+                Some(serde_json::json!(["request"])),
+                None,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_trace_context() {
+        let path = "tests/output/trace_context.log";
+        let logger =
+            CustomLogger::new("span", path).trace_fields("trace.id", "span.id", IdFormat::Decimal);
+        let default = CustomLogger::new("span", "tests/output/trace_context_default.log");
+
+        {
+            let _trace = set_trace(0xabc, 0x12);
+            log_to(&logger, "in request");
+            log_to(&default, "in request");
+            {
+                let _child = set_trace(0xabc, 0x34);
+                assert_eq!(trace().unwrap().span_id, 0x34);
+            }
+            assert_eq!(trace().unwrap().span_id, 0x12);
+        }
+        assert_eq!(trace(), None);
+        log_to(&logger, "after request");
+
+        let entries: Vec<_> = read_log(path, false).map(|e| e.unwrap()).collect();
+        assert_eq!(entries[0].fields["trace.id"], "2748");
+        assert_eq!(entries[0].fields["span.id"], "18");
+        assert!(!entries[0].fields.contains_key("trace_id"));
+        assert!(!entries[1].fields.contains_key("trace.id"));
+
+        let entry = read_log("tests/output/trace_context_default.log", false)
+            .next()
+            .unwrap()
+            .unwrap();
+        assert_eq!(entry.fields["trace_id"], "00000000000000000000000000000abc");
+        assert_eq!(entry.fields["span_id"], "0000000000000012");
+    }
+}
@@ -0,0 +1,259 @@
+use crate::{LogEntry, Sink};
+use std::{
+    io::{self, Write},
+    net::{SocketAddr, TcpStream, ToSocketAddrs},
+    sync::Mutex,
+};
+
+/// How records are delimited on the wire
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Framing {
+    /// each record is followed by `\n`
+    #[default]
+    NewlineDelimited,
+    /// each record is preceded by its length as a 4-byte big-endian integer
+    LengthPrefixed,
+}
+
+impl Framing {
+    /// frame a single record
+    pub fn encode(&self, payload: &[u8]) -> Vec<u8> {
+        let mut frame = Vec::with_capacity(payload.len() + 4);
+        match self {
+            Framing::NewlineDelimited => {
+                frame.extend_from_slice(payload);
+                frame.push(b'\n');
+            }
+            Framing::LengthPrefixed => {
+                frame.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+                frame.extend_from_slice(payload);
+            }
+        }
+        frame
+    }
+}
+
+/// A logger that sends JSON records to a TCP collector
+///
+/// The connection is opened on construction. If a write fails the logger
+/// reconnects once and retries before giving up on the record.
+pub struct TcpLogger {
+    target: String,
+    addr: SocketAddr,
+    framing: Framing,
+    stream: Mutex<Option<TcpStream>>,
+    gzip_batch: Option<usize>,
+    // framed records waiting to be compressed, and how many there are
+    pending: Mutex<(Vec<u8>, usize)>,
+}
+
+impl TcpLogger {
+    /// connect to a collector
+    /// # Arguments
+    /// * `target` - The target to match
+    /// * `addr` - The address of the collector
+    pub fn new<A: ToSocketAddrs>(target: &str, addr: A) -> io::Result<TcpLogger> {
+        let addr = addr.to_socket_addrs()?.next().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "no address to connect to")
+        })?;
+        Ok(TcpLogger {
+            target: target.to_string(),
+            addr,
+            framing: Framing::default(),
+            stream: Mutex::new(Some(TcpStream::connect(addr)?)),
+            gzip_batch: None,
+            pending: Mutex::new((Vec::new(), 0)),
+        })
+    }
+
+    /// set how records are delimited on the wire
+    pub fn framing(mut self, framing: Framing) -> TcpLogger {
+        self.framing = framing;
+        self
+    }
+
+    /// gzip the records in batches of `records` before sending them
+    ///
+    /// Each batch of framed records is sent as one gzip member, so the
+    /// receiver sees the gzip magic bytes `1f 8b` at the start of the stream
+    /// and can decompress everything it receives as a multi-member gzip
+    /// stream, e.g. with `flate2::read::MultiGzDecoder`, to get the framed
+    /// records back. A partial batch is sent on flush and when the logger is
+    /// dropped.
+    /// # Example
+    /// ```no_run
+    /// # use crate::loggers::*;
+    /// let logger = TcpLogger::new("api", "127.0.0.1:5170").unwrap().gzip_batch(100);
+    /// ```
+    pub fn gzip_batch(mut self, records: usize) -> TcpLogger {
+        self.gzip_batch = Some(records.max(1));
+        self
+    }
+
+    /// write `bytes` to the collector, reconnecting once if the write fails
+    fn send(&self, bytes: &[u8]) -> io::Result<()> {
+        let mut stream = self.stream.lock().unwrap();
+        if let Some(s) = stream.as_mut() {
+            if s.write_all(bytes).is_ok() {
+                return Ok(());
+            }
+        }
+        *stream = None;
+        let mut s = TcpStream::connect(self.addr)?;
+        s.write_all(bytes)?;
+        *stream = Some(s);
+        Ok(())
+    }
+
+    /// compress and send the pending batch, if any
+    fn send_batch(&self, pending: &mut (Vec<u8>, usize)) -> io::Result<()> {
+        if pending.1 == 0 {
+            return Ok(());
+        }
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(&pending.0)?;
+        let batch = encoder.finish()?;
+        pending.0.clear();
+        pending.1 = 0;
+        self.send(&batch)
+    }
+}
+
+impl Sink for TcpLogger {
+    fn write_line(&self, line: &str) -> io::Result<()> {
+        let frame = self.framing.encode(line.as_bytes());
+        let Some(batch) = self.gzip_batch else {
+            return self.send(&frame);
+        };
+        let mut pending = self.pending.lock().unwrap();
+        pending.0.extend_from_slice(&frame);
+        pending.1 += 1;
+        if pending.1 >= batch {
+            self.send_batch(&mut pending)?;
+        }
+        Ok(())
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        self.send_batch(&mut self.pending.lock().unwrap())?;
+        match self.stream.lock().unwrap().as_mut() {
+            Some(s) => s.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for TcpLogger {
+    fn drop(&mut self) {
+        if let Err(e) = Sink::flush(self) {
+            eprintln!(
+                "TcpLogger {}: cannot send records to {}: {}",
+                self.target, self.addr, e
+            );
+        }
+    }
+}
+
+impl log::Log for TcpLogger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        metadata.target() == self.target
+    }
+
+    fn log(&self, record: &log::Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        let timestamp = crate::clock::timestamp();
+        let line = serde_json::to_string(&LogEntry::from_record(record, &self.target, &timestamp))
+            .unwrap();
+        if let Err(e) = self.write_line(&line) {
+            eprintln!(
+                "TcpLogger {}: cannot send record to {}: {}",
+                self.target, self.addr, e
+            );
+        }
+    }
+
+    fn flush(&self) {
+        let _ = Sink::flush(self);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use log::Log;
+    use std::{io::Read, net::TcpListener};
+
+    #[test]
+    fn test_length_prefixed() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let logger = TcpLogger::new("tcp", listener.local_addr().unwrap())
+            .unwrap()
+            .framing(Framing::LengthPrefixed);
+        let (mut conn, _) = listener.accept().unwrap();
+
+        for message in ["first", "second line"] {
+            logger.log(
+                &log::Record::builder()
+                    .target("tcp")
+                    .level(log::Level::Info)
+                    .args(format_args!("{}", message))
+                    .build(),
+            );
+        }
+        drop(logger);
+
+        let mut received = Vec::new();
+        conn.read_to_end(&mut received).unwrap();
+        let mut messages = Vec::new();
+        let mut rest = &received[..];
+        while !rest.is_empty() {
+            let len = u32::from_be_bytes(rest[..4].try_into().unwrap()) as usize;
+            let entry: LogEntry = serde_json::from_slice(&rest[4..4 + len]).unwrap();
+            messages.push(entry.message);
+            rest = &rest[4 + len..];
+        }
+        assert_eq!(messages, vec!["first", "second line"]);
+    }
+
+    #[test]
+    fn test_gzip_batch() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let logger = TcpLogger::new("tcp_gzip", listener.local_addr().unwrap())
+            .unwrap()
+            .gzip_batch(2);
+        let (mut conn, _) = listener.accept().unwrap();
+
+        for message in ["first", "second", "third"] {
+            logger.log(
+                &log::Record::builder()
+                    .target("tcp_gzip")
+                    .level(log::Level::Info)
+                    .args(format_args!("{}", message))
+                    .build(),
+            );
+        }
+        drop(logger);
+
+        let mut received = Vec::new();
+        conn.read_to_end(&mut received).unwrap();
+        assert_eq!(received[..2], [0x1f, 0x8b]);
+        let mut decoded = String::new();
+        flate2::read::MultiGzDecoder::new(&received[..])
+            .read_to_string(&mut decoded)
+            .unwrap();
+        let messages: Vec<String> = decoded
+            .lines()
+            .map(|line| serde_json::from_str::<LogEntry>(line).unwrap().message)
+            .collect();
+        assert_eq!(messages, vec!["first", "second", "third"]);
+    }
+
+    #[test]
+    fn test_newline_delimited() {
+        assert_eq!(Framing::NewlineDelimited.encode(b"{}"), b"{}\n");
+        assert_eq!(Framing::LengthPrefixed.encode(b"{}"), b"\x00\x00\x00\x02{}");
+    }
+}
@@ -0,0 +1,104 @@
+use crate::CustomLogger;
+
+/// A logger that adds a layer in front of an existing `log::Log`
+///
+/// Every record is handed to the layer and then forwarded to the inner
+/// logger, so a logger from another crate keeps working while the layer,
+/// typically a [`CustomLogger`], also writes the record in this crate's
+/// JSON format with rotation. Each of them applies its own filtering.
+pub struct TeeLogger {
+    inner: Box<dyn log::Log>,
+    layer: Box<dyn log::Log>,
+}
+
+impl TeeLogger {
+    /// create a TeeLogger
+    /// # Arguments
+    /// * `inner` - The logger to forward every record to
+    /// * `layer` - The logger that also receives every record
+    /// # Example
+    /// ```
+    /// # use crate::loggers::*;
+    /// # let existing = RingBufferLogger::new("api", 100);
+    /// let logger = TeeLogger::new(
+    ///     Box::new(existing),
+    ///     Box::new(CustomLogger::new("api", "system.log").console(false)),
+    /// );
+    /// ```
+    pub fn new(inner: Box<dyn log::Log>, layer: Box<dyn log::Log>) -> TeeLogger {
+        TeeLogger { inner, layer }
+    }
+
+    /// tee every record the inner logger accepts to `filepath`, whatever its
+    /// target, without printing it
+    /// # Example
+    /// ```
+    /// # use crate::loggers::*;
+    /// # let existing = RingBufferLogger::new("api", 100);
+    /// let logger = TeeLogger::to_file(Box::new(existing), "system.log");
+    /// ```
+    pub fn to_file(inner: Box<dyn log::Log>, filepath: &str) -> TeeLogger {
+        let layer = CustomLogger::new("", filepath)
+            .any_target(true)
+            .console(false);
+        TeeLogger::new(inner, Box::new(layer))
+    }
+}
+
+impl log::Log for TeeLogger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        if crate::inspect::is_hook(metadata) {
+            crate::inspect::forward("TeeLogger layer", self.layer.as_ref());
+            crate::inspect::forward("TeeLogger inner", self.inner.as_ref());
+            return false;
+        }
+        self.layer.enabled(metadata) || self.inner.enabled(metadata)
+    }
+
+    fn log(&self, record: &log::Record) {
+        self.layer.log(record);
+        self.inner.log(record);
+    }
+
+    fn flush(&self) {
+        self.layer.flush();
+        self.inner.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{read_log, LogEntry, MemorySink, MultiSink};
+    use log::Log;
+
+    #[test]
+    fn test_tee_logger() {
+        let path = "tests/output/tee.log";
+        let _ = std::fs::remove_file(path);
+        let memory = MemorySink::new();
+        let mut inner = MultiSink::new("tee");
+        inner.add_sink(Box::new(memory.clone()));
+
+        let logger = TeeLogger::to_file(Box::new(inner), path);
+        for message in ["first", "second"] {
+            logger.log(
+                &log::Record::builder()
+                    .target("tee")
+                    .level(log::Level::Info)
+                    .args(format_args!("{}", message))
+                    .build(),
+            );
+        }
+        logger.flush();
+
+        let from_file: Vec<String> = read_log(path, false).map(|e| e.unwrap().message).collect();
+        assert_eq!(from_file, vec!["first", "second"]);
+        let from_memory: Vec<String> = memory
+            .lines()
+            .iter()
+            .map(|line| serde_json::from_str::<LogEntry>(line).unwrap().message)
+            .collect();
+        assert_eq!(from_memory, vec!["first", "second"]);
+    }
+}
@@ -0,0 +1,57 @@
+//! Capturing records in tests
+//!
+//! The global logger can only be installed once per process, so tests can
+//! not swap in a logger of their own. Instead, while [`capture`] runs its
+//! closure, an installed [`Logger`](crate::Logger) hands every record logged
+//! on the current thread to an in-memory buffer rather than to its loggers.
+//!
+//! ```
+//! use loggers::*;
+//!
+//! # let _ = Logger::new().install();
+//! let entries = testing::capture(|| {
+//!     log::warn!(target: "billing", "card declined");
+//! });
+//! assert_eq!(entries[0].message, "card declined");
+//! ```
+
+use crate::LogEntry;
+use std::cell::RefCell;
+
+thread_local! {
+    static CAPTURED: RefCell<Option<Vec<LogEntry>>> = const { RefCell::new(None) };
+}
+
+/// run `f`, returning the records logged on this thread while it ran
+///
+/// Records of every target and level are captured, and none of them reach
+/// the installed loggers. Captures may be nested; the innermost one
+/// receives the records.
+pub fn capture<F: FnOnce()>(f: F) -> Vec<LogEntry> {
+    struct Restore(Option<Vec<LogEntry>>);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            CAPTURED.with(|captured| *captured.borrow_mut() = self.0.take());
+        }
+    }
+
+    let outer = CAPTURED.with(|captured| captured.borrow_mut().replace(Vec::new()));
+    let restore = Restore(outer);
+    f();
+    let entries = CAPTURED.with(|captured| captured.borrow_mut().take());
+    drop(restore);
+    entries.unwrap_or_default()
+}
+
+/// keep `record` if a capture is active on this thread, returning whether it was kept
+pub(crate) fn intercept(record: &log::Record) -> bool {
+    CAPTURED.with(|captured| match captured.borrow_mut().as_mut() {
+        Some(entries) => {
+            let timestamp = crate::clock::timestamp();
+            entries.push(LogEntry::from_record(record, record.target(), &timestamp));
+            true
+        }
+        None => false,
+    })
+}
@@ -0,0 +1,213 @@
+use crate::LogEntry;
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+/// the number of distinct messages tracked before the counts start over
+const MAX_TRACKED: usize = 4096;
+
+/// how often the adaptive sampler measures the record rate and adjusts
+const ADAPTIVE_WINDOW: Duration = Duration::from_secs(1);
+
+/// Lets through the first of each run of `n` identical messages
+pub(crate) struct EveryN {
+    n: u64,
+    seen: Mutex<HashMap<(String, String), u64>>,
+}
+
+impl EveryN {
+    pub(crate) fn new(n: u64) -> EveryN {
+        EveryN {
+            n: n.max(1),
+            seen: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// whether `entry` should be written; periodic repeats get a `skipped` field
+    /// with the number of occurrences dropped since the previous one
+    pub(crate) fn admit(&self, entry: &mut LogEntry) -> bool {
+        let mut seen = self.seen.lock().unwrap();
+        let key = (entry.severity.clone(), entry.message.clone());
+        if !seen.contains_key(&key) && seen.len() >= MAX_TRACKED {
+            seen.clear();
+        }
+        let count = seen.entry(key).or_insert(0);
+        let index = *count;
+        *count += 1;
+        if !index.is_multiple_of(self.n) {
+            return false;
+        }
+        if index > 0 {
+            entry
+                .fields
+                .insert("skipped".to_string(), (self.n - 1).into());
+        }
+        true
+    }
+}
+
+/// Lets through an evenly spaced `rate` fraction of records
+pub(crate) struct Sampler {
+    rate: f64,
+    seen: AtomicU64,
+}
+
+impl Sampler {
+    pub(crate) fn new(rate: f64) -> Sampler {
+        Sampler {
+            rate: rate.clamp(0.0, 1.0),
+            seen: AtomicU64::new(0),
+        }
+    }
+
+    /// whether `entry` should be written; kept records get a `sample_rate` field
+    pub(crate) fn admit(&self, entry: &mut LogEntry) -> bool {
+        let n = self.seen.fetch_add(1, Ordering::Relaxed) as f64;
+        // keep the records at which the running total of `rate` crosses an integer
+        if ((n + 1.0) * self.rate).floor() <= (n * self.rate).floor() {
+            return false;
+        }
+        entry
+            .fields
+            .insert("sample_rate".to_string(), self.rate.into());
+        true
+    }
+}
+
+/// Keeps the records written to about `target` per second, sampling harder
+/// as the rate rises above it and relaxing as it falls
+pub(crate) struct AdaptiveSampler {
+    target: f64,
+    state: Mutex<AdaptiveState>,
+}
+
+struct AdaptiveState {
+    window_start: Instant,
+    // records seen and dropped in the current window
+    seen: u64,
+    dropped: u64,
+    // the fraction of records kept, and the running total of it that decides
+    // which records are kept
+    rate: f64,
+    credit: f64,
+}
+
+impl AdaptiveSampler {
+    pub(crate) fn new(target: u64) -> AdaptiveSampler {
+        AdaptiveSampler {
+            target: target.max(1) as f64,
+            state: Mutex::new(AdaptiveState {
+                window_start: Instant::now(),
+                seen: 0,
+                dropped: 0,
+                rate: 1.0,
+                credit: 0.0,
+            }),
+        }
+    }
+
+    /// whether `entry`, arriving at `now`, should be written; kept records get
+    /// a `sample_rate` field
+    ///
+    /// When a window with dropped records ends, also returns a summary record
+    /// to write first, with the window's `sample_rate`, `records` and
+    /// `dropped` counts.
+    pub(crate) fn admit(&self, entry: &mut LogEntry, now: Instant) -> (bool, Option<LogEntry>) {
+        let mut state = self.state.lock().unwrap();
+        let mut summary = None;
+        let elapsed = now.saturating_duration_since(state.window_start);
+        if elapsed >= ADAPTIVE_WINDOW {
+            if state.dropped > 0 {
+                summary = Some(self.summary(entry, &state));
+            }
+            let observed = state.seen as f64 / elapsed.as_secs_f64();
+            state.rate = (self.target / observed).min(1.0);
+            state.window_start = now;
+            state.seen = 0;
+            state.dropped = 0;
+        }
+
+        state.seen += 1;
+        state.credit += state.rate;
+        if state.credit < 1.0 {
+            state.dropped += 1;
+            return (false, summary);
+        }
+        state.credit -= 1.0;
+        entry
+            .fields
+            .insert("sample_rate".to_string(), state.rate.into());
+        (true, summary)
+    }
+
+    fn summary(&self, entry: &LogEntry, state: &AdaptiveState) -> LogEntry {
+        let mut fields = serde_json::Map::new();
+        fields.insert("sample_rate".to_string(), state.rate.into());
+        fields.insert("records".to_string(), state.seen.into());
+        fields.insert("dropped".to_string(), state.dropped.into());
+        LogEntry {
+            severity: log::Level::Info.to_string(),
+            timestamp: entry.timestamp.clone(),
+            target: entry.target.clone(),
+            message: "adaptive sampling dropped records".to_string(),
+            location: None,
+            fields,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry() -> LogEntry {
+        LogEntry {
+            severity: "INFO".to_string(),
+            timestamp: "2024-01-01T00:00:00.000Z".to_string(),
+            target: "adaptive".to_string(),
+            message: "request".to_string(),
+            location: None,
+            fields: serde_json::Map::new(),
+        }
+    }
+
+    #[test]
+    fn test_adaptive_sampler() {
+        let sampler = AdaptiveSampler::new(100);
+        let start = sampler.state.lock().unwrap().window_start;
+        // drive `count` records evenly through the second starting at `second`
+        let run = |second: u64, count: u64| {
+            let (mut kept, mut summaries) = (0, Vec::new());
+            for i in 0..count {
+                let at = start
+                    + Duration::from_secs(second)
+                    + Duration::from_secs(1) * i as u32 / count as u32;
+                let (admit, summary) = sampler.admit(&mut entry(), at);
+                kept += admit as u64;
+                summaries.extend(summary);
+            }
+            (kept, summaries)
+        };
+
+        // the first window has no measured rate yet, then 1000/s is cut to 100/s
+        assert_eq!(run(0, 1000).0, 1000);
+        let (kept, summaries) = run(1, 1000);
+        assert!((99..=101).contains(&kept), "kept {}", kept);
+        assert!(summaries.is_empty());
+
+        // the drops are summarized when the overloaded window ends, and the
+        // sampler relaxes once the rate falls back below the target
+        let (kept, summaries) = run(2, 50);
+        assert!(kept <= 6, "kept {}", kept);
+        assert_eq!(summaries.len(), 1);
+        assert_eq!(summaries[0].fields["records"], 1000);
+        assert!(summaries[0].fields["dropped"].as_u64().unwrap() >= 899);
+        assert_eq!(run(3, 50).0, 50);
+        assert_eq!(run(4, 50).0, 50);
+    }
+}
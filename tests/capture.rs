@@ -0,0 +1,36 @@
+use loggers::*;
+
+fn charge(amount: u32) -> Result<(), String> {
+    if amount > 100 {
+        log::warn!(target: "billing", amount = amount; "card declined");
+        return Err("declined".to_string());
+    }
+    log::info!(target: "billing", "charged {}", amount);
+    Ok(())
+}
+
+#[test]
+fn test_capture() {
+    let path = "tests/output/capture.log";
+    let mut logger = Logger::new();
+    logger.add_logger(Box::new(CustomLogger::new("billing", path)));
+    logger.install().expect("Failed to set logger");
+
+    let entries = testing::capture(|| {
+        charge(20).unwrap();
+        let inner = testing::capture(|| {
+            let _ = charge(500);
+        });
+        assert_eq!(inner.len(), 1);
+        assert_eq!(inner[0].severity, "WARN");
+        assert_eq!(inner[0].fields["amount"], 500);
+    });
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].target, "billing");
+    assert_eq!(entries[0].message, "charged 20");
+
+    // outside a capture records reach the installed loggers again
+    charge(30).unwrap();
+    let messages: Vec<String> = read_log(path, false).map(|e| e.unwrap().message).collect();
+    assert_eq!(messages, vec!["charged 30"]);
+}
@@ -0,0 +1,38 @@
+//! Helpers for tests that check what a logger prints by running themselves
+//! again in a child process
+
+// every test binary compiles its own copy and uses only some of them
+#![allow(dead_code)]
+
+use serde_json::Value;
+use std::process::{Command, Output};
+
+/// the value of `var` when this process is the child started by [`run_child`]
+pub fn child_value(var: &str) -> Option<String> {
+    std::env::var(var).ok()
+}
+
+/// run the test named `test` again in a child process with `var` set to
+/// `value`, capturing what it prints
+pub fn run_child(test: &str, var: &str, value: &str) -> Output {
+    run_child_with(test, &[(var, value)])
+}
+
+/// run the test named `test` again in a child process with each of `vars`
+/// set, capturing what it prints
+pub fn run_child_with(test: &str, vars: &[(&str, &str)]) -> Output {
+    let output = Command::new(std::env::current_exe().unwrap())
+        .args(["--exact", test, "--nocapture", "--test-threads=1"])
+        .envs(vars.iter().copied())
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    output
+}
+
+/// the first JSON object printed in `text`
+pub fn find_json(text: &str) -> Option<Value> {
+    text.lines()
+        // the harness may print the test name on the same line
+        .find_map(|line| serde_json::from_str(&line[line.find('{')?..]).ok())
+}
@@ -0,0 +1,32 @@
+mod common;
+
+use log::Log;
+use loggers::*;
+
+const CHILD: &str = "LOGGERS_CONSOLE_ONLY_CHILD";
+
+#[test]
+fn test_console_only() {
+    if common::child_value(CHILD).is_some() {
+        let logger = CustomLogger::console_only("console_only").console_format(ConsoleFormat::Json);
+        logger.log(
+            &log::Record::builder()
+                .target("console_only")
+                .level(log::Level::Info)
+                .args(format_args!("to stdout"))
+                .build(),
+        );
+        return;
+    }
+
+    let output = common::run_child("test_console_only", CHILD, "1");
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(!stdout.contains("Cannot open file"));
+    assert!(!stderr.contains("Cannot open file"));
+
+    let record = common::find_json(&stdout).expect("no JSON record on stdout");
+    assert_eq!(record["severity"], "INFO");
+    assert_eq!(record["target"], "console_only");
+    assert_eq!(record["message"], "to stdout");
+}
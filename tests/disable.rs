@@ -0,0 +1,23 @@
+use loggers::*;
+
+#[test]
+fn test_disable() {
+    let path = "tests/output/disable.log";
+    let mut logger = Logger::new();
+    logger.add_logger(Box::new(CustomLogger::new("disable", path)));
+    logger.install().expect("Failed to set logger");
+    let wait = || std::thread::sleep(DISABLE_RECHECK + std::time::Duration::from_millis(100));
+    let messages =
+        || -> Vec<String> { read_log(path, false).map(|e| e.unwrap().message).collect() };
+
+    log::info!(target: "disable", "before");
+    std::env::set_var(DISABLE_VAR, "1");
+    wait();
+    log::info!(target: "disable", "while disabled");
+    assert_eq!(messages(), vec!["before"]);
+
+    std::env::remove_var(DISABLE_VAR);
+    wait();
+    log::info!(target: "disable", "after");
+    assert_eq!(messages(), vec!["before", "after"]);
+}
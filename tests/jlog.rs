@@ -0,0 +1,28 @@
+use loggers::*;
+use serde_json::Value;
+
+#[test]
+fn test_jlog() {
+    let path = "tests/output/jlog.log";
+    let mut logger = Logger::new();
+    logger.add_logger(Box::new(CustomLogger::new("jlog", path)));
+    log::set_boxed_logger(Box::new(logger)).expect("Failed to set logger");
+    log::set_max_level(log::LevelFilter::Trace);
+
+    let id = 42;
+    jlog!(target: "jlog", level: Info, { "user" => id, "action" => "login" }, "user {} logged in", id);
+    jlog!(target: "jlog", level: Warn, {}, "no fields");
+
+    let contents = std::fs::read_to_string(path).unwrap();
+    let lines: Vec<Value> = contents
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(lines.len(), 2);
+    assert_eq!(lines[0]["severity"], "INFO");
+    assert_eq!(lines[0]["message"], "user 42 logged in");
+    assert_eq!(lines[0]["user"], 42);
+    assert_eq!(lines[0]["action"], "login");
+    assert_eq!(lines[1]["severity"], "WARN");
+    assert_eq!(lines[1]["message"], "no fields");
+}
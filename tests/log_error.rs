@@ -0,0 +1,42 @@
+use loggers::*;
+use serde_json::Value;
+use std::fmt;
+
+#[derive(Debug)]
+struct ConfigError(std::io::Error);
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "failed to load config")
+    }
+}
+
+impl std::error::Error for ConfigError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.0)
+    }
+}
+
+#[test]
+fn test_log_error() {
+    let path = "tests/output/log_error.log";
+    let mut logger = Logger::new();
+    logger.add_logger(Box::new(CustomLogger::new("log_error", path)));
+    log::set_boxed_logger(Box::new(logger)).expect("Failed to set logger");
+    log::set_max_level(log::LevelFilter::Trace);
+
+    let err = ConfigError(std::io::Error::new(
+        std::io::ErrorKind::NotFound,
+        "config.toml not found",
+    ));
+    log_error!(target: "log_error", &err);
+
+    let contents = std::fs::read_to_string(path).unwrap();
+    let v: Value = serde_json::from_str(contents.trim_end()).unwrap();
+    assert_eq!(v["severity"], "ERROR");
+    assert_eq!(v["message"], "failed to load config");
+    assert_eq!(
+        v["error_chain"],
+        serde_json::json!(["failed to load config", "config.toml not found"])
+    );
+}
@@ -0,0 +1,40 @@
+use loggers::*;
+use serde_json::Value;
+
+fn connect() {
+    log_fn!(target: "log_fn", level: Warn, "retrying in {}s", 5);
+    let retry = || log_fn!(target: "log_fn", level: Info, "from a closure");
+    retry();
+}
+
+#[test]
+fn test_log_fn() {
+    let path = "tests/output/log_fn.log";
+    let without = "tests/output/log_fn_without.log";
+    let mut logger = Logger::new();
+    logger.add_logger(Box::new(CustomLogger::new("log_fn", path).function(true)));
+    logger.add_logger(Box::new(CustomLogger::new("log_fn", without)));
+    logger.install().expect("Failed to set logger");
+
+    connect();
+
+    let read = |path| -> Vec<Value> {
+        std::fs::read_to_string(path)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    };
+    let lines = read(path);
+    assert_eq!(lines.len(), 2);
+    assert_eq!(lines[0]["message"], "retrying in 5s");
+    assert_eq!(lines[0]["function"], "log_fn::connect");
+    assert_eq!(lines[1]["function"], "log_fn::connect");
+
+    let lines = read(without);
+    assert_eq!(lines.len(), 2);
+    assert!(lines.iter().all(|line| line.get("function").is_none()));
+    assert!(lines
+        .iter()
+        .all(|line| line.get("loggers.function").is_none()));
+}
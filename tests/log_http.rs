@@ -0,0 +1,32 @@
+use loggers::*;
+use serde_json::Value;
+use std::time::Duration;
+
+#[test]
+fn test_log_http() {
+    let path = "tests/output/log_http.log";
+    let mut logger = Logger::new();
+    logger.add_logger(Box::new(CustomLogger::new("log_http", path)));
+    logger.install().expect("Failed to set logger");
+
+    log_http!(target: "log_http", "GET", "/users/42", 200, Duration::from_micros(12_500));
+    log_http!(target: "log_http", "POST", "/orders", 503, Duration::from_secs(2));
+
+    let contents = std::fs::read_to_string(path).unwrap();
+    let lines: Vec<Value> = contents
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(lines.len(), 2);
+    assert_eq!(lines[0]["severity"], "INFO");
+    assert_eq!(lines[0]["message"], "GET /users/42 200 12.5ms");
+    assert_eq!(lines[0]["method"], "GET");
+    assert_eq!(lines[0]["path"], "/users/42");
+    assert_eq!(lines[0]["status"], 200);
+    assert!(lines[0]["status"].is_u64());
+    assert_eq!(lines[0]["latency_ms"], 12.5);
+    assert!(lines[0]["latency_ms"].is_f64());
+    assert_eq!(lines[1]["severity"], "ERROR");
+    assert_eq!(lines[1]["status"], 503);
+    assert_eq!(lines[1]["latency_ms"], 2000.0);
+}
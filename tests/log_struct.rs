@@ -0,0 +1,46 @@
+use loggers::*;
+use serde::Serialize;
+use serde_json::Value;
+
+#[derive(Serialize)]
+struct Order {
+    id: u64,
+    total: f64,
+    paid: bool,
+    customer: Customer,
+}
+
+#[derive(Serialize)]
+struct Customer {
+    name: &'static str,
+}
+
+#[test]
+fn test_log_struct() {
+    let path = "tests/output/log_struct.log";
+    let mut logger = Logger::new();
+    logger.add_logger(Box::new(CustomLogger::new("log_struct", path)));
+    logger.install().expect("Failed to set logger");
+
+    let order = Order {
+        id: 7,
+        total: 12.5,
+        paid: true,
+        customer: Customer { name: "ferris" },
+    };
+    log_struct!(target: "log_struct", level: Info, &order, "order {} placed", order.id);
+    log_struct!(target: "log_struct", level: Warn, &vec![1, 2], "batch");
+
+    let contents = std::fs::read_to_string(path).unwrap();
+    let lines: Vec<Value> = contents
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(lines.len(), 2);
+    assert_eq!(lines[0]["message"], "order 7 placed");
+    assert_eq!(lines[0]["id"], 7);
+    assert_eq!(lines[0]["total"], 12.5);
+    assert_eq!(lines[0]["paid"], true);
+    assert_eq!(lines[0]["customer"]["name"], "ferris");
+    assert_eq!(lines[1]["value"], serde_json::json!([1, 2]));
+}
@@ -0,0 +1,29 @@
+use loggers::*;
+
+mod inner {
+    pub fn work() {
+        log::debug!("from inner");
+    }
+}
+
+#[test]
+fn test_match_module_default() {
+    let matching = "tests/output/module_default.log";
+    let exact = "tests/output/module_default_exact.log";
+    let mut logger = Logger::new();
+    logger.add_logger(Box::new(
+        CustomLogger::new("module_default", matching).match_module_default(true),
+    ));
+    logger.add_logger(Box::new(CustomLogger::new("module_default", exact)));
+    log::set_boxed_logger(Box::new(logger)).expect("Failed to set logger");
+    log::set_max_level(log::LevelFilter::Trace);
+
+    inner::work();
+    log::debug!(target: "module_default_other", "not a submodule");
+
+    let entries: Vec<LogEntry> = read_log(matching, false).map(|e| e.unwrap()).collect();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].message, "from inner");
+    assert_eq!(entries[0].severity, "DEBUG");
+    assert_eq!(read_log(exact, false).count(), 0);
+}
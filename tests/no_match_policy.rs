@@ -0,0 +1,41 @@
+mod common;
+
+use loggers::*;
+
+const CHILD: &str = "LOGGERS_NO_MATCH_POLICY_CHILD";
+
+/// the stderr of a child process logging an unmatched record under `policy`
+fn stderr_with(policy: &str) -> String {
+    let output = common::run_child("test_no_match_policy", CHILD, policy);
+    String::from_utf8(output.stderr).unwrap()
+}
+
+#[test]
+fn test_no_match_policy() {
+    if let Some(policy) = common::child_value(CHILD) {
+        let mut logger = Logger::new();
+        logger.add_logger(Box::new(CustomLogger::new(
+            "app",
+            "tests/output/no_match_policy.log",
+        )));
+        logger.set_no_match_policy(match policy.as_str() {
+            "silent" => NoMatchPolicy::Silent,
+            "warn" => NoMatchPolicy::Warn,
+            _ => NoMatchPolicy::Stderr,
+        });
+        logger.log_record(log::Level::Info, "unknown", "unmatched record", &[]);
+        return;
+    }
+
+    let silent = stderr_with("silent");
+    assert!(!silent.contains("unknown"));
+
+    let warn = stderr_with("warn");
+    assert!(warn.contains("No logger matched target \"unknown\""));
+    assert!(!warn.contains("unmatched record"));
+
+    let stderr = stderr_with("stderr");
+    let record = common::find_json(&stderr).expect("no JSON record on stderr");
+    assert_eq!(record["target"], "unknown");
+    assert_eq!(record["message"], "unmatched record");
+}
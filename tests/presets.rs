@@ -0,0 +1,42 @@
+mod common;
+
+use loggers::*;
+
+const CHILD: &str = "LOGGERS_DEV_PRESET_CHILD";
+
+#[test]
+fn test_dev_preset() {
+    if common::child_value(CHILD).is_some() {
+        Logger::dev("dev_preset").expect("Failed to set logger");
+        log::debug!(target: "dev_preset::db", user = "ferris"; "connected");
+        return;
+    }
+
+    let output =
+        common::run_child_with("test_dev_preset", &[(CHILD, "1"), ("CLICOLOR_FORCE", "1")]);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let line = stdout
+        .lines()
+        .position(|l| l.contains(" - connected"))
+        .expect("no record on stdout");
+    let lines: Vec<&str> = stdout.lines().skip(line).take(3).collect();
+    assert!(lines[0].contains("\x1b[34m[DEBUG]\x1b[0m dev_preset::db "));
+    assert!(lines[1].starts_with("    at tests/presets.rs:"));
+    assert_eq!(lines[2], "    user: ferris");
+}
+
+#[test]
+fn test_prod_preset() {
+    let path = "tests/output/prod_preset.log";
+    let _ = std::fs::remove_file(path);
+    Logger::prod("prod_preset", path).expect("Failed to set logger");
+    log::debug!(target: "prod_preset", "dropped");
+    log::info!(target: "prod_preset::api", "kept");
+    assert!(Logger::dev("prod_preset").is_err());
+    Logger::reset();
+
+    let entries: Vec<LogEntry> = read_log(path, false).map(|e| e.unwrap()).collect();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].severity, "INFO");
+    assert_eq!(entries[0].message, "kept");
+}
@@ -0,0 +1,26 @@
+use loggers::*;
+
+fn messages(path: &str) -> Vec<String> {
+    read_log(path, false).map(|e| e.unwrap().message).collect()
+}
+
+#[test]
+fn test_reset() {
+    let (old, new) = ("tests/output/reset_old.log", "tests/output/reset_new.log");
+    let mut logger = Logger::new();
+    logger.add_logger(Box::new(CustomLogger::new("reset", old).console(false)));
+    logger.install().expect("Failed to set logger");
+    log::info!(target: "reset", "before reset");
+    assert!(Logger::new().install().is_err());
+
+    Logger::reset();
+    log::info!(target: "reset", "while reset");
+
+    let mut logger = Logger::new();
+    logger.add_logger(Box::new(CustomLogger::new("reset", new).console(false)));
+    logger.install().expect("Failed to set logger");
+    log::info!(target: "reset", "after reset");
+
+    assert_eq!(messages(old), vec!["before reset"]);
+    assert_eq!(messages(new), vec!["after reset"]);
+}
@@ -0,0 +1,20 @@
+use log::Log;
+use loggers::*;
+
+#[test]
+fn test_dump_on_panic() {
+    let path = "tests/output/ring_panic.log";
+    let _ = std::fs::remove_file(path);
+    let logger = RingBufferLogger::new("ring_panic", 10).dump_on_panic(path);
+    logger.log(
+        &log::Record::builder()
+            .target("ring_panic")
+            .level(log::Level::Error)
+            .args(format_args!("about to fail"))
+            .build(),
+    );
+
+    assert!(std::panic::catch_unwind(|| panic!("boom")).is_err());
+    let messages: Vec<String> = read_log(path, false).map(|e| e.unwrap().message).collect();
+    assert_eq!(messages, vec!["about to fail"]);
+}
@@ -0,0 +1,26 @@
+use loggers::*;
+
+#[test]
+fn test_shutdown() {
+    let path = "tests/output/shutdown.log";
+    let _ = std::fs::remove_file(path);
+
+    let mut logger = Logger::new();
+    logger.add_logger(Box::new(AsyncLogger::new(
+        "shutdown",
+        Box::new(FileSink::new(path).unwrap()),
+    )));
+    logger.install().expect("Failed to set logger");
+
+    for i in 0..1000 {
+        log::info!(target: "shutdown", "record {}", i);
+    }
+    shutdown();
+
+    let messages: Vec<String> = read_log(path, false).map(|e| e.unwrap().message).collect();
+    assert_eq!(messages.len(), 1000);
+    assert_eq!(messages[999], "record 999");
+
+    log::info!(target: "shutdown", "after shutdown");
+    assert_eq!(read_log(path, false).count(), 1001);
+}
@@ -0,0 +1,42 @@
+mod common;
+
+use loggers::*;
+
+const CHILD: &str = "LOGGERS_SILENT_FALLBACK_CHILD";
+const PATH: &str = "tests/output/silent_fallback.log";
+
+/// logs one matched and one unmatched record through a silent fallback
+fn child() {
+    let mut logger = Logger::new();
+    logger.add_logger(Box::new(CustomLogger::new(
+        "app",
+        "tests/output/silent_fallback_app.log",
+    )));
+    logger.set_fallback(Box::new(
+        CustomLogger::new("default", PATH)
+            .any_target(true)
+            .console(false),
+    ));
+    logger.install().expect("Failed to set logger");
+
+    log::info!(target: "app", "matched record");
+    log::info!(target: "unknown", "unmatched record");
+    log::logger().flush();
+}
+
+#[test]
+fn test_silent_fallback() {
+    if common::child_value(CHILD).is_some() {
+        return child();
+    }
+
+    let output = common::run_child("test_silent_fallback", CHILD, "1");
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("matched record"));
+    assert!(!stdout.contains("unmatched record"));
+
+    let entries: Vec<_> = read_log(PATH, false).map(|e| e.unwrap()).collect();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].target, "unknown");
+    assert_eq!(entries[0].message, "unmatched record");
+}
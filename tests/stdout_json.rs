@@ -0,0 +1,38 @@
+mod common;
+
+use log::Log;
+use loggers::*;
+use serde_json::Value;
+
+const CHILD: &str = "LOGGERS_STDOUT_JSON_CHILD";
+const PATH: &str = "tests/output/stdout_json.log";
+
+#[test]
+fn test_stdout_json() {
+    if common::child_value(CHILD).is_some() {
+        let logger = CustomLogger::new("stdout_json", PATH)
+            .format(Format::Gelf)
+            .console_format(ConsoleFormat::Json);
+        logger.log(
+            &log::Record::builder()
+                .target("stdout_json")
+                .level(log::Level::Warn)
+                .args(format_args!("for the collector"))
+                .build(),
+        );
+        return;
+    }
+
+    let output = common::run_child("test_stdout_json", CHILD, "1");
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(!stdout.contains("[WARN]"));
+
+    let record = common::find_json(&stdout).expect("no JSON record on stdout");
+    assert_eq!(record["severity"], "WARN");
+    assert_eq!(record["message"], "for the collector");
+
+    // the file keeps its own format
+    let file: Value =
+        serde_json::from_str(std::fs::read_to_string(PATH).unwrap().trim_end()).unwrap();
+    assert_eq!(file["short_message"], "for the collector");
+}
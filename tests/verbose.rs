@@ -0,0 +1,24 @@
+use loggers::*;
+
+fn parse() -> usize {
+    let items = 3;
+    trace_dev!(target: "verbose", "tokenizing");
+    debug_dev!(target: "verbose", "parsed {} items", items);
+    items
+}
+
+#[test]
+fn test_verbose_macros() {
+    let path = "tests/output/verbose.log";
+    let mut logger = Logger::new();
+    logger.add_logger(Box::new(CustomLogger::new("verbose", path)));
+    logger.install().expect("Failed to set logger");
+
+    assert_eq!(parse(), 3);
+    let messages: Vec<String> = read_log(path, false).map(|e| e.unwrap().message).collect();
+    if cfg!(feature = "verbose") {
+        assert_eq!(messages, vec!["tokenizing", "parsed 3 items"]);
+    } else {
+        assert!(messages.is_empty());
+    }
+}